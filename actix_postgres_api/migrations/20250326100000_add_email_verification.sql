@@ -0,0 +1,25 @@
+-- Add email_verified_at column to users table
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE;
+
+-- Accounts created before verification existed are treated as verified,
+-- otherwise enabling REQUIRE_EMAIL_VERIFICATION would lock all of them out
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
+
+COMMENT ON COLUMN users.email_verified_at IS 'Time the user confirmed ownership of the email address (NULL = unverified)';
+
+-- Create email_verification_tokens table
+-- Tokens are single-use and expire; only the SHA-256 digest of the token is stored.
+CREATE TABLE email_verification_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Add index for better query performance
+CREATE INDEX idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
+
+-- Add comment for the table
+COMMENT ON TABLE email_verification_tokens IS 'Hashed single-use email verification tokens';
@@ -0,0 +1,29 @@
+-- Create user_totp table
+-- One TOTP secret per user; enabled_at stays NULL until the user confirms enrollment with a valid code.
+-- last_used_step stores the last accepted RFC 6238 time step so a code cannot be used twice.
+CREATE TABLE user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    secret VARCHAR(64) NOT NULL,
+    enabled_at TIMESTAMP WITH TIME ZONE,
+    last_used_step BIGINT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON TABLE user_totp IS 'TOTP (RFC 6238) two-factor authentication secrets';
+
+-- Create totp_recovery_codes table
+-- Recovery codes are single-use; only the SHA-256 digest of each code is stored.
+CREATE TABLE totp_recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, code_hash)
+);
+
+-- Add index for better query performance
+CREATE INDEX idx_totp_recovery_codes_user_id ON totp_recovery_codes(user_id);
+
+COMMENT ON TABLE totp_recovery_codes IS 'Hashed single-use two-factor recovery codes';
@@ -0,0 +1,24 @@
+-- Add brute-force protection columns to users table
+-- failed_login_count counts consecutive failed logins; it is reset after a successful login.
+-- locked_until is set once the count reaches the threshold and grows exponentially with further failures.
+ALTER TABLE users ADD COLUMN failed_login_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN locked_until TIMESTAMP WITH TIME ZONE;
+
+COMMENT ON COLUMN users.failed_login_count IS 'Consecutive failed login attempts';
+COMMENT ON COLUMN users.locked_until IS 'Login is refused until this time (NULL = not locked)';
+
+-- Create login_attempts table
+-- Every password login attempt is recorded so failures can be limited per IP address as well.
+CREATE TABLE login_attempts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    email VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45),
+    user_agent TEXT,
+    succeeded BOOLEAN NOT NULL,
+    attempted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Add index for better query performance
+CREATE INDEX idx_login_attempts_ip_attempted_at ON login_attempts(ip_address, attempted_at);
+
+COMMENT ON TABLE login_attempts IS 'Password login attempts used for per-IP rate limiting and auditing';
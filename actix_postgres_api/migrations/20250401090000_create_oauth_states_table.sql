@@ -0,0 +1,16 @@
+-- Create oauth_states table
+-- One row per started OAuth login. The id is carried in the signed `state` parameter,
+-- the PKCE code verifier never leaves the server. Rows are single-use and expire quickly.
+CREATE TABLE oauth_states (
+    id UUID PRIMARY KEY,
+    provider VARCHAR(50) NOT NULL,
+    pkce_verifier VARCHAR(128) NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Add index for cleanup of expired rows
+CREATE INDEX idx_oauth_states_expires_at ON oauth_states(expires_at);
+
+COMMENT ON TABLE oauth_states IS 'Pending OAuth authorization requests (state + PKCE verifier)';
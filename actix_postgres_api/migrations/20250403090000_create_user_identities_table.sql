@@ -0,0 +1,21 @@
+-- Create user_identities table
+-- Links external OAuth accounts to local users by the provider's stable user ID instead of email.
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    provider_user_id VARCHAR(255) NOT NULL,
+    email VARCHAR(255),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_login_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (provider, provider_user_id),
+    UNIQUE (user_id, provider)
+);
+
+-- Add index for listing identities of a user
+CREATE INDEX idx_user_identities_user_id ON user_identities(user_id);
+
+-- A started OAuth flow may link an account to a logged-in user instead of logging in
+ALTER TABLE oauth_states ADD COLUMN link_user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+COMMENT ON TABLE user_identities IS 'External OAuth accounts linked to users';
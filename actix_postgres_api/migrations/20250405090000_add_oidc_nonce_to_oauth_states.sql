@@ -0,0 +1,2 @@
+-- OpenID Connect flows bind the ID token to the authorization request with a nonce
+ALTER TABLE oauth_states ADD COLUMN nonce VARCHAR(64);
@@ -0,0 +1,82 @@
+-- Hierarchical roles with a role-to-permission mapping managed by admins.
+-- A role inherits all permissions of its parent role (admin > trainer > client).
+CREATE TABLE roles (
+    name VARCHAR(50) PRIMARY KEY,
+    description TEXT,
+    parent_role VARCHAR(50) REFERENCES roles(name) ON DELETE SET NULL,
+    is_system BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Permissions are defined by the application code; admins only map them to roles.
+-- Names follow `resource:action[:scope]`; the `any` scope implies `own`.
+CREATE TABLE permissions (
+    name VARCHAR(100) PRIMARY KEY,
+    description TEXT NOT NULL
+);
+
+CREATE TABLE role_permissions (
+    role_name VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission_name VARCHAR(100) NOT NULL REFERENCES permissions(name) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (role_name, permission_name)
+);
+
+INSERT INTO roles (name, description, parent_role, is_system) VALUES
+    ('client', 'Gym client/member', NULL, TRUE),
+    ('trainer', 'Gym trainer/coach', 'client', TRUE),
+    ('admin', 'Administrator', 'trainer', TRUE);
+
+INSERT INTO permissions (name, description) VALUES
+    ('users:read:own', 'View own profile'),
+    ('users:read:any', 'View and list all users'),
+    ('users:write:own', 'Update own profile'),
+    ('users:write:any', 'Update any user'),
+    ('users:delete:any', 'Delete users'),
+    ('users:role:assign', 'Change the role of a user'),
+    ('statistics:read', 'View user statistics'),
+    ('appointments:create', 'Book appointments as a client'),
+    ('appointments:read:own', 'View own appointments'),
+    ('appointments:read:any', 'View all appointments'),
+    ('appointments:write:own', 'Update own appointments'),
+    ('appointments:write:any', 'Update any appointment'),
+    ('appointments:complete:own', 'Mark own training sessions as completed'),
+    ('appointments:complete:any', 'Mark any appointment as completed'),
+    ('appointments:delete:own', 'Cancel own bookings'),
+    ('appointments:delete:any', 'Delete any appointment'),
+    ('chat:read', 'Read chat rooms and messages'),
+    ('chat:write', 'Create chat rooms'),
+    ('two_factor:enroll', 'Enable two-factor authentication'),
+    ('roles:manage', 'Manage roles and their permissions');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('client', 'users:read:own'),
+    ('client', 'users:write:own'),
+    ('client', 'appointments:create'),
+    ('client', 'appointments:read:own'),
+    ('client', 'appointments:write:own'),
+    ('client', 'appointments:delete:own'),
+    ('client', 'chat:read'),
+    ('client', 'chat:write'),
+    ('trainer', 'appointments:complete:own'),
+    ('trainer', 'two_factor:enroll'),
+    ('admin', 'users:read:any'),
+    ('admin', 'users:write:any'),
+    ('admin', 'users:delete:any'),
+    ('admin', 'users:role:assign'),
+    ('admin', 'statistics:read'),
+    ('admin', 'appointments:read:any'),
+    ('admin', 'appointments:write:any'),
+    ('admin', 'appointments:complete:any'),
+    ('admin', 'appointments:delete:any'),
+    ('admin', 'roles:manage');
+
+-- The fixed list of roles is replaced by a reference to the roles table
+ALTER TABLE users DROP CONSTRAINT check_valid_role;
+ALTER TABLE users ALTER COLUMN role TYPE VARCHAR(50);
+ALTER TABLE users ADD CONSTRAINT fk_users_role FOREIGN KEY (role) REFERENCES roles(name);
+
+COMMENT ON COLUMN users.role IS 'Rola użytkownika - nazwa z tabeli roles';
+COMMENT ON TABLE roles IS 'User roles; parent_role forms the permission inheritance hierarchy';
+COMMENT ON TABLE role_permissions IS 'Permissions granted directly to a role (without inherited ones)';
@@ -0,0 +1,6 @@
+-- Provisioning accounts on behalf of other people (POST /api/users); public signup needs no permission
+INSERT INTO permissions (name, description) VALUES
+    ('users:create', 'Create user accounts on behalf of other people');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('admin', 'users:create');
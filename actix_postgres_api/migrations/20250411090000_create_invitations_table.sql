@@ -0,0 +1,34 @@
+-- Create invitations table
+-- Invitations let admins and trainers onboard people with a pre-assigned role.
+-- Only the SHA-256 hash of the token sent by email is stored.
+CREATE TABLE invitations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    accepted_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Add indexes for pending invitations of an email and for listing by inviter
+CREATE INDEX idx_invitations_email ON invitations(email);
+CREATE INDEX idx_invitations_invited_by ON invitations(invited_by);
+
+-- Trainers invite clients and manage their own invitations; admins manage all of them
+INSERT INTO permissions (name, description) VALUES
+    ('invitations:create', 'Invite people to create an account'),
+    ('invitations:read:own', 'List invitations sent by oneself'),
+    ('invitations:read:any', 'List all invitations'),
+    ('invitations:revoke:own', 'Revoke invitations sent by oneself'),
+    ('invitations:revoke:any', 'Revoke any invitation');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('trainer', 'invitations:create'),
+    ('trainer', 'invitations:read:own'),
+    ('trainer', 'invitations:revoke:own'),
+    ('admin', 'invitations:read:any'),
+    ('admin', 'invitations:revoke:any');
@@ -0,0 +1,25 @@
+-- Create api_keys table
+-- Keys authenticate integrations (kiosks, reporting scripts) on behalf of their owner.
+-- A key can only use its scopes, and only those the owner's role still grants.
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash VARCHAR(64) NOT NULL UNIQUE,
+    scopes TEXT[] NOT NULL DEFAULT '{}',
+    expires_at TIMESTAMP WITH TIME ZONE,
+    last_used_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Add index for listing keys of a user
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
+
+INSERT INTO permissions (name, description) VALUES
+    ('api_keys:manage', 'Issue and revoke API keys for any user');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('admin', 'api_keys:manage');
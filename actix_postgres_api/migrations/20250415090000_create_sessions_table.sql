@@ -0,0 +1,19 @@
+-- Create sessions table
+-- Every login starts a session; its id is embedded in access tokens (`sid` claim)
+-- and doubles as the family id of the session's refresh tokens.
+CREATE TABLE sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    user_agent TEXT,
+    ip_address VARCHAR(45),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_active_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE
+);
+
+-- Add index for listing sessions of a user
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
+
+COMMENT ON TABLE sessions IS 'Login sessions (devices) of users; revoking a session invalidates its tokens';
+COMMENT ON COLUMN sessions.expires_at IS 'Expiry of the latest refresh token, extended on every refresh';
@@ -0,0 +1,28 @@
+-- Create impersonation_log table
+-- Admins can act as another user with a short-lived impersonation token. Starting an
+-- impersonation and every request made with such a token is recorded here with both identities.
+CREATE TABLE impersonation_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    event VARCHAR(20) NOT NULL,
+    method VARCHAR(10),
+    path TEXT,
+    status_code INTEGER,
+    ip_address VARCHAR(45),
+    user_agent TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT impersonation_log_event_check CHECK (event IN ('started', 'request'))
+);
+
+-- Add indexes for filtering by either identity
+CREATE INDEX idx_impersonation_log_actor_id ON impersonation_log(actor_id, created_at);
+CREATE INDEX idx_impersonation_log_user_id ON impersonation_log(user_id, created_at);
+
+INSERT INTO permissions (name, description) VALUES
+    ('users:impersonate', 'Act as another user with a time-limited impersonation token'),
+    ('audit:read', 'Read audit logs');
+
+INSERT INTO role_permissions (role_name, permission_name) VALUES
+    ('admin', 'users:impersonate'),
+    ('admin', 'audit:read');
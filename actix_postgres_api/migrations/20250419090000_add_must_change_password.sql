@@ -0,0 +1,10 @@
+-- Accounts that must set a new password before they get a regular access token
+ALTER TABLE users ADD COLUMN must_change_password BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Rows still using the shared default password from the initial migration (ChangeMe123)
+UPDATE users
+SET must_change_password = TRUE
+WHERE password_hash = '$2a$12$k8Y6Nt5zfQXmGO9VvQH2CehxfMY0lPuqJxzAkrxoHSJRZz8obzg4W';
+
+-- New rows must always provide their own hash
+ALTER TABLE users ALTER COLUMN password_hash DROP DEFAULT;
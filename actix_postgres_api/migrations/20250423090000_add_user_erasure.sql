@@ -0,0 +1,6 @@
+-- Right to erasure: the account is anonymized once the grace period has passed.
+-- Until then the user can cancel the request.
+ALTER TABLE users ADD COLUMN erasure_scheduled_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN erased_at TIMESTAMPTZ;
+
+CREATE INDEX idx_users_erasure_scheduled_at ON users(erasure_scheduled_at) WHERE erasure_scheduled_at IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Soft delete: deleted accounts are hidden from every query and purged after a retention period.
+-- Deleted rows keep their email and username, so they cannot be reused before the purge.
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_users_deleted_at ON users(deleted_at) WHERE deleted_at IS NOT NULL;
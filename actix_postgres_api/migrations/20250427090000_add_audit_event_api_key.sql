@@ -0,0 +1,2 @@
+-- API key used for the request (null for logins and bearer tokens)
+ALTER TABLE audit_events ADD COLUMN api_key_id UUID;
@@ -0,0 +1,2 @@
+-- Chat and statistics endpoints do not check permissions, so these are not granted anymore
+DELETE FROM permissions WHERE name IN ('statistics:read', 'chat:read', 'chat:write');
@@ -0,0 +1,110 @@
+use std::env;
+
+use crate::error::AppError;
+
+// Kto może samodzielnie założyć konto (rejestracja i pierwsze logowanie przez OAuth)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignupPolicy {
+    Open,        // anyone can register as a client
+    InviteOnly,  // accounts are created only from invitations
+    Disabled,    // accounts are created only by admins
+}
+
+impl SignupPolicy {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "open" => Some(SignupPolicy::Open),
+            "invite_only" | "invite-only" => Some(SignupPolicy::InviteOnly),
+            "disabled" => Some(SignupPolicy::Disabled),
+            _ => None,
+        }
+    }
+}
+
+// Nieznana wartość zamyka rejestrację - literówka nie może jej otworzyć
+impl From<&str> for SignupPolicy {
+    fn from(s: &str) -> Self {
+        SignupPolicy::parse(s).unwrap_or(SignupPolicy::Disabled)
+    }
+}
+
+// Account lifecycle settings: lifetimes of single-use tokens sent by email and activation rules
+#[derive(Debug, Clone)]
+pub struct AccountConfig {
+    pub password_reset_expiration: i64,     // in seconds
+    pub email_verification_expiration: i64, // in seconds
+    pub require_email_verification: bool,   // reject logins of accounts with unverified email
+    pub invitation_expiration: i64,         // in seconds
+    pub password_change_token_expiration: i64, // in seconds
+    pub signup_policy: SignupPolicy,
+    pub erasure_grace_period: i64,          // in seconds, between an erasure request and the anonymization
+    pub erasure_check_interval: u64,        // in seconds, how often due erasures are processed
+    pub deleted_user_retention: i64,        // in seconds, how long soft-deleted accounts can be restored
+    pub purge_check_interval: u64,          // in seconds, how often expired accounts are purged
+}
+
+impl AccountConfig {
+    pub fn from_env() -> Self {
+        Self {
+            password_reset_expiration: env::var("PASSWORD_RESET_EXPIRATION")
+                .unwrap_or_else(|_| "3600".to_string()) // 1 hour by default
+                .parse()
+                .unwrap_or(3600),
+            email_verification_expiration: env::var("EMAIL_VERIFICATION_EXPIRATION")
+                .unwrap_or_else(|_| "172800".to_string()) // 48 hours by default
+                .parse()
+                .unwrap_or(172800),
+            require_email_verification: env::var("REQUIRE_EMAIL_VERIFICATION")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            invitation_expiration: env::var("INVITATION_EXPIRATION")
+                .unwrap_or_else(|_| "604800".to_string()) // 7 days by default
+                .parse()
+                .unwrap_or(604800),
+            password_change_token_expiration: env::var("PASSWORD_CHANGE_TOKEN_EXPIRATION")
+                .unwrap_or_else(|_| "600".to_string()) // 10 minutes by default
+                .parse()
+                .unwrap_or(600),
+            signup_policy: SignupPolicy::from(
+                env::var("SIGNUP_POLICY").unwrap_or_else(|_| "open".to_string()).as_str()
+            ),
+            erasure_grace_period: env::var("ERASURE_GRACE_PERIOD")
+                .unwrap_or_else(|_| "2592000".to_string()) // 30 days by default
+                .parse()
+                .unwrap_or(2592000),
+            erasure_check_interval: env::var("ERASURE_CHECK_INTERVAL")
+                .unwrap_or_else(|_| "3600".to_string()) // 1 hour by default
+                .parse()
+                .unwrap_or(3600),
+            deleted_user_retention: env::var("DELETED_USER_RETENTION")
+                .unwrap_or_else(|_| "2592000".to_string()) // 30 days by default
+                .parse()
+                .unwrap_or(2592000),
+            purge_check_interval: env::var("PURGE_CHECK_INTERVAL")
+                .unwrap_or_else(|_| "3600".to_string()) // 1 hour by default
+                .parse()
+                .unwrap_or(3600),
+        }
+    }
+
+    // Public signup is allowed only with the open policy
+    pub fn ensure_signup_open(&self) -> Result<(), AppError> {
+        match self.signup_policy {
+            SignupPolicy::Open => Ok(()),
+            SignupPolicy::InviteOnly => Err(AppError::Forbidden("Registration is by invitation only".to_string())),
+            SignupPolicy::Disabled => Err(AppError::Forbidden("Registration is disabled".to_string())),
+        }
+    }
+}
+
+// Sprawdzenie SIGNUP_POLICY przy starcie aplikacji - błąd oznacza, że serwer nie powinien wystartować
+pub fn validate_account_configuration() -> Result<(), AppError> {
+    match env::var("SIGNUP_POLICY") {
+        Ok(value) if SignupPolicy::parse(&value).is_none() => Err(AppError::InternalServerError(format!(
+            "Unsupported SIGNUP_POLICY '{}' - use open, invite_only or disabled",
+            value
+        ))),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,29 @@
+use std::env;
+use actix_web::http::Method;
+
+// Ustawienia impersonacji - administrator działa jako inny użytkownik, np. żeby odtworzyć zgłoszony problem
+#[derive(Debug, Clone)]
+pub struct ImpersonationConfig {
+    pub expiration: i64,    // lifetime of an impersonation token in seconds
+    pub allow_writes: bool, // allow mutating requests (POST, PUT, PATCH, DELETE) with the token
+}
+
+impl ImpersonationConfig {
+    pub fn from_env() -> Self {
+        Self {
+            expiration: env::var("IMPERSONATION_EXPIRATION")
+                .unwrap_or_else(|_| "900".to_string()) // 15 minutes by default
+                .parse()
+                .unwrap_or(900),
+            allow_writes: env::var("IMPERSONATION_ALLOW_WRITES")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Whether a request with this method can change data
+pub fn is_mutating(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
@@ -1,9 +1,15 @@
-use jsonwebtoken::{encode, decode, Header, Validation, EncodingKey, DecodingKey};
-use serde::{Deserialize, Serialize};
-use chrono::{Utc, Duration};
+use jsonwebtoken::{encode, decode, decode_header, Algorithm, Validation};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use chrono::{DateTime, Utc, Duration};
 use sqlx::types::Uuid;
 use std::env;
+use crate::auth_utils::keys::jwt_keys;
+use crate::auth_utils::sessions;
 use crate::error::AppError;
+use crate::models::User;
+
+// Sekret używany, gdy JWT_SECRET nie jest ustawiony - dopuszczalny tylko w środowisku deweloperskim
+const FALLBACK_SECRET: &str = "default_secret_change_in_production";
 
 // Struktura przechowująca dane w tokenie JWT
 #[derive(Debug, Serialize, Deserialize)]
@@ -12,30 +18,195 @@ pub struct Claims {
     pub name: String,        // User's name
     pub email: String,       // User's email
     pub role: String,        // User's role
+    pub sid: String,         // Session ID
+    pub exp: i64,            // Expiration time (Unix timestamp)
+    pub iat: i64,            // Issued at (Unix timestamp)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,  // Admin acting as the user (impersonation tokens only)
+}
+
+// Claim `act` (RFC 8693) - tożsamość administratora, który działa w imieniu użytkownika `sub`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String,         // Actor's user ID
+}
+
+// Token wyzwania wydawany po poprawnym haśle, gdy konto ma włączone 2FA.
+// Ma własne `aud` i inny zestaw pól, więc verify_token go odrzuca - nie może posłużyć jako token dostępu.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChallengeClaims {
+    pub sub: String,         // Subject (user ID)
+    pub aud: String,         // Always TWO_FACTOR_CHALLENGE_AUDIENCE
+    pub exp: i64,            // Expiration time (Unix timestamp)
+    pub iat: i64,            // Issued at (Unix timestamp)
+}
+
+const TWO_FACTOR_CHALLENGE_AUDIENCE: &str = "2fa-challenge";
+
+// Ograniczony token wydawany przy logowaniu konta z flagą must_change_password.
+// Przyjmuje go wyłącznie /api/auth/change-password; verify_token go odrzuca (inne `aud`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasswordChangeClaims {
+    pub sub: String,         // Subject (user ID)
+    pub aud: String,         // Always PASSWORD_CHANGE_AUDIENCE
     pub exp: i64,            // Expiration time (Unix timestamp)
     pub iat: i64,            // Issued at (Unix timestamp)
 }
 
+const PASSWORD_CHANGE_AUDIENCE: &str = "password-change";
+
+// Podpisany parametr `state` logowania OAuth. Wskazuje wiersz oauth_states z weryfikatorem PKCE
+// i niesie dostawcę, więc callback nie ufa niepodpisanym parametrom zapytania.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthStateClaims {
+    pub sub: String,         // Subject (oauth_states row ID)
+    pub aud: String,         // Always OAUTH_STATE_AUDIENCE
+    pub provider: String,    // Provider the flow was started for
+    pub exp: i64,            // Expiration time (Unix timestamp)
+    pub iat: i64,            // Issued at (Unix timestamp)
+}
+
+const OAUTH_STATE_AUDIENCE: &str = "oauth-state";
+
 // Konfiguracja JWT
 pub struct JwtConfig {
-    secret: String,
+    secret: Option<String>,
+    algorithm: Option<String>, // HS256 (wspólny sekret, domyślnie), RS256 lub EdDSA (pary kluczy w plikach PEM)
+    pub signing_key_id: Option<String>,  // `kid` of the key used to sign new tokens
+    pub signing_key_path: Option<String>,  // PEM file with the private signing key
+    pub verification_keys: Vec<(String, String)>,  // (kid, PEM file with public key) of all active keys
     expiration: i64, // czas ważności tokenu w sekundach
+    refresh_expiration: i64, // czas ważności refresh tokenu w sekundach
 }
 
 impl JwtConfig {
     pub fn from_env() -> Self {
         Self {
-            secret: env::var("JWT_SECRET").unwrap_or_else(|_| "default_secret_change_in_production".to_string()),
+            secret: env::var("JWT_SECRET").ok().filter(|secret| !secret.is_empty()),
+            algorithm: env::var("JWT_ALGORITHM").ok().filter(|algorithm| !algorithm.is_empty()),
+            signing_key_id: env::var("JWT_SIGNING_KEY_ID").ok(),
+            signing_key_path: env::var("JWT_SIGNING_KEY_PATH").ok(),
+            // Format: kid=path,kid=path - the previous keys stay here until tokens signed with them expire
+            verification_keys: env::var("JWT_VERIFICATION_KEYS")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|entry| entry.split_once('='))
+                .map(|(kid, path)| (kid.trim().to_string(), path.trim().to_string()))
+                .collect(),
             expiration: env::var("JWT_EXPIRATION")
-                .unwrap_or_else(|_| "86400".to_string()) // 24 godziny domyślnie
+                .unwrap_or_else(|_| "900".to_string()) // 15 minut domyślnie
+                .parse()
+                .unwrap_or(900),
+            refresh_expiration: env::var("REFRESH_TOKEN_EXPIRATION")
+                .unwrap_or_else(|_| "2592000".to_string()) // 30 dni domyślnie
                 .parse()
-                .unwrap_or(86400),
+                .unwrap_or(2592000),
+        }
+    }
+
+    // Lifetime of an access token in seconds
+    pub fn expiration(&self) -> i64 {
+        self.expiration
+    }
+
+    // Lifetime of a refresh token in seconds
+    pub fn refresh_expiration(&self) -> i64 {
+        self.refresh_expiration
+    }
+
+    // Signing algorithm; an unknown JWT_ALGORITHM is a configuration error rather than a silent HS256
+    pub fn algorithm(&self) -> Result<Algorithm, AppError> {
+        match self.algorithm.as_deref().map(str::to_uppercase).as_deref() {
+            None | Some("HS256") => Ok(Algorithm::HS256),
+            Some("RS256") => Ok(Algorithm::RS256),
+            Some("EDDSA") => Ok(Algorithm::EdDSA),
+            Some(_) => Err(AppError::InternalServerError(format!(
+                "Unsupported JWT_ALGORITHM '{}' - use HS256, RS256 or EdDSA",
+                self.algorithm.as_deref().unwrap_or_default()
+            ))),
+        }
+    }
+
+    // HMAC secret (HS256 only)
+    pub fn secret(&self) -> &str {
+        self.secret.as_deref().unwrap_or(FALLBACK_SECRET)
+    }
+
+    fn uses_fallback_secret(&self) -> bool {
+        matches!(self.algorithm(), Ok(Algorithm::HS256)) && self.secret() == FALLBACK_SECRET
+    }
+}
+
+#[cfg(test)]
+impl JwtConfig {
+    // Konfiguracja do testów kluczy, bez zmiennych środowiskowych
+    pub(crate) fn for_keys(algorithm: &str, signing_key_id: &str, signing_key_path: &str, verification_keys: &[(&str, &str)]) -> Self {
+        Self {
+            secret: None,
+            algorithm: Some(algorithm.to_string()),
+            signing_key_id: Some(signing_key_id.to_string()),
+            signing_key_path: Some(signing_key_path.to_string()),
+            verification_keys: verification_keys
+                .iter()
+                .map(|(kid, path)| (kid.to_string(), path.to_string()))
+                .collect(),
+            expiration: 900,
+            refresh_expiration: 2592000,
         }
     }
 }
 
+// Sprawdzenie konfiguracji JWT przy starcie aplikacji - błąd oznacza, że serwer nie powinien wystartować
+pub fn validate_jwt_configuration(is_development: bool) -> Result<(), AppError> {
+    let config = JwtConfig::from_env();
+
+    if config.uses_fallback_secret() {
+        if !is_development {
+            return Err(AppError::InternalServerError(
+                "JWT_SECRET is not set - the built-in fallback secret may only be used in development".to_string(),
+            ));
+        }
+        tracing::warn!("JWT_SECRET is not set - using the insecure fallback secret (development only)");
+    }
+
+    // Wczytanie kluczy teraz, żeby błędne pliki PEM zatrzymały start zamiast pierwszego logowania
+    let keys = jwt_keys()?;
+    tracing::info!("JWT signing algorithm: {:?}", keys.algorithm());
+
+    Ok(())
+}
+
+fn encode_claims<T: Serialize>(claims: &T) -> Result<String, AppError> {
+    let keys = jwt_keys()?;
+
+    encode(&keys.header(), claims, keys.encoding_key())
+        .map_err(|e| AppError::InternalServerError(format!("Token generation error: {}", e)))
+}
+
+// Dekoduje token kluczem wskazanym przez nagłówek (`kid`); `configure` dostosowuje walidację
+fn decode_claims<T: DeserializeOwned>(
+    token: &str,
+    configure: impl FnOnce(&mut Validation),
+) -> Result<T, jsonwebtoken::errors::Error> {
+    let keys = jwt_keys().map_err(|_| jsonwebtoken::errors::ErrorKind::InvalidKeyFormat)?;
+    let header = decode_header(token)?;
+    let decoding_key = keys.decoding_key(&header)
+        .map_err(|_| jsonwebtoken::errors::ErrorKind::InvalidSignature)?;
+
+    let mut validation = Validation::new(keys.algorithm());
+    configure(&mut validation);
+
+    decode::<T>(token, decoding_key, &validation).map(|data| data.claims)
+}
+
 // Funkcja generująca token JWT
-pub fn generate_token(user_id: Uuid, username: &str, email: &str, role: &str) -> Result<String, AppError> {
+pub fn generate_token(
+    user_id: Uuid,
+    username: &str,
+    email: &str,
+    role: &str,
+    session_id: Uuid,
+) -> Result<String, AppError> {
     let config = JwtConfig::from_env();
     
     let now = Utc::now();
@@ -46,31 +217,133 @@ pub fn generate_token(user_id: Uuid, username: &str, email: &str, role: &str) ->
         name: username.to_string(),
         email: email.to_string(),
         role: role.to_string(),
+        sid: session_id.to_string(),
         exp: expiration.timestamp(),
         iat: now.timestamp(),
+        act: None,
     };
     
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.secret.as_bytes())
-    ).map_err(|e| AppError::InternalServerError(format!("Token generation error: {}", e)))
+    encode_claims(&claims)
 }
 
-// Funkcja weryfikująca token JWT
+// Funkcja generująca token impersonacji - token użytkownika `user` z claimem `act` administratora.
+// Należy do sesji administratora, więc jej zakończenie unieważnia także ten token.
+pub fn generate_impersonation_token(
+    user: &User,
+    actor_id: Uuid,
+    actor_session_id: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<String, AppError> {
+    let claims = Claims {
+        sub: user.id.to_string(),
+        name: user.username.clone(),
+        email: user.email.clone(),
+        role: user.role.clone(),
+        sid: actor_session_id.to_string(),
+        exp: expires_at.timestamp(),
+        iat: Utc::now().timestamp(),
+        act: Some(Actor { sub: actor_id.to_string() }),
+    };
+
+    encode_claims(&claims)
+}
+
+// Funkcja weryfikująca token JWT - odrzuca też tokeny sesji unieważnionych w tym procesie
 pub fn verify_token(token: &str) -> Result<Claims, AppError> {
-    let config = JwtConfig::from_env();
-    
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(config.secret.as_bytes()),
-        &Validation::default()
-    )
-    .map(|data| data.claims)
+    let claims = decode_claims::<Claims>(token, |_| {})
     .map_err(|e| match e.kind() {
         jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::ValidationError("Token expired".to_string()),
         jsonwebtoken::errors::ErrorKind::InvalidToken => AppError::ValidationError("Invalid token".to_string()),
+        // Unknown `kid`, a rotated-out key or a token from another issuer
+        jsonwebtoken::errors::ErrorKind::InvalidSignature | jsonwebtoken::errors::ErrorKind::Base64(_) => {
+            AppError::ValidationError("Invalid token".to_string())
+        }
+        // Tokens of another kind (e.g. 2FA challenge) have a different audience or claim set
+        jsonwebtoken::errors::ErrorKind::InvalidAudience | jsonwebtoken::errors::ErrorKind::Json(_) => {
+            AppError::ValidationError("Invalid token".to_string())
+        }
         _ => AppError::InternalServerError(format!("Token validation error: {}", e)),
+    })?;
+
+    let session_id = Uuid::parse_str(&claims.sid)
+        .map_err(|_| AppError::ValidationError("Invalid token".to_string()))?;
+    if sessions::is_revoked(&session_id) {
+        return Err(AppError::ValidationError("Session has been revoked".to_string()));
+    }
+
+    Ok(claims)
+}
+
+// Funkcja generująca krótkotrwały token wyzwania dla drugiego kroku logowania
+pub fn generate_challenge_token(user_id: Uuid, expiration_seconds: i64) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = ChallengeClaims {
+        sub: user_id.to_string(),
+        aud: TWO_FACTOR_CHALLENGE_AUDIENCE.to_string(),
+        exp: (now + Duration::seconds(expiration_seconds)).timestamp(),
+        iat: now.timestamp(),
+    };
+
+    encode_claims(&claims)
+}
+
+// Funkcja weryfikująca token wyzwania 2FA
+pub fn verify_challenge_token(token: &str) -> Result<ChallengeClaims, AppError> {
+    decode_claims::<ChallengeClaims>(token, |validation| {
+        validation.set_audience(&[TWO_FACTOR_CHALLENGE_AUDIENCE]);
+    })
+    .map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::ValidationError("Challenge token expired".to_string()),
+        _ => AppError::ValidationError("Invalid challenge token".to_string()),
+    })
+}
+
+// Funkcja generująca ograniczony token zmiany hasła
+pub fn generate_password_change_token(user_id: Uuid, expiration_seconds: i64) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = PasswordChangeClaims {
+        sub: user_id.to_string(),
+        aud: PASSWORD_CHANGE_AUDIENCE.to_string(),
+        exp: (now + Duration::seconds(expiration_seconds)).timestamp(),
+        iat: now.timestamp(),
+    };
+
+    encode_claims(&claims)
+}
+
+// Funkcja weryfikująca token zmiany hasła
+pub fn verify_password_change_token(token: &str) -> Result<PasswordChangeClaims, AppError> {
+    decode_claims::<PasswordChangeClaims>(token, |validation| {
+        validation.set_audience(&[PASSWORD_CHANGE_AUDIENCE]);
+    })
+    .map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::ValidationError("Password change token expired".to_string()),
+        _ => AppError::ValidationError("Invalid password change token".to_string()),
+    })
+}
+
+// Funkcja generująca podpisany parametr `state` dla logowania OAuth
+pub fn generate_oauth_state_token(state_id: Uuid, provider: &str, expiration_seconds: i64) -> Result<String, AppError> {
+    let now = Utc::now();
+    let claims = OAuthStateClaims {
+        sub: state_id.to_string(),
+        aud: OAUTH_STATE_AUDIENCE.to_string(),
+        provider: provider.to_string(),
+        exp: (now + Duration::seconds(expiration_seconds)).timestamp(),
+        iat: now.timestamp(),
+    };
+
+    encode_claims(&claims)
+}
+
+// Funkcja weryfikująca parametr `state` zwrócony przez dostawcę OAuth
+pub fn verify_oauth_state_token(token: &str) -> Result<OAuthStateClaims, AppError> {
+    decode_claims::<OAuthStateClaims>(token, |validation| {
+        validation.set_audience(&[OAUTH_STATE_AUDIENCE]);
+    })
+    .map_err(|e| match e.kind() {
+        jsonwebtoken::errors::ErrorKind::ExpiredSignature => AppError::ValidationError("OAuth state expired".to_string()),
+        _ => AppError::ValidationError("Invalid OAuth state".to_string()),
     })
 }
 
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::fs;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header};
+use lazy_static::lazy_static;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+
+use crate::auth_utils::jwt::JwtConfig;
+use crate::error::AppError;
+
+// DER prefix of an Ed25519 SubjectPublicKeyInfo; the raw 32-byte key follows it
+const ED25519_SPKI_PREFIX: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+// Klucze JWT wczytywane raz przy pierwszym użyciu (pliki PEM nie są czytane przy każdym żądaniu)
+lazy_static! {
+    static ref JWT_KEYS: Result<JwtKeys, String> = JwtKeys::from_config(&JwtConfig::from_env()).map_err(|e| e.to_string());
+}
+
+/// Returns the process-wide JWT key set
+pub fn jwt_keys() -> Result<&'static JwtKeys, AppError> {
+    JWT_KEYS
+        .as_ref()
+        .map_err(|e| AppError::InternalServerError(format!("JWT keys are not available: {}", e)))
+}
+
+// Klucz weryfikujący z identyfikatorem `kid`
+struct VerificationKey {
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+// Klucz podpisujący tokeny oraz wszystkie aktywne klucze weryfikujące.
+// Przy HS256 jest to jeden wspólny sekret; przy RS256/EdDSA tokeny mają nagłówek `kid`,
+// a klucze publiczne są publikowane pod /.well-known/jwks.json.
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    signing_kid: Option<String>,
+    encoding_key: EncodingKey,
+    hmac_key: Option<DecodingKey>,
+    verification_keys: HashMap<String, VerificationKey>,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &JwtConfig) -> Result<Self, AppError> {
+        match config.algorithm()? {
+            Algorithm::HS256 => Ok(Self {
+                algorithm: Algorithm::HS256,
+                signing_kid: None,
+                encoding_key: EncodingKey::from_secret(config.secret().as_bytes()),
+                hmac_key: Some(DecodingKey::from_secret(config.secret().as_bytes())),
+                verification_keys: HashMap::new(),
+            }),
+            algorithm @ (Algorithm::RS256 | Algorithm::EdDSA) => Self::load_asymmetric(config, algorithm),
+            other => Err(AppError::InternalServerError(format!("Unsupported JWT algorithm: {:?}", other))),
+        }
+    }
+
+    fn load_asymmetric(config: &JwtConfig, algorithm: Algorithm) -> Result<Self, AppError> {
+        let signing_kid = config.signing_key_id.clone()
+            .ok_or_else(|| AppError::InternalServerError("JWT_SIGNING_KEY_ID is not set".to_string()))?;
+        let signing_key_path = config.signing_key_path.clone()
+            .ok_or_else(|| AppError::InternalServerError("JWT_SIGNING_KEY_PATH is not set".to_string()))?;
+
+        let private_pem = read_pem(&signing_key_path)?;
+        let encoding_key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem),
+            _ => EncodingKey::from_ed_pem(&private_pem),
+        }
+        .map_err(|e| AppError::InternalServerError(format!("Invalid JWT signing key {}: {}", signing_key_path, e)))?;
+
+        let mut verification_keys = HashMap::new();
+        for (kid, path) in &config.verification_keys {
+            let jwk = public_key_to_jwk(algorithm, kid, &read_pem(path)?)
+                .map_err(|e| AppError::InternalServerError(format!("Invalid JWT verification key {}: {}", path, e)))?;
+            let decoding_key = DecodingKey::from_jwk(&jwk)
+                .map_err(|e| AppError::InternalServerError(format!("Invalid JWT verification key {}: {}", path, e)))?;
+            verification_keys.insert(kid.clone(), VerificationKey { decoding_key, jwk });
+        }
+
+        // Bez klucza publicznego dla bieżącego `kid` nikt nie zweryfikowałby wydawanych tokenów
+        if !verification_keys.contains_key(&signing_kid) {
+            return Err(AppError::InternalServerError(format!(
+                "JWT_VERIFICATION_KEYS has no public key for the signing key id '{}'",
+                signing_kid
+            )));
+        }
+
+        Ok(Self {
+            algorithm,
+            signing_kid: Some(signing_kid),
+            encoding_key,
+            hmac_key: None,
+            verification_keys,
+        })
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Header for newly issued tokens (algorithm and the `kid` of the signing key)
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.signing_kid.clone();
+        header
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    /// Picks the verification key for a token based on its header
+    pub fn decoding_key(&self, header: &Header) -> Result<&DecodingKey, AppError> {
+        if header.alg != self.algorithm {
+            return Err(AppError::ValidationError("Invalid token".to_string()));
+        }
+
+        if let Some(hmac_key) = &self.hmac_key {
+            return Ok(hmac_key);
+        }
+
+        header.kid
+            .as_deref()
+            .and_then(|kid| self.verification_keys.get(kid))
+            .map(|key| &key.decoding_key)
+            .ok_or_else(|| AppError::ValidationError("Invalid token".to_string()))
+    }
+
+    /// Public keys for /.well-known/jwks.json (empty for HS256, the shared secret is never published)
+    pub fn jwks(&self) -> JwkSet {
+        let mut keys: Vec<Jwk> = self.verification_keys.values().map(|key| key.jwk.clone()).collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+        JwkSet { keys }
+    }
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>, AppError> {
+    fs::read(path).map_err(|e| AppError::InternalServerError(format!("Failed to read key file {}: {}", path, e)))
+}
+
+// Converts a public key in PEM format into a JWK with the given key id
+fn public_key_to_jwk(algorithm: Algorithm, kid: &str, pem: &[u8]) -> Result<Jwk, String> {
+    let pem = std::str::from_utf8(pem).map_err(|e| e.to_string())?;
+
+    let (key_algorithm, parameters) = match algorithm {
+        Algorithm::RS256 => {
+            let key = RsaPublicKey::from_public_key_pem(pem)
+                .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+                .map_err(|e| e.to_string())?;
+
+            (KeyAlgorithm::RS256, AlgorithmParameters::RSA(RSAKeyParameters {
+                key_type: RSAKeyType::RSA,
+                n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }))
+        }
+        Algorithm::EdDSA => {
+            let der = pem_body(pem)?;
+            let raw_key = der
+                .strip_prefix(&ED25519_SPKI_PREFIX[..])
+                .filter(|key| key.len() == 32)
+                .ok_or_else(|| "not an Ed25519 public key".to_string())?;
+
+            (KeyAlgorithm::EdDSA, AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(raw_key),
+            }))
+        }
+        other => return Err(format!("unsupported algorithm {:?}", other)),
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+// Decodes the base64 body of a PEM block
+fn pem_body(pem: &str) -> Result<Vec<u8>, String> {
+    let body: String = pem
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .map(str::trim)
+        .collect();
+
+    STANDARD.decode(body).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, encode, Validation};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct TestClaims {
+        sub: String,
+        exp: i64,
+    }
+
+    // Klucze testowe z tests/keys (wygenerowane tylko na potrzeby testów)
+    fn key_path(name: &str) -> String {
+        format!("{}/tests/keys/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn load(algorithm: &str, signing_kid: &str, signing_key: &str, verification_keys: &[(&str, &str)]) -> Result<JwtKeys, AppError> {
+        let verification_keys: Vec<(&str, String)> = verification_keys
+            .iter()
+            .map(|(kid, name)| (*kid, key_path(name)))
+            .collect();
+        let verification_keys: Vec<(&str, &str)> = verification_keys
+            .iter()
+            .map(|(kid, path)| (*kid, path.as_str()))
+            .collect();
+
+        JwtKeys::from_config(&JwtConfig::for_keys(algorithm, signing_kid, &key_path(signing_key), &verification_keys))
+    }
+
+    fn sign(keys: &JwtKeys) -> String {
+        let claims = TestClaims { sub: "user".to_string(), exp: chrono::Utc::now().timestamp() + 60 };
+        encode(&keys.header(), &claims, keys.encoding_key()).unwrap()
+    }
+
+    fn verify(keys: &JwtKeys, token: &str) -> Result<TestClaims, AppError> {
+        let header = decode_header(token).map_err(|_| AppError::ValidationError("Invalid token".to_string()))?;
+        let decoding_key = keys.decoding_key(&header)?;
+
+        decode::<TestClaims>(token, decoding_key, &Validation::new(keys.algorithm()))
+            .map(|data| data.claims)
+            .map_err(|_| AppError::ValidationError("Invalid token".to_string()))
+    }
+
+    #[test]
+    fn signs_and_verifies_rs256_tokens() {
+        let keys = load("RS256", "rsa-old", "rsa-old.pem", &[("rsa-old", "rsa-old.pub.pem")]).unwrap();
+        let token = sign(&keys);
+
+        assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some("rsa-old"));
+        assert_eq!(verify(&keys, &token).unwrap().sub, "user");
+        assert_eq!(keys.jwks().keys.len(), 1);
+    }
+
+    #[test]
+    fn signs_and_verifies_eddsa_tokens() {
+        let keys = load("EdDSA", "ed", "ed25519.pem", &[("ed", "ed25519.pub.pem")]).unwrap();
+        let token = sign(&keys);
+
+        assert_eq!(decode_header(&token).unwrap().alg, Algorithm::EdDSA);
+        assert_eq!(verify(&keys, &token).unwrap().sub, "user");
+    }
+
+    #[test]
+    fn verifies_tokens_signed_with_a_rotated_key() {
+        let before = load("RS256", "rsa-old", "rsa-old.pem", &[("rsa-old", "rsa-old.pub.pem")]).unwrap();
+        let old_token = sign(&before);
+
+        // Nowy klucz podpisuje, poprzedni zostaje do weryfikacji
+        let after = load("RS256", "rsa-new", "rsa-new.pem", &[("rsa-old", "rsa-old.pub.pem"), ("rsa-new", "rsa-new.pub.pem")]).unwrap();
+        let new_token = sign(&after);
+
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("rsa-new"));
+        assert!(verify(&after, &old_token).is_ok());
+        assert!(verify(&after, &new_token).is_ok());
+        assert!(verify(&before, &new_token).is_err());
+
+        // Po wycofaniu starego klucza jego tokeny są odrzucane
+        let retired = load("RS256", "rsa-new", "rsa-new.pem", &[("rsa-new", "rsa-new.pub.pem")]).unwrap();
+        assert!(verify(&retired, &old_token).is_err());
+        assert_eq!(retired.jwks().keys.len(), 1);
+    }
+
+    #[test]
+    fn rejects_tokens_signed_with_another_algorithm() {
+        let rsa = load("RS256", "rsa-old", "rsa-old.pem", &[("rsa-old", "rsa-old.pub.pem")]).unwrap();
+        let eddsa = load("EdDSA", "rsa-old", "ed25519.pem", &[("rsa-old", "ed25519.pub.pem")]).unwrap();
+
+        assert!(verify(&rsa, &sign(&eddsa)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_algorithm() {
+        assert!(load("HS512", "rsa-old", "rsa-old.pem", &[("rsa-old", "rsa-old.pub.pem")]).is_err());
+    }
+
+    #[test]
+    fn requires_a_public_key_for_the_signing_key() {
+        assert!(load("RS256", "rsa-new", "rsa-new.pem", &[("rsa-old", "rsa-old.pub.pem")]).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use std::env;
+use chrono::{DateTime, Utc};
+
+// Ochrona przed zgadywaniem haseł: blokada konta z wykładniczym wydłużaniem i limit błędów na adres IP
+#[derive(Debug, Clone)]
+pub struct LockoutConfig {
+    pub max_failed_logins: i32,     // consecutive failures before the account is locked
+    pub base_lockout_seconds: i64,  // first lock duration, doubled with every further failure
+    pub max_lockout_seconds: i64,   // upper bound for a single lock
+    pub ip_max_failed_logins: i64,  // failures allowed from one IP address within the window
+    pub ip_window_seconds: i64,
+}
+
+impl LockoutConfig {
+    pub fn from_env() -> Self {
+        Self {
+            max_failed_logins: env::var("LOGIN_MAX_FAILED_ATTEMPTS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            base_lockout_seconds: env::var("LOGIN_LOCKOUT_BASE_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            max_lockout_seconds: env::var("LOGIN_LOCKOUT_MAX_SECONDS")
+                .unwrap_or_else(|_| "3600".to_string()) // 1 hour by default
+                .parse()
+                .unwrap_or(3600),
+            ip_max_failed_logins: env::var("LOGIN_IP_MAX_FAILED_ATTEMPTS")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            ip_window_seconds: env::var("LOGIN_IP_WINDOW_SECONDS")
+                .unwrap_or_else(|_| "900".to_string()) // 15 minutes by default
+                .parse()
+                .unwrap_or(900),
+        }
+    }
+}
+
+/// Seconds left until `until`, rounded up, for the Retry-After header
+pub fn retry_after_seconds(until: DateTime<Utc>) -> u64 {
+    let millis = (until - Utc::now()).num_milliseconds().max(0);
+    ((millis + 999) / 1000).max(1) as u64
+}
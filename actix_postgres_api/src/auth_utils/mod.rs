@@ -1,9 +1,20 @@
 // Re-export all public items from submodules
-pub use self::password::{hash_password, verify_password, validate_password};
+pub use self::password::{hash_password, verify_password, password_needs_rehash, validate_password_hash_configuration, DEFAULT_PASSWORD};
+pub use self::password_policy::{validate_password, PasswordContext, PasswordViolation};
 pub use self::validation::{validate_email, validate_phone_number, validate_username, validate_full_name};
 pub use self::roles::validate_role;
 pub mod oauth;
+pub mod oidc;
 pub mod jwt;
+pub mod keys;
+pub mod tokens;
+pub mod account;
+pub mod totp;
+pub mod lockout;
+pub mod permissions;
+pub mod sessions;
+pub mod impersonation;
+pub mod password_policy;
 
 // Define submodules
 mod password;
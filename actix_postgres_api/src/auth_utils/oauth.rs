@@ -1,11 +1,18 @@
 use serde::{Deserialize, Serialize};
 use std::env;
 use reqwest::Client;
+use oauth2::{PkceCodeChallenge, PkceCodeVerifier};
+use chrono::{Duration, Utc};
 use crate::error::AppError;
-use crate::models::User;
-use crate::auth_utils::jwt::generate_token;
+use crate::models::{LoginOutcome, UserIdentity};
 use sqlx::postgres::PgPool;
 use crate::database::user::UserRepository;
+use crate::database::{IdentityRepository, OAuthStateRepository};
+use crate::auth_utils::jwt::{generate_oauth_state_token, verify_oauth_state_token};
+use crate::auth_utils::oidc::{self, OidcProviderConfig};
+use crate::services::AuthService;
+use crate::middleware::ClientInfo;
+use crate::auth_utils::account::AccountConfig;
 use uuid::Uuid;
 
 // OAuth provider configurations
@@ -18,6 +25,7 @@ pub struct OAuthConfig {
     pub github_client_id: String,
     pub github_client_secret: String,
     pub redirect_url: String,
+    pub state_expiration: i64, // czas na dokończenie logowania u dostawcy w sekundach
 }
 
 impl OAuthConfig {
@@ -30,6 +38,10 @@ impl OAuthConfig {
             github_client_id: env::var("GITHUB_CLIENT_ID").unwrap_or_default(),
             github_client_secret: env::var("GITHUB_CLIENT_SECRET").unwrap_or_default(),
             redirect_url: env::var("OAUTH_REDIRECT_URL").unwrap_or_else(|_| "http://localhost:8080/auth/callback".to_string()),
+            state_expiration: env::var("OAUTH_STATE_EXPIRATION")
+                .unwrap_or_else(|_| "600".to_string()) // 10 minut domyślnie
+                .parse()
+                .unwrap_or(600),
         }
     }
 }
@@ -40,6 +52,7 @@ pub enum OAuthProvider {
     Google,
     Facebook,
     GitHub,
+    Oidc(String),  // OpenID Connect issuer configured in OIDC_PROVIDERS
 }
 
 impl ToString for OAuthProvider {
@@ -48,21 +61,30 @@ impl ToString for OAuthProvider {
             OAuthProvider::Google => "google".to_string(),
             OAuthProvider::Facebook => "facebook".to_string(),
             OAuthProvider::GitHub => "github".to_string(),
+            OAuthProvider::Oidc(name) => name.clone(),
         }
     }
 }
 
-impl From<&str> for OAuthProvider {
-    fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "google" => OAuthProvider::Google,
-            "facebook" => OAuthProvider::Facebook,
-            "github" => OAuthProvider::GitHub,
-            _ => OAuthProvider::Google, // Default to Google
+impl OAuthProvider {
+    // Built-in providers take precedence over OIDC providers with the same name
+    pub fn from_name(name: &str) -> Result<Self, AppError> {
+        match name.to_lowercase().as_str() {
+            "google" => Ok(OAuthProvider::Google),
+            "facebook" => Ok(OAuthProvider::Facebook),
+            "github" => Ok(OAuthProvider::GitHub),
+            other if OidcProviderConfig::from_env(other).is_some() => Ok(OAuthProvider::Oidc(other.to_string())),
+            _ => Err(AppError::BadRequest(format!("Unsupported OAuth provider: {}", name))),
         }
     }
 }
 
+// Tokens returned by the provider's token endpoint; `id_token` only for OpenID Connect
+pub struct ProviderTokens {
+    pub access_token: String,
+    pub id_token: Option<String>,
+}
+
 // OAuth user info from providers
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OAuthUserInfo {
@@ -70,6 +92,19 @@ pub struct OAuthUserInfo {
     pub email: String,
     pub name: String,
     pub provider: String,
+    pub email_verified: bool,  // Whether the provider has confirmed ownership of the email
+}
+
+// Started authorization request: where to send the browser and the id bound to it via cookie
+pub struct OAuthAuthorization {
+    pub url: String,
+    pub state_id: Uuid,
+}
+
+// Result of the OAuth callback - a login, or a provider account linked to a logged-in user
+pub enum OAuthCallbackOutcome {
+    Login(LoginOutcome),
+    Linked(UserIdentity),
 }
 
 // OAuth service for handling authentication with providers
@@ -77,6 +112,9 @@ pub struct OAuthService {
     config: OAuthConfig,
     client: Client,
     repo: UserRepository,
+    state_repo: OAuthStateRepository,
+    identity_repo: IdentityRepository,
+    auth: AuthService,
 }
 
 impl OAuthService {
@@ -84,35 +122,83 @@ impl OAuthService {
         Self {
             config: OAuthConfig::from_env(),
             client: Client::new(),
-            repo: UserRepository::new(pool),
+            repo: UserRepository::new(pool.clone()),
+            state_repo: OAuthStateRepository::new(pool.clone()),
+            identity_repo: IdentityRepository::new(pool.clone()),
+            auth: AuthService::new(pool),
         }
     }
 
+    // Start a login (or linking, with `link_user_id`): persist a single-use state with a fresh PKCE verifier
+    // and build the provider URL
+    pub async fn start_authorization(
+        &self,
+        provider: OAuthProvider,
+        link_user_id: Option<Uuid>,
+    ) -> Result<OAuthAuthorization, AppError> {
+        let (code_challenge, code_verifier) = PkceCodeChallenge::new_random_sha256();
+        let state_id = Uuid::new_v4();
+        let expires_at = Utc::now() + Duration::seconds(self.config.state_expiration);
+        let nonce = matches!(provider, OAuthProvider::Oidc(_)).then(|| Uuid::new_v4().simple().to_string());
+
+        // Resolve the URL first so an unreachable OIDC issuer does not leave a pending state behind
+        let state = generate_oauth_state_token(state_id, &provider.to_string(), self.config.state_expiration)?;
+        let url = self.get_authorization_url(provider.clone(), &state, &code_challenge, nonce.as_deref()).await?;
+
+        self.state_repo
+            .create(state_id, &provider.to_string(), code_verifier.secret(), link_user_id, nonce.as_deref(), expires_at)
+            .await?;
+
+        Ok(OAuthAuthorization { url, state_id })
+    }
+
     // Generate authorization URL for the specified provider
-    pub fn get_authorization_url(&self, provider: OAuthProvider) -> String {
-        match provider {
-            OAuthProvider::Google => {
-                format!(
-                    "https://accounts.google.com/o/oauth2/v2/auth?client_id={}&redirect_uri={}&response_type=code&scope=email%20profile",
-                    self.config.google_client_id,
-                    self.config.redirect_url
-                )
-            }
-            OAuthProvider::Facebook => {
-                format!(
-                    "https://www.facebook.com/v12.0/dialog/oauth?client_id={}&redirect_uri={}&response_type=code&scope=email,public_profile",
-                    self.config.facebook_client_id,
-                    self.config.redirect_url
-                )
-            }
-            OAuthProvider::GitHub => {
-                format!(
-                    "https://github.com/login/oauth/authorize?client_id={}&redirect_uri={}&scope=user:email",
-                    self.config.github_client_id,
-                    self.config.redirect_url
-                )
+    pub async fn get_authorization_url(
+        &self,
+        provider: OAuthProvider,
+        state: &str,
+        code_challenge: &PkceCodeChallenge,
+        nonce: Option<&str>,
+    ) -> Result<String, AppError> {
+        let (base_url, client_id, scope) = match provider {
+            OAuthProvider::Google => (
+                "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+                self.config.google_client_id.clone(),
+                "email profile".to_string(),
+            ),
+            OAuthProvider::Facebook => (
+                "https://www.facebook.com/v12.0/dialog/oauth".to_string(),
+                self.config.facebook_client_id.clone(),
+                "email,public_profile".to_string(),
+            ),
+            OAuthProvider::GitHub => (
+                "https://github.com/login/oauth/authorize".to_string(),
+                self.config.github_client_id.clone(),
+                "user:email".to_string(),
+            ),
+            OAuthProvider::Oidc(name) => {
+                let config = oidc_config(&name)?;
+                let metadata = oidc::metadata(&self.client, &config).await?;
+                (metadata.authorization_endpoint, config.client_id, config.scopes)
             }
+        };
+
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query
+            .append_pair("client_id", &client_id)
+            .append_pair("redirect_uri", &self.config.redirect_url)
+            .append_pair("response_type", "code")
+            .append_pair("scope", &scope)
+            .append_pair("state", state)
+            .append_pair("code_challenge", code_challenge.as_str())
+            .append_pair("code_challenge_method", code_challenge.method().as_str());
+        if let Some(nonce) = nonce {
+            query.append_pair("nonce", nonce);
         }
+
+        // The discovery document may already put parameters into the authorization endpoint
+        let separator = if base_url.contains('?') { '&' } else { '?' };
+        Ok(format!("{}{}{}", base_url, separator, query.finish()))
     }
 
     // Exchange authorization code for access token
@@ -120,13 +206,15 @@ impl OAuthService {
         &self,
         provider: OAuthProvider,
         code: &str,
-    ) -> Result<String, AppError> {
-        match provider {
+        code_verifier: &PkceCodeVerifier,
+    ) -> Result<ProviderTokens, AppError> {
+        let token_response: serde_json::Value = match provider {
             OAuthProvider::Google => {
                 let params = [
                     ("client_id", self.config.google_client_id.as_str()),
                     ("client_secret", self.config.google_client_secret.as_str()),
                     ("code", code),
+                    ("code_verifier", code_verifier.secret().as_str()),
                     ("redirect_uri", self.config.redirect_url.as_str()),
                     ("grant_type", "authorization_code"),
                 ];
@@ -138,19 +226,15 @@ impl OAuthService {
                     .await
                     .map_err(|e| AppError::InternalServerError(format!("OAuth token exchange error: {}", e)))?;
 
-                let token_response: serde_json::Value = response.json().await
-                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?;
-
-                token_response.get("access_token")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .ok_or_else(|| AppError::InternalServerError("Failed to get access token".to_string()))
+                response.json().await
+                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?
             }
             OAuthProvider::Facebook => {
                 let params = [
                     ("client_id", self.config.facebook_client_id.as_str()),
                     ("client_secret", self.config.facebook_client_secret.as_str()),
                     ("code", code),
+                    ("code_verifier", code_verifier.secret().as_str()),
                     ("redirect_uri", self.config.redirect_url.as_str()),
                 ];
 
@@ -161,19 +245,15 @@ impl OAuthService {
                     .await
                     .map_err(|e| AppError::InternalServerError(format!("OAuth token exchange error: {}", e)))?;
 
-                let token_response: serde_json::Value = response.json().await
-                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?;
-
-                token_response.get("access_token")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .ok_or_else(|| AppError::InternalServerError("Failed to get access token".to_string()))
+                response.json().await
+                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?
             }
             OAuthProvider::GitHub => {
                 let params = [
                     ("client_id", self.config.github_client_id.as_str()),
                     ("client_secret", self.config.github_client_secret.as_str()),
                     ("code", code),
+                    ("code_verifier", code_verifier.secret().as_str()),
                     ("redirect_uri", self.config.redirect_url.as_str()),
                 ];
 
@@ -185,23 +265,54 @@ impl OAuthService {
                     .await
                     .map_err(|e| AppError::InternalServerError(format!("OAuth token exchange error: {}", e)))?;
 
-                let token_response: serde_json::Value = response.json().await
-                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?;
+                response.json().await
+                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?
+            }
+            OAuthProvider::Oidc(name) => {
+                let config = oidc_config(&name)?;
+                let metadata = oidc::metadata(&self.client, &config).await?;
+                let params = [
+                    ("client_id", config.client_id.as_str()),
+                    ("client_secret", config.client_secret.as_str()),
+                    ("code", code),
+                    ("code_verifier", code_verifier.secret().as_str()),
+                    ("redirect_uri", self.config.redirect_url.as_str()),
+                    ("grant_type", "authorization_code"),
+                ];
 
-                token_response.get("access_token")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .ok_or_else(|| AppError::InternalServerError("Failed to get access token".to_string()))
+                let response = self.client
+                    .post(&metadata.token_endpoint)
+                    .form(&params)
+                    .header("Accept", "application/json")
+                    .send()
+                    .await
+                    .map_err(|e| AppError::InternalServerError(format!("OAuth token exchange error: {}", e)))?;
+
+                response.json().await
+                    .map_err(|e| AppError::InternalServerError(format!("OAuth token parsing error: {}", e)))?
             }
-        }
+        };
+
+        let access_token = token_response.get("access_token")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| AppError::InternalServerError("Failed to get access token".to_string()))?;
+
+        let id_token = token_response.get("id_token")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        Ok(ProviderTokens { access_token, id_token })
     }
 
-    // Get user info from provider using access token
+    // Get user info from provider using access token (or the validated ID token for OpenID Connect)
     pub async fn get_user_info(
         &self,
         provider: OAuthProvider,
-        access_token: &str,
+        tokens: &ProviderTokens,
+        nonce: Option<&str>,
     ) -> Result<OAuthUserInfo, AppError> {
+        let access_token = tokens.access_token.as_str();
         match provider {
             OAuthProvider::Google => {
                 let response = self.client
@@ -226,11 +337,16 @@ impl OAuthService {
                     .and_then(|v| v.as_str())
                     .unwrap_or("Google User");
 
+                let email_verified = user_data.get("verified_email")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
                 Ok(OAuthUserInfo {
                     id: id.to_string(),
                     email: email.to_string(),
                     name: name.to_string(),
                     provider: "google".to_string(),
+                    email_verified,
                 })
             }
             OAuthProvider::Facebook => {
@@ -262,6 +378,8 @@ impl OAuthService {
                     email: email.to_string(),
                     name: name.to_string(),
                     provider: "facebook".to_string(),
+                    // Facebook does not report whether the email was confirmed
+                    email_verified: false,
                 })
             }
             OAuthProvider::GitHub => {
@@ -298,63 +416,238 @@ impl OAuthService {
                     .map_err(|e| AppError::InternalServerError(format!("OAuth email info parsing error: {}", e)))?;
 
                 // Find primary email
-                let email = emails.iter()
+                let primary = emails.iter()
                     .find(|e| e.get("primary").and_then(|v| v.as_bool()).unwrap_or(false))
-                    .and_then(|e| e.get("email").and_then(|v| v.as_str()))
                     .ok_or_else(|| AppError::InternalServerError("Missing user email".to_string()))?;
 
+                let email = primary.get("email")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| AppError::InternalServerError("Missing user email".to_string()))?;
+
+                let email_verified = primary.get("verified")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
                 Ok(OAuthUserInfo {
                     id,
                     email: email.to_string(),
                     name: name.to_string(),
                     provider: "github".to_string(),
+                    email_verified,
                 })
             }
+            OAuthProvider::Oidc(name) => {
+                let config = oidc_config(&name)?;
+                let id_token = tokens.id_token.as_deref()
+                    .ok_or_else(|| AppError::InternalServerError("Missing ID token".to_string()))?;
+
+                let mut claims = oidc::validate_id_token(&self.client, &config, id_token, nonce).await?;
+
+                // Issuers may keep profile claims out of the ID token - fill them from the userinfo endpoint
+                if !claims.contains_key(&config.claims.email) {
+                    if let Some(userinfo_endpoint) = oidc::metadata(&self.client, &config).await?.userinfo_endpoint {
+                        let response = self.client
+                            .get(&userinfo_endpoint)
+                            .header("Authorization", format!("Bearer {}", access_token))
+                            .send()
+                            .await
+                            .map_err(|e| AppError::InternalServerError(format!("OAuth user info error: {}", e)))?;
+
+                        let userinfo: serde_json::Map<String, serde_json::Value> = response.json().await
+                            .map_err(|e| AppError::InternalServerError(format!("OAuth user info parsing error: {}", e)))?;
+
+                        // Userinfo must describe the same subject as the validated ID token
+                        if userinfo.get("sub") != claims.get("sub") {
+                            return Err(AppError::InternalServerError("OAuth user info subject mismatch".to_string()));
+                        }
+
+                        for (key, value) in userinfo {
+                            claims.entry(key).or_insert(value);
+                        }
+                    }
+                }
+
+                oidc::map_claims(&config, &claims)
+            }
         }
     }
 
-    // Process OAuth login - find or create user and generate JWT token
+    // Process OAuth callback - find or create user and issue access and refresh tokens
+    // (or a two-factor challenge when the account has 2FA enabled); links the account instead
+    // when the flow was started by a logged-in user
     pub async fn process_oauth_login(
         &self,
-        provider: OAuthProvider,
+        state: &str,
         code: &str,
-    ) -> Result<(User, String), AppError> {
+        browser_state_id: Option<&str>,
+        client: &ClientInfo,
+    ) -> Result<OAuthCallbackOutcome, AppError> {
+        // The state must be ours, issued to this browser and not used before
+        let claims = verify_oauth_state_token(state)?;
+        if browser_state_id != Some(claims.sub.as_str()) {
+            tracing::warn!(target: "security", event = "oauth_state_mismatch", "OAuth callback state does not match the browser session");
+            return Err(AppError::ValidationError("Invalid OAuth state".to_string()));
+        }
+
+        let state_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::ValidationError("Invalid OAuth state".to_string()))?;
+        let pending = self.state_repo.consume(state_id).await?
+            .ok_or_else(|| AppError::ValidationError("OAuth state expired or already used".to_string()))?;
+
+        // Provider comes from the stored state, never from the callback query
+        let provider = OAuthProvider::from_name(&pending.provider)?;
+        let code_verifier = PkceCodeVerifier::new(pending.pkce_verifier);
+
         // Exchange authorization code for access token
-        let access_token = self.exchange_code_for_token(provider.clone(), code).await?;
+        let tokens = self.exchange_code_for_token(provider.clone(), code, &code_verifier).await?;
         
         // Get user info from provider
-        let user_info = self.get_user_info(provider, &access_token).await?;
-        
-        // Check if user exists by email
-        let user_result = self.repo.find_by_email(&user_info.email).await;
+        let user_info = self.get_user_info(provider, &tokens, pending.nonce.as_deref()).await?;
+
+        if let Some(user_id) = pending.link_user_id {
+            return self.link_identity(user_id, &user_info).await.map(OAuthCallbackOutcome::Linked);
+        }
+
+        let email_verified = user_info.email_verified;
+        let user = self.find_or_create_user(user_info).await?;
+
+        // An address confirmed by the provider counts as verified on our side as well
+        let user = if email_verified && user.email_verified_at.is_none() {
+            self.repo.mark_email_verified(user.id).await?
+        } else {
+            user
+        };
+
+        if user.email_verified_at.is_none() && AccountConfig::from_env().require_email_verification {
+            return Err(AppError::Forbidden("Email address has not been verified".to_string()));
+        }
         
-        let user = match user_result {
-            Ok(existing_user) => {
-                // User exists, return existing user
-                existing_user
-            },
-            Err(_) => {
-                // User doesn't exist, create new user
+        self.auth.complete_login(user, client).await.map(OAuthCallbackOutcome::Login)
+    }
+
+    // Identities of the user, e.g. for the account settings page
+    pub async fn list_identities(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, AppError> {
+        self.identity_repo.find_by_user(user_id).await
+    }
+
+    pub async fn unlink_identity(&self, user_id: Uuid, provider: OAuthProvider) -> Result<(), AppError> {
+        let provider = provider.to_string();
+        if !self.identity_repo.delete(user_id, &provider).await? {
+            return Err(AppError::NotFound(format!("No {} account is linked", provider)));
+        }
+
+        tracing::info!("Unlinked {} account from user {}", provider, user_id);
+        Ok(())
+    }
+
+    // Finds the user by the linked provider account. Without a link, an existing account with the same
+    // email is linked only if the provider has verified the address - otherwise anyone could register
+    // the victim's email at a provider and take over the account.
+    async fn find_or_create_user(&self, user_info: OAuthUserInfo) -> Result<crate::models::User, AppError> {
+        if let Some(identity) = self.identity_repo
+            .find_by_provider_user_id(&user_info.provider, &user_info.id)
+            .await?
+        {
+            self.identity_repo.touch_last_login(identity.id).await?;
+            return self.repo.find_by_id(identity.user_id).await;
+        }
+
+        let user = match self.repo.find_by_email(&user_info.email).await {
+            Ok(existing_user) if user_info.email_verified => existing_user,
+            Ok(_) => {
+                return Err(AppError::BadRequest(format!(
+                    "An account with this email already exists. Log in and link your {} account first",
+                    user_info.provider
+                )));
+            }
+            Err(AppError::NotFoundError(_)) => {
+                // Pierwsze logowanie przez OAuth zakłada konto - obowiązuje ta sama polityka co przy rejestracji
+                AccountConfig::from_env().ensure_signup_open()?;
+
                 // Generate a random password for OAuth users
                 let random_password = Uuid::new_v4().to_string();
                 
                 // Create user with data from OAuth provider
                 let new_user = crate::models::CreateUserRequest {
-                    username: format!("{}_user", user_info.provider),
-                    email: user_info.email,
+                    username: self.generate_unique_username(&user_info).await?,
+                    email: user_info.email.clone(),
                     password: random_password,
-                    full_name: user_info.name,
+                    full_name: user_info.name.clone(),
                     phone_number: None,
                     role: Some("client".to_string()), // Default role for OAuth users
                 };
                 
                 self.repo.create(new_user).await?
             }
+            Err(e) => return Err(e),
         };
-        
-        // Generate JWT token
-        let token = generate_token(user.id, &user.username, &user.email, &user.role)?;
-        
-        Ok((user, token))
+
+        self.identity_repo
+            .create(user.id, &user_info.provider, &user_info.id, Some(&user_info.email))
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!(
+                "Another {} account is already linked to this user",
+                user_info.provider
+            )))?;
+
+        Ok(user)
+    }
+
+    async fn link_identity(&self, user_id: Uuid, user_info: &OAuthUserInfo) -> Result<UserIdentity, AppError> {
+        if let Some(identity) = self.identity_repo
+            .find_by_provider_user_id(&user_info.provider, &user_info.id)
+            .await?
+        {
+            return if identity.user_id == user_id {
+                Ok(identity)
+            } else {
+                Err(AppError::BadRequest(format!("This {} account is linked to another user", user_info.provider)))
+            };
+        }
+
+        let identity = self.identity_repo
+            .create(user_id, &user_info.provider, &user_info.id, Some(&user_info.email))
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!(
+                "Another {} account is already linked. Unlink it first",
+                user_info.provider
+            )))?;
+
+        tracing::info!("Linked {} account to user {}", user_info.provider, user_id);
+        Ok(identity)
     }
-}
\ No newline at end of file
+
+    // Username derived from the email's local part, with a random suffix when it is already taken
+    async fn generate_unique_username(&self, user_info: &OAuthUserInfo) -> Result<String, AppError> {
+        let mut base: String = user_info.email
+            .split('@')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+            .take(32)
+            .collect::<String>()
+            .to_lowercase();
+        if base.len() < 3 {
+            base = format!("{}_user", user_info.provider);
+        }
+
+        if !self.repo.username_exists(&base).await? {
+            return Ok(base);
+        }
+
+        for _ in 0..5 {
+            let candidate = format!("{}_{}", base, &Uuid::new_v4().simple().to_string()[..6]);
+            if !self.repo.username_exists(&candidate).await? {
+                return Ok(candidate);
+            }
+        }
+
+        Err(AppError::InternalServerError("Failed to generate a unique username".to_string()))
+    }
+}
+
+fn oidc_config(name: &str) -> Result<OidcProviderConfig, AppError> {
+    OidcProviderConfig::from_env(name)
+        .ok_or_else(|| AppError::BadRequest(format!("Unsupported OAuth provider: {}", name)))
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use lazy_static::lazy_static;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::auth_utils::oauth::OAuthUserInfo;
+use crate::error::AppError;
+
+// How long discovery documents and JWKS are reused before being fetched again
+const METADATA_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+// Dokumenty discovery i klucze wystawców, współdzielone między żądaniami
+lazy_static! {
+    static ref ISSUER_CACHE: Mutex<HashMap<String, CachedIssuer>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Clone)]
+struct CachedIssuer {
+    metadata: OidcMetadata,
+    jwks: JwkSet,
+    fetched_at: Instant,
+}
+
+// Names of the ID token claims that make up OAuthUserInfo
+#[derive(Debug, Clone)]
+pub struct OidcClaimMapping {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub email_verified: String,
+}
+
+// Konfiguracja dostawcy OpenID Connect (np. Keycloak), zmienne OIDC_{NAZWA}_*
+#[derive(Debug, Clone)]
+pub struct OidcProviderConfig {
+    pub name: String,
+    pub discovery_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: String,
+    pub claims: OidcClaimMapping,
+}
+
+impl OidcProviderConfig {
+    // Names listed in OIDC_PROVIDERS (comma separated, lowercase)
+    pub fn configured_names() -> Vec<String> {
+        env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    // Returns None if the provider is not listed in OIDC_PROVIDERS or has no discovery URL
+    pub fn from_env(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if !Self::configured_names().contains(&name) {
+            return None;
+        }
+
+        let prefix = format!("OIDC_{}_", name.to_uppercase().replace('-', "_"));
+        let var = |key: &str| env::var(format!("{}{}", prefix, key)).ok().filter(|value| !value.is_empty());
+
+        Some(Self {
+            discovery_url: var("DISCOVERY_URL")?,
+            client_id: var("CLIENT_ID").unwrap_or_default(),
+            client_secret: var("CLIENT_SECRET").unwrap_or_default(),
+            scopes: var("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+            claims: OidcClaimMapping {
+                id: var("ID_CLAIM").unwrap_or_else(|| "sub".to_string()),
+                email: var("EMAIL_CLAIM").unwrap_or_else(|| "email".to_string()),
+                name: var("NAME_CLAIM").unwrap_or_else(|| "name".to_string()),
+                email_verified: var("EMAIL_VERIFIED_CLAIM").unwrap_or_else(|| "email_verified".to_string()),
+            },
+            name,
+        })
+    }
+}
+
+// Fields of the discovery document (/.well-known/openid-configuration) used by the login flow
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+    pub userinfo_endpoint: Option<String>,
+}
+
+// Discovery document of the provider, cached for METADATA_CACHE_TTL
+pub async fn metadata(client: &Client, config: &OidcProviderConfig) -> Result<OidcMetadata, AppError> {
+    Ok(issuer(client, config, false).await?.metadata)
+}
+
+async fn issuer(client: &Client, config: &OidcProviderConfig, force_refresh: bool) -> Result<CachedIssuer, AppError> {
+    if !force_refresh {
+        let cache = ISSUER_CACHE.lock().map_err(|_| AppError::InternalServerError("OIDC cache poisoned".to_string()))?;
+        if let Some(cached) = cache.get(&config.discovery_url) {
+            if cached.fetched_at.elapsed() < METADATA_CACHE_TTL {
+                return Ok(cached.clone());
+            }
+        }
+    }
+
+    let metadata: OidcMetadata = fetch_json(client, &config.discovery_url).await?;
+    let jwks: JwkSet = fetch_json(client, &metadata.jwks_uri).await?;
+
+    let cached = CachedIssuer { metadata, jwks, fetched_at: Instant::now() };
+    ISSUER_CACHE
+        .lock()
+        .map_err(|_| AppError::InternalServerError("OIDC cache poisoned".to_string()))?
+        .insert(config.discovery_url.clone(), cached.clone());
+
+    Ok(cached)
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(client: &Client, url: &str) -> Result<T, AppError> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| AppError::InternalServerError(format!("OIDC request to {} failed: {}", url, e)))?;
+
+    response.json().await
+        .map_err(|e| AppError::InternalServerError(format!("OIDC response from {} is invalid: {}", url, e)))
+}
+
+// Validates the ID token (signature against the issuer's JWKS, issuer, audience, expiry and nonce)
+// and returns its claims
+pub async fn validate_id_token(
+    client: &Client,
+    config: &OidcProviderConfig,
+    id_token: &str,
+    expected_nonce: Option<&str>,
+) -> Result<Map<String, Value>, AppError> {
+    let invalid = || AppError::ValidationError("Invalid ID token".to_string());
+
+    let header = decode_header(id_token).map_err(|_| invalid())?;
+
+    // Tylko algorytmy asymetryczne - HS256 z sekretem klienta i "none" nie są akceptowane
+    if !matches!(
+        header.alg,
+        Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512
+            | Algorithm::PS256 | Algorithm::PS384 | Algorithm::PS512
+            | Algorithm::ES256 | Algorithm::ES384 | Algorithm::EdDSA
+    ) {
+        return Err(invalid());
+    }
+
+    let mut issuer = issuer(client, config, false).await?;
+    let find_key = |jwks: &JwkSet| match &header.kid {
+        Some(kid) => jwks.find(kid).cloned(),
+        None if jwks.keys.len() == 1 => jwks.keys.first().cloned(),
+        None => None,
+    };
+
+    // Nieznany `kid` oznacza zwykle rotację kluczy u wystawcy - pobieramy JWKS ponownie
+    let jwk = match find_key(&issuer.jwks) {
+        Some(jwk) => jwk,
+        None => {
+            issuer = self::issuer(client, config, true).await?;
+            find_key(&issuer.jwks).ok_or_else(invalid)?
+        }
+    };
+
+    let decoding_key = DecodingKey::from_jwk(&jwk).map_err(|_| invalid())?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&issuer.metadata.issuer]);
+    validation.set_audience(&[&config.client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+    let claims = decode::<Map<String, Value>>(id_token, &decoding_key, &validation)
+        .map_err(|e| {
+            tracing::warn!(target: "security", event = "oidc_id_token_rejected", provider = %config.name, "ID token rejected: {}", e);
+            invalid()
+        })?
+        .claims;
+
+    if let Some(expected_nonce) = expected_nonce {
+        if claims.get("nonce").and_then(Value::as_str) != Some(expected_nonce) {
+            tracing::warn!(target: "security", event = "oidc_nonce_mismatch", provider = %config.name, "ID token nonce mismatch");
+            return Err(invalid());
+        }
+    }
+
+    Ok(claims)
+}
+
+// Maps ID token (and optionally userinfo) claims to OAuthUserInfo using the configured claim names
+pub fn map_claims(config: &OidcProviderConfig, claims: &Map<String, Value>) -> Result<OAuthUserInfo, AppError> {
+    let claim = |name: &str| claims.get(name).and_then(|value| match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    });
+
+    let id = claim(&config.claims.id)
+        .ok_or_else(|| AppError::InternalServerError("Missing user ID".to_string()))?;
+
+    let email = claim(&config.claims.email)
+        .ok_or_else(|| AppError::InternalServerError("Missing user email".to_string()))?;
+
+    let name = claim(&config.claims.name)
+        .unwrap_or_else(|| format!("{} User", config.name));
+
+    // Some issuers send the flag as the string "true"
+    let email_verified = match claims.get(&config.claims.email_verified) {
+        Some(Value::Bool(verified)) => *verified,
+        Some(Value::String(verified)) => verified == "true",
+        _ => false,
+    };
+
+    Ok(OAuthUserInfo {
+        id,
+        email,
+        name,
+        provider: config.name.clone(),
+        email_verified,
+    })
+}
@@ -1,38 +1,207 @@
-use bcrypt::{hash, verify, DEFAULT_COST};
+use std::env;
+use std::str::FromStr;
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::{Argon2, Params};
 use crate::error::AppError;
 
-/// Generuje hash hasła z użyciem bcrypt
-pub fn hash_password(password: &str) -> Result<String, AppError> {
-    hash(password, DEFAULT_COST)
-        .map_err(|e| AppError::InternalServerError(format!("Hashing error: {}", e)))
+// Shared password of the accounts from the initial migration; such accounts must change it
+pub const DEFAULT_PASSWORD: &str = "ChangeMe123";
+
+// Algorytm używany do nowych hashy. Zapisane hashe opisują się same (format PHC dla Argon2,
+// `$2b$<koszt>$` dla bcrypt), więc starsze hashe nadal można zweryfikować.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PasswordAlgorithm {
+    Argon2id,
+    Bcrypt,
 }
 
-/// Weryfikuje, czy podane hasło odpowiada hashowi
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
-    verify(password, hash)
-        .map_err(|e| AppError::InternalServerError(format!("Verification error: {}", e)))
+impl PasswordAlgorithm {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "argon2id" => Some(PasswordAlgorithm::Argon2id),
+            "bcrypt" => Some(PasswordAlgorithm::Bcrypt),
+            _ => None,
+        }
+    }
 }
 
-// Funkcja pomocnicza do walidacji siły hasła
-pub fn validate_password(password: &str) -> Result<(), AppError> {
-    if password.len() < 8 {
-        return Err(AppError::ValidationError("Password must be at least 8 characters long".to_string()));
+// Unknown values are refused at startup (validate_password_hash_configuration)
+impl From<&str> for PasswordAlgorithm {
+    fn from(s: &str) -> Self {
+        PasswordAlgorithm::parse(s).unwrap_or(PasswordAlgorithm::Argon2id)
     }
-    
-    // Sprawdź, czy hasło zawiera cyfrę
-    if !password.chars().any(|c| c.is_digit(10)) {
-        return Err(AppError::ValidationError("Password must contain at least one digit".to_string()));
+}
+
+// Password hashing settings; hashes made with other settings are upgraded at the next login
+#[derive(Debug, Clone)]
+pub struct PasswordHashConfig {
+    pub algorithm: PasswordAlgorithm,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub bcrypt_cost: u32,
+}
+
+impl PasswordHashConfig {
+    pub fn from_env() -> Self {
+        let var = |key: &str, default: u32| env::var(key).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+
+        Self {
+            algorithm: PasswordAlgorithm::from(
+                env::var("PASSWORD_HASH_ALGORITHM").unwrap_or_else(|_| "argon2id".to_string()).as_str()
+            ),
+            // Defaults follow the OWASP recommendation for Argon2id (19 MiB, 2 iterations, 1 lane)
+            argon2_memory_kib: var("ARGON2_MEMORY_KIB", 19456),
+            argon2_iterations: var("ARGON2_ITERATIONS", 2),
+            argon2_parallelism: var("ARGON2_PARALLELISM", 1),
+            bcrypt_cost: var("BCRYPT_COST", bcrypt::DEFAULT_COST),
+        }
     }
-    
-    // Sprawdź, czy hasło zawiera dużą literę
-    if !password.chars().any(|c| c.is_uppercase()) {
-        return Err(AppError::ValidationError("Password must contain at least one uppercase letter".to_string()));
+
+    // Hasher for new hashes
+    fn hasher(&self) -> Result<Box<dyn PasswordHasher>, AppError> {
+        Ok(match self.algorithm {
+            PasswordAlgorithm::Argon2id => Box::new(Argon2idHasher::new(self)?),
+            PasswordAlgorithm::Bcrypt => Box::new(BcryptHasher { cost: self.bcrypt_cost }),
+        })
     }
-    
-    // Sprawdź, czy hasło zawiera małą literę
-    if !password.chars().any(|c| c.is_lowercase()) {
-        return Err(AppError::ValidationError("Password must contain at least one lowercase letter".to_string()));
+}
+
+// Sprawdzenie konfiguracji hashowania przy starcie aplikacji - błąd oznacza, że serwer nie powinien wystartować
+pub fn validate_password_hash_configuration() -> Result<(), AppError> {
+    if let Ok(value) = env::var("PASSWORD_HASH_ALGORITHM") {
+        if PasswordAlgorithm::parse(&value).is_none() {
+            return Err(AppError::InternalServerError(format!(
+                "Unsupported PASSWORD_HASH_ALGORITHM '{}' - use argon2id or bcrypt",
+                value
+            )));
+        }
     }
-    
-    Ok(())
-}
\ No newline at end of file
+
+    // Błędne parametry Argon2 zatrzymują start zamiast pierwszej rejestracji
+    PasswordHashConfig::from_env().hasher().map(|_| ())
+}
+
+// Jeden algorytm hashowania haseł
+trait PasswordHasher {
+    /// Whether the stored hash was made with this algorithm
+    fn recognizes(&self, hash: &str) -> bool;
+    fn hash(&self, password: &str) -> Result<String, AppError>;
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError>;
+    /// Whether the hash (of this algorithm) was made with other parameters than the configured ones
+    fn is_outdated(&self, hash: &str) -> bool;
+}
+
+struct Argon2idHasher {
+    params: Params,
+}
+
+impl Argon2idHasher {
+    fn new(config: &PasswordHashConfig) -> Result<Self, AppError> {
+        let params = Params::new(config.argon2_memory_kib, config.argon2_iterations, config.argon2_parallelism, None)
+            .map_err(|e| AppError::InternalServerError(format!("Invalid Argon2 parameters: {}", e)))?;
+
+        Ok(Self { params })
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, self.params.clone())
+    }
+}
+
+impl PasswordHasher for Argon2idHasher {
+    fn recognizes(&self, hash: &str) -> bool {
+        hash.starts_with("$argon2id$")
+    }
+
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| AppError::InternalServerError(format!("Hashing error: {}", e)))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError> {
+        let parsed = PasswordHash::new(hash)
+            .map_err(|e| AppError::InternalServerError(format!("Verification error: {}", e)))?;
+
+        // Parametry są odczytywane z hasha, więc hashe ze starszymi ustawieniami też pasują
+        Ok(Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+    }
+
+    fn is_outdated(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                parsed.version != Some(argon2::Version::V0x13.into())
+                    || params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+struct BcryptHasher {
+    cost: u32,
+}
+
+impl PasswordHasher for BcryptHasher {
+    fn recognizes(&self, hash: &str) -> bool {
+        ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix))
+    }
+
+    fn hash(&self, password: &str) -> Result<String, AppError> {
+        bcrypt::hash(password, self.cost)
+            .map_err(|e| AppError::InternalServerError(format!("Hashing error: {}", e)))
+    }
+
+    fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError> {
+        bcrypt::verify(password, hash)
+            .map_err(|e| AppError::InternalServerError(format!("Verification error: {}", e)))
+    }
+
+    fn is_outdated(&self, hash: &str) -> bool {
+        bcrypt::HashParts::from_str(hash).map_or(true, |parts| parts.get_cost() != self.cost)
+    }
+}
+
+// Every supported algorithm, for verifying stored hashes
+fn known_hashers(config: &PasswordHashConfig) -> Result<Vec<Box<dyn PasswordHasher>>, AppError> {
+    Ok(vec![
+        Box::new(Argon2idHasher::new(config)?),
+        Box::new(BcryptHasher { cost: config.bcrypt_cost }),
+    ])
+}
+
+/// Generuje hash hasła skonfigurowanym algorytmem (domyślnie Argon2id)
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    PasswordHashConfig::from_env().hasher()?.hash(password)
+}
+
+/// Weryfikuje, czy podane hasło odpowiada hashowi (Argon2id lub bcrypt)
+pub fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
+    let config = PasswordHashConfig::from_env();
+
+    match known_hashers(&config)?.into_iter().find(|hasher| hasher.recognizes(hash)) {
+        Some(hasher) => hasher.verify(password, hash),
+        None => Err(AppError::InternalServerError("Verification error: unknown password hash format".to_string())),
+    }
+}
+
+/// Whether the hash should be replaced: made with another algorithm or other parameters than configured
+pub fn password_needs_rehash(hash: &str) -> bool {
+    let config = PasswordHashConfig::from_env();
+
+    match config.hasher() {
+        Ok(hasher) => !hasher.recognizes(hash) || hasher.is_outdated(hash),
+        Err(_) => false,
+    }
+}
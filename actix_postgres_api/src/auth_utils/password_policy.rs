@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::models::User;
+
+// Lista popularnych i wyciekłych haseł dołączona do binarki (małe litery, jedno hasło w wierszu)
+const BUNDLED_COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+// Personal values shorter than this are not checked (e.g. a two-letter name)
+const MIN_PERSONAL_TOKEN_LENGTH: usize = 3;
+
+// Lista wczytywana raz - wbudowana plus opcjonalny plik z PASSWORD_BLOCKLIST_PATH
+lazy_static! {
+    static ref COMMON_PASSWORDS: HashSet<String> = load_common_passwords();
+}
+
+fn load_common_passwords() -> HashSet<String> {
+    let mut passwords: HashSet<String> = parse_password_list(BUNDLED_COMMON_PASSWORDS);
+
+    if let Ok(path) = env::var("PASSWORD_BLOCKLIST_PATH") {
+        match fs::read_to_string(&path) {
+            Ok(contents) => passwords.extend(parse_password_list(&contents)),
+            Err(e) => tracing::error!("Failed to read password blocklist {}: {}", path, e),
+        }
+    }
+
+    passwords
+}
+
+fn parse_password_list(contents: &str) -> HashSet<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// Password policy settings; every rule can be tuned or turned off with environment variables
+#[derive(Debug, Clone)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub require_character_classes: bool,  // at least one digit, uppercase and lowercase letter
+    pub min_entropy_bits: f64,            // 0 disables the check
+    pub reject_personal_info: bool,       // username, email or full name inside the password
+    pub reject_common: bool,              // bundled list plus PASSWORD_BLOCKLIST_PATH
+}
+
+impl PasswordPolicyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            min_length: env::var("PASSWORD_MIN_LENGTH")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .unwrap_or(8),
+            require_character_classes: env::var("PASSWORD_REQUIRE_CHARACTER_CLASSES")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            min_entropy_bits: env::var("PASSWORD_MIN_ENTROPY_BITS")
+                .unwrap_or_else(|_| "40".to_string())
+                .parse()
+                .unwrap_or(40.0),
+            reject_personal_info: env::var("PASSWORD_REJECT_PERSONAL_INFO")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+            reject_common: env::var("PASSWORD_REJECT_COMMON")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
+        }
+    }
+}
+
+// Naruszona reguła polityki haseł - wszystkie naruszenia są zwracane w jednej odpowiedzi
+#[derive(Debug, Clone, Serialize)]
+pub struct PasswordViolation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+impl PasswordViolation {
+    fn new(rule: &'static str, message: impl Into<String>) -> Self {
+        Self { rule, message: message.into() }
+    }
+}
+
+// Account data the password must not contain; fields that are not known yet stay None
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PasswordContext<'a> {
+    pub username: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub full_name: Option<&'a str>,
+}
+
+impl<'a> PasswordContext<'a> {
+    pub fn new(username: &'a str, email: &'a str, full_name: &'a str) -> Self {
+        Self {
+            username: Some(username),
+            email: Some(email),
+            full_name: Some(full_name),
+        }
+    }
+
+    // Lowercase values checked against the password: the username, the email and its local part,
+    // the full name and each of its parts
+    fn tokens(&self) -> Vec<String> {
+        let mut tokens = Vec::new();
+
+        if let Some(username) = self.username {
+            tokens.push(username.to_lowercase());
+        }
+
+        if let Some(email) = self.email {
+            let email = email.to_lowercase();
+            if let Some((local_part, _)) = email.split_once('@') {
+                tokens.push(local_part.to_string());
+            }
+            tokens.push(email);
+        }
+
+        if let Some(full_name) = self.full_name {
+            let full_name = full_name.to_lowercase();
+            tokens.extend(full_name.split(|c: char| c.is_whitespace() || c == '-').map(str::to_string));
+            tokens.push(full_name.split_whitespace().collect());
+        }
+
+        tokens.retain(|token| token.chars().count() >= MIN_PERSONAL_TOKEN_LENGTH);
+        tokens
+    }
+}
+
+impl<'a> From<&'a User> for PasswordContext<'a> {
+    fn from(user: &'a User) -> Self {
+        Self::new(&user.username, &user.email, &user.full_name)
+    }
+}
+
+// Estimated entropy in bits: size of the used character pool times the length,
+// where runs of the same character count once (so "aaaaaaaA1" is not rated as long)
+pub fn estimate_entropy(password: &str) -> f64 {
+    let mut pool = 0u32;
+    if password.chars().any(|c| c.is_ascii_lowercase()) { pool += 26; }
+    if password.chars().any(|c| c.is_ascii_uppercase()) { pool += 26; }
+    if password.chars().any(|c| c.is_ascii_digit()) { pool += 10; }
+    if password.chars().any(|c| c.is_ascii_punctuation() || c == ' ') { pool += 33; }
+    if !password.is_ascii() { pool += 100; }
+
+    if pool == 0 {
+        return 0.0;
+    }
+
+    let mut chars: Vec<char> = password.chars().collect();
+    chars.dedup();
+
+    chars.len() as f64 * f64::from(pool).log2()
+}
+
+fn is_common(password: &str) -> bool {
+    let password = password.to_lowercase();
+    // "Summer2024!" is as weak as "summer"
+    let base = password.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_ascii_punctuation());
+
+    COMMON_PASSWORDS.contains(&password) || (!base.is_empty() && COMMON_PASSWORDS.contains(base))
+}
+
+// Sprawdza hasło ze wszystkimi regułami polityki i zwraca listę naruszeń (pustą, gdy hasło jest poprawne)
+pub fn check_password(password: &str, context: &PasswordContext, config: &PasswordPolicyConfig) -> Vec<PasswordViolation> {
+    let mut violations = Vec::new();
+
+    if password.chars().count() < config.min_length {
+        violations.push(PasswordViolation::new(
+            "min_length",
+            format!("Password must be at least {} characters long", config.min_length),
+        ));
+    }
+
+    if config.require_character_classes {
+        if !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordViolation::new("digit", "Password must contain at least one digit"));
+        }
+
+        if !password.chars().any(|c| c.is_uppercase()) {
+            violations.push(PasswordViolation::new("uppercase", "Password must contain at least one uppercase letter"));
+        }
+
+        if !password.chars().any(|c| c.is_lowercase()) {
+            violations.push(PasswordViolation::new("lowercase", "Password must contain at least one lowercase letter"));
+        }
+    }
+
+    if config.min_entropy_bits > 0.0 && estimate_entropy(password) < config.min_entropy_bits {
+        violations.push(PasswordViolation::new(
+            "min_entropy",
+            "Password is too predictable, use a longer password or more kinds of characters",
+        ));
+    }
+
+    if config.reject_personal_info {
+        let lowercase = password.to_lowercase();
+        if context.tokens().iter().any(|token| lowercase.contains(token.as_str())) {
+            violations.push(PasswordViolation::new(
+                "personal_info",
+                "Password must not contain the username, email or name",
+            ));
+        }
+    }
+
+    if config.reject_common && is_common(password) {
+        violations.push(PasswordViolation::new(
+            "common_password",
+            "Password is too common or has appeared in a data breach",
+        ));
+    }
+
+    violations
+}
+
+// Funkcja pomocnicza do walidacji hasła zgodnie z polityką z konfiguracji
+pub fn validate_password(password: &str, context: &PasswordContext) -> Result<(), AppError> {
+    let violations = check_password(password, context, &PasswordPolicyConfig::from_env());
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::PasswordPolicy(violations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entropy_grows_with_character_pool() {
+        assert_eq!(estimate_entropy(""), 0.0);
+        assert_eq!(estimate_entropy("abcd"), 4.0 * 26f64.log2());
+        assert_eq!(estimate_entropy("abCD12"), 6.0 * 62f64.log2());
+        assert_eq!(estimate_entropy("aB1!"), 4.0 * 95f64.log2());
+        assert!(estimate_entropy("zażółć") > estimate_entropy("zazolc"));
+    }
+
+    #[test]
+    fn entropy_counts_repeated_runs_once() {
+        assert_eq!(estimate_entropy("aaaaaaaA1"), estimate_entropy("aA1"));
+        // Powtórzenia rozdzielone innymi znakami liczą się osobno
+        assert_eq!(estimate_entropy("abab"), 4.0 * 26f64.log2());
+    }
+
+    #[test]
+    fn common_passwords_ignore_case() {
+        assert!(is_common("password"));
+        assert!(is_common("PassWord"));
+        assert!(!is_common("Granite12345"));
+    }
+
+    #[test]
+    fn common_passwords_strip_trailing_digits_and_punctuation() {
+        assert!(is_common("Summer2024!"));
+        assert!(is_common("password123"));
+        assert!(is_common("qwerty!!"));
+        // Only the trailing part is stripped
+        assert!(!is_common("2024summer"));
+        assert!(!is_common("2024!"));
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::HashSet;
+
+// Nazwy uprawnień w formacie `zasób:akcja[:zakres]`. Zakres `any` obejmuje dane innych
+// użytkowników i implikuje zakres `own` - rola z `appointments:write:any` może też
+// edytować własne wizyty.
+pub const USERS_READ_ANY: &str = "users:read:any";
+pub const USERS_CREATE: &str = "users:create";
+pub const USERS_DELETE_ANY: &str = "users:delete:any";
+pub const USERS_ROLE_ASSIGN: &str = "users:role:assign";
+pub const APPOINTMENTS_CREATE: &str = "appointments:create";
+pub const APPOINTMENTS_READ_ANY: &str = "appointments:read:any";
+pub const TWO_FACTOR_ENROLL: &str = "two_factor:enroll";
+pub const INVITATIONS_CREATE: &str = "invitations:create";
+pub const API_KEYS_MANAGE: &str = "api_keys:manage";
+pub const ROLES_MANAGE: &str = "roles:manage";
+pub const USERS_IMPERSONATE: &str = "users:impersonate";
+pub const AUDIT_READ: &str = "audit:read";
+
+// Scoped actions; the handler picks `:own` or `:any` depending on who owns the resource
+pub const USERS_READ: &str = "users:read";
+pub const USERS_WRITE: &str = "users:write";
+pub const APPOINTMENTS_READ: &str = "appointments:read";
+pub const APPOINTMENTS_WRITE: &str = "appointments:write";
+pub const APPOINTMENTS_COMPLETE: &str = "appointments:complete";
+pub const APPOINTMENTS_DELETE: &str = "appointments:delete";
+pub const INVITATIONS_READ: &str = "invitations:read";
+pub const INVITATIONS_REVOKE: &str = "invitations:revoke";
+
+/// Whether a granted permission satisfies the required one
+pub fn implies(granted: &str, required: &str) -> bool {
+    if granted == required {
+        return true;
+    }
+
+    match (granted.strip_suffix(":any"), required.strip_suffix(":own")) {
+        (Some(granted_action), Some(required_action)) => granted_action == required_action,
+        _ => false,
+    }
+}
+
+/// Whether any of the granted permissions satisfies the required one
+pub fn is_granted(granted: &HashSet<String>, required: &str) -> bool {
+    granted.iter().any(|permission| implies(permission, required))
+}
@@ -1,9 +1,18 @@
 use crate::error::AppError;
 
-// Funkcja walidująca rolę użytkownika
+// Funkcja walidująca format nazwy roli - istnienie roli sprawdzane jest w bazie
 pub fn validate_role(role: &str) -> Result<String, AppError> {
-    match role.to_lowercase().as_str() {
-        "client" | "trainer" | "admin" => Ok(role.to_lowercase()),
-        _ => Err(AppError::ValidationError("Invalid role. Must be 'client', 'trainer', or 'admin'".to_string()))
+    let role = role.trim().to_lowercase();
+
+    let valid = (2..=50).contains(&role.len())
+        && role.starts_with(|c: char| c.is_ascii_lowercase())
+        && role.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if !valid {
+        return Err(AppError::ValidationError(
+            "Invalid role. Must be 2-50 characters: lowercase letters, digits or underscores".to_string()
+        ));
     }
-}
\ No newline at end of file
+
+    Ok(role)
+}
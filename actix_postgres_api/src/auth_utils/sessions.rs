@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{DateTime, Duration, Utc};
+use lazy_static::lazy_static;
+use uuid::Uuid;
+
+use crate::auth_utils::jwt::JwtConfig;
+
+// Sesje unieważnione w tym procesie, z czasem, po którym ich tokeny dostępu i tak wygasną.
+// Pozwala verify_token odrzucić token bez zapytania do bazy; o sesjach unieważnionych przez
+// inne instancje AuthUser dowiaduje się z tabeli sessions.
+lazy_static! {
+    static ref REVOKED_SESSIONS: Mutex<HashMap<Uuid, DateTime<Utc>>> = Mutex::new(HashMap::new());
+}
+
+pub fn mark_revoked(session_ids: &[Uuid]) {
+    let now = Utc::now();
+    // Access tokens of the session issued before the revocation expire by then
+    let forget_at = now + Duration::seconds(JwtConfig::from_env().expiration());
+
+    if let Ok(mut revoked) = REVOKED_SESSIONS.lock() {
+        revoked.retain(|_, until| *until > now);
+        for id in session_ids {
+            revoked.insert(*id, forget_at);
+        }
+    }
+}
+
+pub fn is_revoked(session_id: &Uuid) -> bool {
+    REVOKED_SESSIONS
+        .lock()
+        .map(|revoked| revoked.get(session_id).is_some_and(|until| *until > Utc::now()))
+        .unwrap_or(false)
+}
@@ -0,0 +1,17 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+// Number of random bytes in an opaque token (hex encoded to twice as many characters)
+const TOKEN_BYTES: usize = 32;
+
+/// Generates a random opaque token (refresh tokens, one-time links, etc.)
+pub fn generate_opaque_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rand::rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Hashes an opaque token with SHA-256 so that only the digest is stored in the database
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
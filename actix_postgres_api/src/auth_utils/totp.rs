@@ -0,0 +1,100 @@
+use std::env;
+use chrono::Utc;
+use rand::RngCore;
+use totp_rs::{Algorithm, Secret, TOTP};
+use crate::error::AppError;
+
+// Parametry RFC 6238 zgodne z popularnymi aplikacjami (Google Authenticator, Authy, 1Password)
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+// Accept codes from one step before and after the current one to tolerate clock drift
+const TOTP_SKEW: i64 = 1;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_BYTES: usize = 5;
+
+// Konfiguracja uwierzytelniania dwuskładnikowego
+#[derive(Debug, Clone)]
+pub struct TotpConfig {
+    pub issuer: String,             // Name shown in the authenticator app
+    pub challenge_expiration: i64,  // Lifetime of the login challenge token in seconds
+}
+
+impl TotpConfig {
+    pub fn from_env() -> Self {
+        Self {
+            issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "Actix Postgres API".to_string()),
+            challenge_expiration: env::var("TWO_FACTOR_CHALLENGE_EXPIRATION")
+                .unwrap_or_else(|_| "300".to_string()) // 5 minut domyślnie
+                .parse()
+                .unwrap_or(300),
+        }
+    }
+}
+
+/// Generates a new random TOTP secret, base32 encoded
+pub fn generate_totp_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+fn build_totp(secret: &str, account_name: &str) -> Result<TOTP, AppError> {
+    let secret_bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::InternalServerError(format!("Invalid TOTP secret: {:?}", e)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP,
+        secret_bytes,
+        Some(TotpConfig::from_env().issuer.replace(':', "")),
+        account_name.to_string(),
+    )
+    .map_err(|e| AppError::InternalServerError(format!("TOTP setup error: {:?}", e)))
+}
+
+/// Builds the otpauth:// provisioning URI that authenticator apps read from a QR code
+pub fn provisioning_uri(secret: &str, account_name: &str) -> Result<String, AppError> {
+    Ok(build_totp(secret, account_name)?.get_url())
+}
+
+/// Checks a TOTP code and returns the time step it belongs to.
+/// Steps up to `last_used_step` are rejected so that a code cannot be replayed.
+pub fn verify_totp_code(secret: &str, code: &str, last_used_step: Option<i64>) -> Result<Option<i64>, AppError> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let totp = build_totp(secret, "")?;
+    let current_step = Utc::now().timestamp() / TOTP_STEP as i64;
+
+    for step in (current_step - TOTP_SKEW)..=(current_step + TOTP_SKEW) {
+        if last_used_step.is_some_and(|last| step <= last) {
+            continue;
+        }
+        if totp.check(code, step as u64 * TOTP_STEP) {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Generates a fresh set of single-use recovery codes (shown to the user once)
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+            rand::rng().fill_bytes(&mut bytes);
+            let code = hex::encode(bytes);
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// Normalizes a recovery code typed by the user before hashing (case and dashes are ignored)
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_lowercase()
+}
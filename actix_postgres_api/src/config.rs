@@ -10,6 +10,7 @@ pub struct Config {
     pub ssl_cert_path: Option<String>,
     pub ssl_key_path: Option<String>,
     pub use_ssl: bool,
+    pub app_env: String,  // development, staging, production... (production when unset)
 }
 
 impl Config {
@@ -29,6 +30,12 @@ impl Config {
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .unwrap_or(false),
+            // Bez APP_ENV obowiązują zasady produkcyjne - tryb deweloperski trzeba włączyć jawnie
+            app_env: env::var("APP_ENV").unwrap_or_else(|_| "production".to_string()),
         })
     }
+
+    pub fn is_development(&self) -> bool {
+        self.app_env.eq_ignore_ascii_case("development")
+    }
 }
\ No newline at end of file
@@ -0,0 +1,124 @@
+use crate::error::AppError;
+use crate::models::{ApiKey, CreateApiKeyRequest};
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct ApiKeyRepository {
+    pool: PgPool,
+}
+
+impl ApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        request: &CreateApiKeyRequest,
+        key_prefix: &str,
+        key_hash: &str,
+        created_by: Uuid,
+    ) -> Result<ApiKey, AppError> {
+        let params = format!("user_id={}, name={}, scopes={:?}", request.user_id, request.name, request.scopes);
+        let span = create_db_span(
+            "create_api_key",
+            "INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes, expires_at, created_by) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "api_keys", || async {
+            let key = sqlx::query_as::<_, ApiKey>(
+                r#"
+                INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scopes, expires_at, created_by)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING *
+                "#
+            )
+            .bind(request.user_id)
+            .bind(&request.name)
+            .bind(key_prefix)
+            .bind(key_hash)
+            .bind(&request.scopes)
+            .bind(request.expires_at)
+            .bind(created_by)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(key)
+        }).instrument(span).await
+    }
+
+    // All keys, or only the keys of `user_id`, newest first
+    pub async fn find_all(&self, user_id: Option<Uuid>) -> Result<Vec<ApiKey>, AppError> {
+        let params = format!("user_id={:?}", user_id);
+        let span = create_db_span(
+            "find_api_keys",
+            "SELECT * FROM api_keys WHERE ($1::uuid IS NULL OR user_id = $1) ORDER BY created_at DESC",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "api_keys", || async {
+            let keys = sqlx::query_as::<_, ApiKey>(
+                "SELECT * FROM api_keys WHERE ($1::uuid IS NULL OR user_id = $1) ORDER BY created_at DESC"
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(keys)
+        }).instrument(span).await
+    }
+
+    // Finds an active key and records its use in the same statement; returns None for unknown,
+    // revoked or expired keys
+    pub async fn use_key(&self, key_hash: &str) -> Result<Option<ApiKey>, AppError> {
+        let span = create_db_span(
+            "use_api_key",
+            "UPDATE api_keys SET last_used_at = NOW() WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW()) RETURNING *",
+            "key_hash=<redacted>",
+        );
+
+        DbMetrics::track("UPDATE", "api_keys", || async {
+            let key = sqlx::query_as::<_, ApiKey>(
+                r#"
+                UPDATE api_keys
+                SET last_used_at = NOW()
+                WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+                RETURNING *
+                "#
+            )
+            .bind(key_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(key)
+        }).instrument(span).await
+    }
+
+    // Returns None if the key does not exist or was already revoked
+    pub async fn revoke(&self, id: Uuid) -> Result<Option<ApiKey>, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "revoke_api_key",
+            "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "api_keys", || async {
+            let key = sqlx::query_as::<_, ApiKey>(
+                "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL RETURNING *"
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(key)
+        }).instrument(span).await
+    }
+}
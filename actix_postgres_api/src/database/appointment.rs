@@ -2,6 +2,7 @@ use crate::error::AppError;
 use crate::models::appointment::{Appointment, CreateAppointmentRequest, UpdateAppointmentRequest, AppointmentStatus, AppointmentType, AppointmentWithNames};
 use crate::monitoring::DbMetrics;
 use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
 use sqlx::{postgres::PgPool, types::Uuid};
 use tracing::Instrument;
 
@@ -129,6 +130,28 @@ impl AppointmentRepository {
         }).instrument(span).await
     }
     
+    // Appointments where the user is either the client or the trainer
+    pub async fn find_by_participant(&self, user_id: Uuid) -> Result<Vec<Appointment>, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "find_appointments_by_participant",
+            "SELECT * FROM appointments WHERE client_id = $1 OR trainer_id = $1 ORDER BY appointment_date ASC, start_time ASC",
+            &params,
+        );
+        
+        DbMetrics::track("SELECT", "appointments", || async {
+            let appointments = sqlx::query_as::<_, Appointment>(
+                "SELECT * FROM appointments WHERE client_id = $1 OR trainer_id = $1 ORDER BY appointment_date ASC, start_time ASC"
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+            
+            Ok(appointments)
+        }).instrument(span).await
+    }
+    
     pub async fn find_by_client_id_with_names(&self, client_id: Uuid) -> Result<Vec<AppointmentWithNames>, AppError> {
         let params = format!("client_id={}", client_id);
         let span = create_db_span(
@@ -237,7 +260,8 @@ impl AppointmentRepository {
         }).instrument(span).await
     }
 
-    pub async fn update(&self, id: Uuid, appointment: UpdateAppointmentRequest) -> Result<Appointment, AppError> {
+    // With `expected_versions` the row is only updated while its `updated_at` is one of them
+    pub async fn update(&self, id: Uuid, appointment: UpdateAppointmentRequest, expected_versions: Option<&[DateTime<Utc>]>) -> Result<Appointment, AppError> {
         let params = format!("id={}", id);
         let span = create_db_span(
             "update_appointment",
@@ -246,96 +270,99 @@ impl AppointmentRepository {
         );
         
         DbMetrics::track("UPDATE", "appointments", || async {
-            // First check if appointment exists
-            let existing = self.find_by_id(id).await?;
-            
-            // Build dynamic query based on provided fields
+            // Build dynamic query based on provided fields; every column follows updated_at, so each one starts with a comma
             let mut query_builder = sqlx::QueryBuilder::new(
                 "UPDATE appointments SET updated_at = NOW()"
             );
             
-            let mut needs_comma = false;
-            
             if let Some(type_) = &appointment.type_ {
                 // Validate type
                 let valid_type = AppointmentType::from(type_.as_str()).to_string();
                 
                 query_builder.push(", \"type\" = ");
                 query_builder.push_bind(valid_type);
-                needs_comma = true;
             }
             
             if let Some(date) = appointment.appointment_date {
-                if needs_comma { query_builder.push(", "); }
-                query_builder.push("appointment_date = ");
+                query_builder.push(", appointment_date = ");
                 query_builder.push_bind(date);
-                needs_comma = true;
             }
             
             if let Some(time) = appointment.start_time {
-                if needs_comma { query_builder.push(", "); }
-                query_builder.push("start_time = ");
+                query_builder.push(", start_time = ");
                 query_builder.push_bind(time);
-                needs_comma = true;
             }
             
             if let Some(duration) = appointment.duration_minutes {
-                if needs_comma { query_builder.push(", "); }
-                query_builder.push("duration_minutes = ");
+                query_builder.push(", duration_minutes = ");
                 query_builder.push_bind(duration);
-                needs_comma = true;
             }
             
             if let Some(status) = &appointment.status {
                 // Validate status
                 let valid_status = AppointmentStatus::from(status.as_str()).to_string();
                 
-                if needs_comma { query_builder.push(", "); }
-                query_builder.push("status = ");
+                query_builder.push(", status = ");
                 query_builder.push_bind(valid_status);
-                needs_comma = true;
             }
             
             if let Some(location) = &appointment.location {
-                if needs_comma { query_builder.push(", "); }
-                query_builder.push("location = ");
+                query_builder.push(", location = ");
                 query_builder.push_bind(location);
             }
             
             query_builder.push(" WHERE id = ");
             query_builder.push_bind(id);
+            if let Some(versions) = expected_versions {
+                query_builder.push(" AND updated_at = ANY(");
+                query_builder.push_bind(versions.to_vec());
+                query_builder.push(")");
+            }
             query_builder.push(" RETURNING *");
             
             let query = query_builder.build_query_as::<Appointment>();
             
             let updated_appointment = query
-                .fetch_one(&self.pool)
+                .fetch_optional(&self.pool)
                 .await
                 .map_err(AppError::DatabaseError)?;
             
-            Ok(updated_appointment)
+            match updated_appointment {
+                Some(appointment) => Ok(appointment),
+                None => Err(self.version_conflict(id).await),
+            }
         }).instrument(span).await
     }
 
-    pub async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+    pub async fn delete(&self, id: Uuid, expected_versions: Option<&[DateTime<Utc>]>) -> Result<(), AppError> {
         let params = format!("id={}", id);
         let span = create_db_span(
             "delete_appointment",
-            "DELETE FROM appointments WHERE id = $1",
+            "DELETE FROM appointments WHERE id = $1 AND ($2::timestamptz[] IS NULL OR updated_at = ANY($2))",
             &params,
         );
         
         DbMetrics::track("DELETE", "appointments", || async {
-            // First check if appointment exists
-            let _ = self.find_by_id(id).await?;
-            
-            sqlx::query("DELETE FROM appointments WHERE id = $1")
+            let result = sqlx::query("DELETE FROM appointments WHERE id = $1 AND ($2::timestamptz[] IS NULL OR updated_at = ANY($2))")
                 .bind(id)
+                .bind(expected_versions)
                 .execute(&self.pool)
                 .await
                 .map_err(AppError::DatabaseError)?;
             
+            if result.rows_affected() == 0 {
+                return Err(self.version_conflict(id).await);
+            }
+            
             Ok(())
         }).instrument(span).await
     }
+
+    // Nothing was written: either the appointment is gone or it no longer has the expected version
+    async fn version_conflict(&self, id: Uuid) -> AppError {
+        match self.find_by_id(id).await {
+            Ok(_) => AppError::PreconditionFailed(format!("Appointment with id {} has been modified by another request", id)),
+            Err(e) => e,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,112 @@
+use crate::error::AppError;
+use crate::models::{AuditEvent, AuditEventQuery, NewAuditEvent, PageRequest};
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use sqlx::postgres::PgPool;
+use tracing::Instrument;
+
+// Wspólny warunek WHERE zapytań o zdarzenia (wszystkie filtry są opcjonalne)
+const AUDIT_EVENT_FILTER: &str = r#"
+    ($1::uuid IS NULL OR actor_id = $1)
+    AND ($2::varchar IS NULL OR action = $2)
+    AND ($3::varchar IS NULL OR target_type = $3)
+    AND ($4::uuid IS NULL OR target_id = $4)
+    AND ($5::timestamptz IS NULL OR created_at >= $5)
+    AND ($6::timestamptz IS NULL OR created_at < $6)
+"#;
+
+pub struct AuditEventRepository {
+    pool: PgPool,
+}
+
+impl AuditEventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, event: &NewAuditEvent) -> Result<(), AppError> {
+        let params = format!(
+            "actor_id={:?}, action={}, target_type={:?}, target_id={:?}",
+            event.actor_id, event.action, event.target_type, event.target_id
+        );
+        let span = create_db_span(
+            "create_audit_event",
+            "INSERT INTO audit_events (actor_id, impersonator_id, api_key_id, action, target_type, target_id, changes, ip_address, request_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "audit_events", || async {
+            sqlx::query(
+                r#"
+                INSERT INTO audit_events (actor_id, impersonator_id, api_key_id, action, target_type, target_id, changes, ip_address, request_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#
+            )
+            .bind(event.actor_id)
+            .bind(event.impersonator_id)
+            .bind(event.api_key_id)
+            .bind(event.action)
+            .bind(event.target_type)
+            .bind(event.target_id)
+            .bind(&event.changes)
+            .bind(event.ip_address.as_deref())
+            .bind(event.request_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // One page of events matching the filters, newest first, with the total number of matches
+    pub async fn find_page(
+        &self,
+        filter: &AuditEventQuery,
+        page: PageRequest,
+    ) -> Result<(Vec<AuditEvent>, i64), AppError> {
+        let params = format!(
+            "actor_id={:?}, action={:?}, target_type={:?}, target_id={:?}, from={:?}, to={:?}, page={}, per_page={}",
+            filter.actor_id, filter.action, filter.target_type, filter.target_id, filter.from, filter.to,
+            page.page, page.per_page
+        );
+        let select_sql = format!(
+            "SELECT * FROM audit_events WHERE {} ORDER BY created_at DESC, id DESC LIMIT $7 OFFSET $8",
+            AUDIT_EVENT_FILTER
+        );
+        let count_sql = format!("SELECT COUNT(*) FROM audit_events WHERE {}", AUDIT_EVENT_FILTER);
+        let span = create_db_span(
+            "find_audit_events",
+            "SELECT * FROM audit_events WHERE <filters> ORDER BY created_at DESC, id DESC LIMIT $7 OFFSET $8",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "audit_events", || async {
+            let events = sqlx::query_as::<_, AuditEvent>(&select_sql)
+                .bind(filter.actor_id)
+                .bind(filter.action.as_deref())
+                .bind(filter.target_type.as_deref())
+                .bind(filter.target_id)
+                .bind(filter.from)
+                .bind(filter.to)
+                .bind(page.per_page)
+                .bind(page.offset())
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            let total: i64 = sqlx::query_scalar(&count_sql)
+                .bind(filter.actor_id)
+                .bind(filter.action.as_deref())
+                .bind(filter.target_type.as_deref())
+                .bind(filter.target_id)
+                .bind(filter.from)
+                .bind(filter.to)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok((events, total))
+        }).instrument(span).await
+    }
+}
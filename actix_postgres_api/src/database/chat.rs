@@ -78,6 +78,21 @@ impl ChatRepository {
         .await
     }
 
+    // All messages sent by a user, oldest first
+    pub async fn get_messages_by_sender(pool: &PgPool, sender_id: Uuid) -> Result<Vec<ChatMessage>, Error> {
+        sqlx::query_as!(ChatMessage,
+            r#"
+            SELECT id, sender_id, sender_name, content, room_id, created_at
+            FROM chat_messages
+            WHERE sender_id = $1
+            ORDER BY created_at ASC
+            "#,
+            sender_id
+        )
+        .fetch_all(pool)
+        .await
+    }
+
     // Create a new chat room
     pub async fn create_room(
         pool: &PgPool,
@@ -0,0 +1,87 @@
+use crate::error::AppError;
+use crate::models::EmailVerificationToken;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct EmailVerificationRepository {
+    pool: PgPool,
+}
+
+impl EmailVerificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Stores a new verification token, invalidating any unused tokens issued earlier for the same user
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<EmailVerificationToken, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "create_email_verification_token",
+            "INSERT INTO email_verification_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "email_verification_tokens", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            sqlx::query(
+                "UPDATE email_verification_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL"
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            let token = sqlx::query_as::<_, EmailVerificationToken>(
+                r#"
+                INSERT INTO email_verification_tokens (user_id, token_hash, expires_at)
+                VALUES ($1, $2, $3)
+                RETURNING *
+                "#
+            )
+            .bind(user_id)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(token)
+        }).instrument(span).await
+    }
+
+    // Atomically marks a valid token as used; returns None for unknown, used or expired tokens
+    pub async fn consume(&self, token_hash: &str) -> Result<Option<EmailVerificationToken>, AppError> {
+        let span = create_db_span(
+            "consume_email_verification_token",
+            "UPDATE email_verification_tokens SET used_at = NOW() WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING *",
+            "token_hash=<redacted>",
+        );
+
+        DbMetrics::track("UPDATE", "email_verification_tokens", || async {
+            let token = sqlx::query_as::<_, EmailVerificationToken>(
+                r#"
+                UPDATE email_verification_tokens
+                SET used_at = NOW()
+                WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+                RETURNING *
+                "#
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(token)
+        }).instrument(span).await
+    }
+}
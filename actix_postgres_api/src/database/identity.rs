@@ -0,0 +1,140 @@
+use crate::error::AppError;
+use crate::models::UserIdentity;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct IdentityRepository {
+    pool: PgPool,
+}
+
+impl IdentityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_by_provider_user_id(
+        &self,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> Result<Option<UserIdentity>, AppError> {
+        let params = format!("provider={}, provider_user_id={}", provider, provider_user_id);
+        let span = create_db_span(
+            "find_user_identity",
+            "SELECT * FROM user_identities WHERE provider = $1 AND provider_user_id = $2",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "user_identities", || async {
+            let identity = sqlx::query_as::<_, UserIdentity>(
+                "SELECT * FROM user_identities WHERE provider = $1 AND provider_user_id = $2"
+            )
+            .bind(provider)
+            .bind(provider_user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(identity)
+        }).instrument(span).await
+    }
+
+    pub async fn find_by_user(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "find_user_identities",
+            "SELECT * FROM user_identities WHERE user_id = $1 ORDER BY created_at",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "user_identities", || async {
+            let identities = sqlx::query_as::<_, UserIdentity>(
+                "SELECT * FROM user_identities WHERE user_id = $1 ORDER BY created_at"
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(identities)
+        }).instrument(span).await
+    }
+
+    // Links a provider account to the user; returns None if the user already has an account of that provider
+    // linked or the provider account belongs to someone else
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        provider_user_id: &str,
+        email: Option<&str>,
+    ) -> Result<Option<UserIdentity>, AppError> {
+        let params = format!("user_id={}, provider={}, provider_user_id={}", user_id, provider, provider_user_id);
+        let span = create_db_span(
+            "create_user_identity",
+            "INSERT INTO user_identities (user_id, provider, provider_user_id, email) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "user_identities", || async {
+            let identity = sqlx::query_as::<_, UserIdentity>(
+                r#"
+                INSERT INTO user_identities (user_id, provider, provider_user_id, email, last_login_at)
+                VALUES ($1, $2, $3, $4, NOW())
+                ON CONFLICT DO NOTHING
+                RETURNING *
+                "#
+            )
+            .bind(user_id)
+            .bind(provider)
+            .bind(provider_user_id)
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(identity)
+        }).instrument(span).await
+    }
+
+    pub async fn touch_last_login(&self, id: Uuid) -> Result<(), AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "touch_user_identity_last_login",
+            "UPDATE user_identities SET last_login_at = NOW() WHERE id = $1",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "user_identities", || async {
+            sqlx::query("UPDATE user_identities SET last_login_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // Returns false if the user has no account of that provider linked
+    pub async fn delete(&self, user_id: Uuid, provider: &str) -> Result<bool, AppError> {
+        let params = format!("user_id={}, provider={}", user_id, provider);
+        let span = create_db_span(
+            "delete_user_identity",
+            "DELETE FROM user_identities WHERE user_id = $1 AND provider = $2",
+            &params,
+        );
+
+        DbMetrics::track("DELETE", "user_identities", || async {
+            let result = sqlx::query("DELETE FROM user_identities WHERE user_id = $1 AND provider = $2")
+                .bind(user_id)
+                .bind(provider)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+}
@@ -0,0 +1,133 @@
+use crate::error::AppError;
+use crate::models::{ImpersonationLogEntry, ImpersonationLogQuery, PageRequest};
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct ImpersonationLogRepository {
+    pool: PgPool,
+}
+
+impl ImpersonationLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record_start(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Result<(), AppError> {
+        let params = format!("actor_id={}, user_id={}, ip={:?}", actor_id, user_id, ip_address);
+        let span = create_db_span(
+            "record_impersonation_start",
+            "INSERT INTO impersonation_log (actor_id, user_id, event, ip_address, user_agent) VALUES ($1, $2, 'started', $3, $4)",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "impersonation_log", || async {
+            sqlx::query(
+                "INSERT INTO impersonation_log (actor_id, user_id, event, ip_address, user_agent) VALUES ($1, $2, 'started', $3, $4)"
+            )
+            .bind(actor_id)
+            .bind(user_id)
+            .bind(ip_address)
+            .bind(user_agent)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    pub async fn record_request(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+        method: &str,
+        path: &str,
+        status_code: i32,
+        ip_address: Option<&str>,
+    ) -> Result<(), AppError> {
+        let params = format!(
+            "actor_id={}, user_id={}, method={}, path={}, status={}",
+            actor_id, user_id, method, path, status_code
+        );
+        let span = create_db_span(
+            "record_impersonation_request",
+            "INSERT INTO impersonation_log (actor_id, user_id, event, method, path, status_code, ip_address) VALUES ($1, $2, 'request', $3, $4, $5, $6)",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "impersonation_log", || async {
+            sqlx::query(
+                r#"
+                INSERT INTO impersonation_log (actor_id, user_id, event, method, path, status_code, ip_address)
+                VALUES ($1, $2, 'request', $3, $4, $5, $6)
+                "#
+            )
+            .bind(actor_id)
+            .bind(user_id)
+            .bind(method)
+            .bind(path)
+            .bind(status_code)
+            .bind(ip_address)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // One page of log entries, optionally only of one admin and/or one impersonated user,
+    // newest first, with the total number of matches
+    pub async fn find_page(
+        &self,
+        filter: &ImpersonationLogQuery,
+        page: PageRequest,
+    ) -> Result<(Vec<ImpersonationLogEntry>, i64), AppError> {
+        let params = format!(
+            "actor_id={:?}, user_id={:?}, page={}, per_page={}",
+            filter.actor_id, filter.user_id, page.page, page.per_page
+        );
+        let span = create_db_span(
+            "find_impersonation_log",
+            "SELECT * FROM impersonation_log WHERE ($1::uuid IS NULL OR actor_id = $1) AND ($2::uuid IS NULL OR user_id = $2) ORDER BY created_at DESC, id DESC LIMIT $3 OFFSET $4",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "impersonation_log", || async {
+            let entries = sqlx::query_as::<_, ImpersonationLogEntry>(
+                r#"
+                SELECT * FROM impersonation_log
+                WHERE ($1::uuid IS NULL OR actor_id = $1) AND ($2::uuid IS NULL OR user_id = $2)
+                ORDER BY created_at DESC, id DESC
+                LIMIT $3 OFFSET $4
+                "#
+            )
+            .bind(filter.actor_id)
+            .bind(filter.user_id)
+            .bind(page.per_page)
+            .bind(page.offset())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            let total: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM impersonation_log WHERE ($1::uuid IS NULL OR actor_id = $1) AND ($2::uuid IS NULL OR user_id = $2)"
+            )
+            .bind(filter.actor_id)
+            .bind(filter.user_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok((entries, total))
+        }).instrument(span).await
+    }
+}
@@ -0,0 +1,171 @@
+use crate::error::AppError;
+use crate::models::Invitation;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct InvitationRepository {
+    pool: PgPool,
+}
+
+impl InvitationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Stores a new invitation, revoking pending invitations issued earlier for the same email
+    pub async fn create(
+        &self,
+        email: &str,
+        role: &str,
+        invited_by: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Invitation, AppError> {
+        let params = format!("email={}, role={}, invited_by={}", email, role, invited_by);
+        let span = create_db_span(
+            "create_invitation",
+            "INSERT INTO invitations (email, role, invited_by, token_hash, expires_at) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "invitations", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            sqlx::query(
+                "UPDATE invitations SET revoked_at = NOW() WHERE LOWER(email) = LOWER($1) AND accepted_at IS NULL AND revoked_at IS NULL"
+            )
+            .bind(email)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            let invitation = sqlx::query_as::<_, Invitation>(
+                r#"
+                INSERT INTO invitations (email, role, invited_by, token_hash, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+                "#
+            )
+            .bind(email)
+            .bind(role)
+            .bind(invited_by)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(invitation)
+        }).instrument(span).await
+    }
+
+    // All invitations, or only those sent by `invited_by`, newest first
+    pub async fn find_all(&self, invited_by: Option<Uuid>) -> Result<Vec<Invitation>, AppError> {
+        let params = format!("invited_by={:?}", invited_by);
+        let span = create_db_span(
+            "find_invitations",
+            "SELECT * FROM invitations WHERE ($1::uuid IS NULL OR invited_by = $1) ORDER BY created_at DESC",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "invitations", || async {
+            let invitations = sqlx::query_as::<_, Invitation>(
+                "SELECT * FROM invitations WHERE ($1::uuid IS NULL OR invited_by = $1) ORDER BY created_at DESC"
+            )
+            .bind(invited_by)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(invitations)
+        }).instrument(span).await
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Invitation, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span("find_invitation_by_id", "SELECT * FROM invitations WHERE id = $1", &params);
+
+        DbMetrics::track("SELECT", "invitations", || async {
+            let invitation = sqlx::query_as::<_, Invitation>("SELECT * FROM invitations WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            invitation.ok_or_else(|| AppError::NotFound(format!("Invitation with ID {} not found", id)))
+        }).instrument(span).await
+    }
+
+    // Returns None for unknown, accepted, revoked or expired invitations
+    pub async fn find_pending_by_token_hash(&self, token_hash: &str) -> Result<Option<Invitation>, AppError> {
+        let span = create_db_span(
+            "find_pending_invitation",
+            "SELECT * FROM invitations WHERE token_hash = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > NOW()",
+            "token_hash=<redacted>",
+        );
+
+        DbMetrics::track("SELECT", "invitations", || async {
+            let invitation = sqlx::query_as::<_, Invitation>(
+                r#"
+                SELECT * FROM invitations
+                WHERE token_hash = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > NOW()
+                "#
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(invitation)
+        }).instrument(span).await
+    }
+
+    // Part of the account creation transaction (UserRepository::create_from_invitation);
+    // returns false if the invitation is no longer pending
+    pub async fn mark_accepted(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE invitations
+            SET accepted_at = NOW(), accepted_user_id = $2
+            WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL AND expires_at > NOW()
+            "#
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Returns false if the invitation was already accepted or revoked
+    pub async fn revoke(&self, id: Uuid) -> Result<bool, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "revoke_invitation",
+            "UPDATE invitations SET revoked_at = NOW() WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "invitations", || async {
+            let result = sqlx::query(
+                "UPDATE invitations SET revoked_at = NOW() WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL"
+            )
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+}
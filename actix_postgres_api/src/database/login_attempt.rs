@@ -0,0 +1,77 @@
+use crate::error::AppError;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgPool;
+use tracing::Instrument;
+
+pub struct LoginAttemptRepository {
+    pool: PgPool,
+}
+
+impl LoginAttemptRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn record(
+        &self,
+        email: &str,
+        ip_address: Option<&str>,
+        user_agent: Option<&str>,
+        succeeded: bool,
+    ) -> Result<(), AppError> {
+        let params = format!("email={}, ip={:?}, succeeded={}", email, ip_address, succeeded);
+        let span = create_db_span(
+            "record_login_attempt",
+            "INSERT INTO login_attempts (email, ip_address, user_agent, succeeded) VALUES ($1, $2, $3, $4)",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "login_attempts", || async {
+            sqlx::query(
+                "INSERT INTO login_attempts (email, ip_address, user_agent, succeeded) VALUES ($1, $2, $3, $4)"
+            )
+            .bind(email)
+            .bind(ip_address)
+            .bind(user_agent)
+            .bind(succeeded)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // Number of failed attempts from the IP address since `since`, with the time of the oldest one
+    pub async fn recent_failures_by_ip(
+        &self,
+        ip_address: &str,
+        since: DateTime<Utc>,
+    ) -> Result<(i64, Option<DateTime<Utc>>), AppError> {
+        let params = format!("ip={}, since={}", ip_address, since);
+        let span = create_db_span(
+            "count_failed_login_attempts_by_ip",
+            "SELECT COUNT(*), MIN(attempted_at) FROM login_attempts WHERE ip_address = $1 AND succeeded = FALSE AND attempted_at > $2",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "login_attempts", || async {
+            let result = sqlx::query_as::<_, (i64, Option<DateTime<Utc>>)>(
+                r#"
+                SELECT COUNT(*), MIN(attempted_at)
+                FROM login_attempts
+                WHERE ip_address = $1 AND succeeded = FALSE AND attempted_at > $2
+                "#
+            )
+            .bind(ip_address)
+            .bind(since)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result)
+        }).instrument(span).await
+    }
+}
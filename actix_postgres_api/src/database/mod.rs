@@ -3,6 +3,19 @@ pub mod user;
 pub mod connection;
 pub mod chat;
 pub mod appointment;
+pub mod refresh_token;
+pub mod password_reset;
+pub mod email_verification;
+pub mod two_factor;
+pub mod login_attempt;
+pub mod oauth_state;
+pub mod identity;
+pub mod role;
+pub mod invitation;
+pub mod api_key;
+pub mod session;
+pub mod impersonation;
+pub mod audit;
 
 // Re-export database components for easier imports
 // These are exported to provide a cleaner API for other modules
@@ -14,4 +27,17 @@ pub use user::UserRepository;
 pub use chat::ChatRepository;
 
 // Re-export repositories
-pub use appointment::AppointmentRepository;
\ No newline at end of file
+pub use appointment::AppointmentRepository;
+pub use refresh_token::RefreshTokenRepository;
+pub use password_reset::PasswordResetRepository;
+pub use email_verification::EmailVerificationRepository;
+pub use two_factor::TwoFactorRepository;
+pub use login_attempt::LoginAttemptRepository;
+pub use oauth_state::OAuthStateRepository;
+pub use identity::IdentityRepository;
+pub use role::RoleRepository;
+pub use invitation::InvitationRepository;
+pub use api_key::ApiKeyRepository;
+pub use session::SessionRepository;
+pub use impersonation::ImpersonationLogRepository;
+pub use audit::AuditEventRepository;
\ No newline at end of file
@@ -0,0 +1,88 @@
+use crate::error::AppError;
+use crate::models::OAuthState;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct OAuthStateRepository {
+    pool: PgPool,
+}
+
+impl OAuthStateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Stores a new pending authorization request and drops expired ones
+    pub async fn create(
+        &self,
+        id: Uuid,
+        provider: &str,
+        pkce_verifier: &str,
+        link_user_id: Option<Uuid>,
+        nonce: Option<&str>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<OAuthState, AppError> {
+        let params = format!("id={}, provider={}", id, provider);
+        let span = create_db_span(
+            "create_oauth_state",
+            "INSERT INTO oauth_states (id, provider, pkce_verifier, link_user_id, nonce, expires_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "oauth_states", || async {
+            sqlx::query("DELETE FROM oauth_states WHERE expires_at < NOW()")
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            let state = sqlx::query_as::<_, OAuthState>(
+                r#"
+                INSERT INTO oauth_states (id, provider, pkce_verifier, link_user_id, nonce, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .bind(provider)
+            .bind(pkce_verifier)
+            .bind(link_user_id)
+            .bind(nonce)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(state)
+        }).instrument(span).await
+    }
+
+    // Atomically marks a pending state as used; returns None for unknown, used or expired states
+    pub async fn consume(&self, id: Uuid) -> Result<Option<OAuthState>, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "consume_oauth_state",
+            "UPDATE oauth_states SET used_at = NOW() WHERE id = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "oauth_states", || async {
+            let state = sqlx::query_as::<_, OAuthState>(
+                r#"
+                UPDATE oauth_states
+                SET used_at = NOW()
+                WHERE id = $1 AND used_at IS NULL AND expires_at > NOW()
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(state)
+        }).instrument(span).await
+    }
+}
@@ -0,0 +1,108 @@
+use crate::error::AppError;
+use crate::models::PasswordResetToken;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct PasswordResetRepository {
+    pool: PgPool,
+}
+
+impl PasswordResetRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Stores a new reset token, invalidating any unused tokens issued earlier for the same user
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<PasswordResetToken, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "create_password_reset_token",
+            "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "password_reset_tokens", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            sqlx::query(
+                "UPDATE password_reset_tokens SET used_at = NOW() WHERE user_id = $1 AND used_at IS NULL"
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            let token = sqlx::query_as::<_, PasswordResetToken>(
+                r#"
+                INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+                VALUES ($1, $2, $3)
+                RETURNING *
+                "#
+            )
+            .bind(user_id)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(token)
+        }).instrument(span).await
+    }
+
+    // Returns the token if it is still valid, without using it up
+    pub async fn find_valid(&self, token_hash: &str) -> Result<Option<PasswordResetToken>, AppError> {
+        let span = create_db_span(
+            "find_valid_password_reset_token",
+            "SELECT * FROM password_reset_tokens WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()",
+            "token_hash=<redacted>",
+        );
+
+        DbMetrics::track("SELECT", "password_reset_tokens", || async {
+            let token = sqlx::query_as::<_, PasswordResetToken>(
+                "SELECT * FROM password_reset_tokens WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()"
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(token)
+        }).instrument(span).await
+    }
+
+    // Atomically marks a valid token as used; returns None for unknown, used or expired tokens
+    pub async fn consume(&self, token_hash: &str) -> Result<Option<PasswordResetToken>, AppError> {
+        let span = create_db_span(
+            "consume_password_reset_token",
+            "UPDATE password_reset_tokens SET used_at = NOW() WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW() RETURNING *",
+            "token_hash=<redacted>",
+        );
+
+        DbMetrics::track("UPDATE", "password_reset_tokens", || async {
+            let token = sqlx::query_as::<_, PasswordResetToken>(
+                r#"
+                UPDATE password_reset_tokens
+                SET used_at = NOW()
+                WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+                RETURNING *
+                "#
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(token)
+        }).instrument(span).await
+    }
+}
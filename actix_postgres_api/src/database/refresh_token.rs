@@ -0,0 +1,165 @@
+use crate::error::AppError;
+use crate::models::RefreshToken;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct RefreshTokenRepository {
+    pool: PgPool,
+}
+
+impl RefreshTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        family_id: Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<RefreshToken, AppError> {
+        let params = format!("user_id={}, family_id={}", user_id, family_id);
+        let span = create_db_span(
+            "create_refresh_token",
+            "INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at) VALUES ($1, $2, $3, $4) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "refresh_tokens", || async {
+            let token = sqlx::query_as::<_, RefreshToken>(
+                r#"
+                INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+                "#
+            )
+            .bind(user_id)
+            .bind(family_id)
+            .bind(token_hash)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(token)
+        }).instrument(span).await
+    }
+
+    pub async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshToken>, AppError> {
+        let span = create_db_span(
+            "find_refresh_token_by_hash",
+            "SELECT * FROM refresh_tokens WHERE token_hash = $1",
+            "token_hash=<redacted>",
+        );
+
+        DbMetrics::track("SELECT", "refresh_tokens", || async {
+            let token = sqlx::query_as::<_, RefreshToken>(
+                "SELECT * FROM refresh_tokens WHERE token_hash = $1"
+            )
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(token)
+        }).instrument(span).await
+    }
+
+    // Revokes the current token and stores its successor in a single transaction.
+    // Returns None when the token has already been rotated by a concurrent request.
+    pub async fn rotate(
+        &self,
+        current: &RefreshToken,
+        new_token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<RefreshToken>, AppError> {
+        let params = format!("id={}, family_id={}", current.id, current.family_id);
+        let span = create_db_span(
+            "rotate_refresh_token",
+            "UPDATE refresh_tokens SET revoked_at = NOW(), replaced_by = $1 WHERE id = $2 AND revoked_at IS NULL",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "refresh_tokens", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            let new_token = sqlx::query_as::<_, RefreshToken>(
+                r#"
+                INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+                "#
+            )
+            .bind(current.user_id)
+            .bind(current.family_id)
+            .bind(new_token_hash)
+            .bind(expires_at)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            let result = sqlx::query(
+                "UPDATE refresh_tokens SET revoked_at = NOW(), replaced_by = $1 WHERE id = $2 AND revoked_at IS NULL"
+            )
+            .bind(new_token.id)
+            .bind(current.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            if result.rows_affected() == 0 {
+                tx.rollback().await.map_err(AppError::DatabaseError)?;
+                return Ok(None);
+            }
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(Some(new_token))
+        }).instrument(span).await
+    }
+
+    pub async fn revoke_family(&self, family_id: Uuid) -> Result<u64, AppError> {
+        let params = format!("family_id={}", family_id);
+        let span = create_db_span(
+            "revoke_refresh_token_family",
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "refresh_tokens", || async {
+            let result = sqlx::query(
+                "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL"
+            )
+            .bind(family_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected())
+        }).instrument(span).await
+    }
+
+    pub async fn revoke_all_for_user(&self, user_id: Uuid) -> Result<u64, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "revoke_user_refresh_tokens",
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "refresh_tokens", || async {
+            let result = sqlx::query(
+                "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL"
+            )
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected())
+        }).instrument(span).await
+    }
+}
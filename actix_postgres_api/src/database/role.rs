@@ -0,0 +1,229 @@
+use crate::error::AppError;
+use crate::models::role::{Permission, Role};
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use sqlx::postgres::PgPool;
+use tracing::Instrument;
+
+pub struct RoleRepository {
+    pool: PgPool,
+}
+
+impl RoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_all(&self) -> Result<Vec<Role>, AppError> {
+        let span = create_db_span("find_all_roles", "SELECT * FROM roles ORDER BY name", "");
+
+        DbMetrics::track("SELECT", "roles", || async {
+            let roles = sqlx::query_as::<_, Role>("SELECT * FROM roles ORDER BY name")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(roles)
+        }).instrument(span).await
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Role, AppError> {
+        let params = format!("name={}", name);
+        let span = create_db_span("find_role_by_name", "SELECT * FROM roles WHERE name = $1", &params);
+
+        DbMetrics::track("SELECT", "roles", || async {
+            let role = sqlx::query_as::<_, Role>("SELECT * FROM roles WHERE name = $1")
+                .bind(name)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            role.ok_or_else(|| AppError::NotFound(format!("Role {} not found", name)))
+        }).instrument(span).await
+    }
+
+    pub async fn exists(&self, name: &str) -> Result<bool, AppError> {
+        let params = format!("name={}", name);
+        let span = create_db_span("check_role_exists", "SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1)", &params);
+
+        DbMetrics::track("SELECT", "roles", || async {
+            let exists = sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1)")
+                .bind(name)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(exists)
+        }).instrument(span).await
+    }
+
+    // Returns None if a role with that name already exists
+    pub async fn create(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        parent_role: Option<&str>,
+    ) -> Result<Option<Role>, AppError> {
+        let params = format!("name={}, parent_role={:?}", name, parent_role);
+        let span = create_db_span(
+            "create_role",
+            "INSERT INTO roles (name, description, parent_role) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "roles", || async {
+            let role = sqlx::query_as::<_, Role>(
+                r#"
+                INSERT INTO roles (name, description, parent_role)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (name) DO NOTHING
+                RETURNING *
+                "#
+            )
+            .bind(name)
+            .bind(description)
+            .bind(parent_role)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(role)
+        }).instrument(span).await
+    }
+
+    pub async fn update(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        parent_role: Option<&str>,
+    ) -> Result<Role, AppError> {
+        let params = format!("name={}, parent_role={:?}", name, parent_role);
+        let span = create_db_span(
+            "update_role",
+            "UPDATE roles SET description = $2, parent_role = $3 WHERE name = $1 RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "roles", || async {
+            let role = sqlx::query_as::<_, Role>(
+                r#"
+                UPDATE roles
+                SET description = $2, parent_role = $3, updated_at = NOW()
+                WHERE name = $1
+                RETURNING *
+                "#
+            )
+            .bind(name)
+            .bind(description)
+            .bind(parent_role)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            role.ok_or_else(|| AppError::NotFound(format!("Role {} not found", name)))
+        }).instrument(span).await
+    }
+
+    // Users keep a reference to their role, so a role that is still assigned cannot be deleted
+    // (this includes soft-deleted users until they are purged, which resets the role to client)
+    pub async fn delete(&self, name: &str) -> Result<(), AppError> {
+        let params = format!("name={}", name);
+        let span = create_db_span("delete_role", "DELETE FROM roles WHERE name = $1 AND is_system = FALSE", &params);
+
+        DbMetrics::track("DELETE", "roles", || async {
+            let result = sqlx::query("DELETE FROM roles WHERE name = $1 AND is_system = FALSE")
+                .bind(name)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| match &e {
+                    sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+                        AppError::BadRequest(format!("Role {} is still assigned to users", name))
+                    }
+                    _ => AppError::DatabaseError(e),
+                })?;
+
+            if result.rows_affected() == 0 {
+                return Err(AppError::NotFound(format!("Role {} not found or cannot be deleted", name)));
+            }
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    pub async fn find_all_permissions(&self) -> Result<Vec<Permission>, AppError> {
+        let span = create_db_span("find_all_permissions", "SELECT * FROM permissions ORDER BY name", "");
+
+        DbMetrics::track("SELECT", "permissions", || async {
+            let permissions = sqlx::query_as::<_, Permission>("SELECT * FROM permissions ORDER BY name")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(permissions)
+        }).instrument(span).await
+    }
+
+    // All (role, permission) pairs granted directly
+    pub async fn find_all_grants(&self) -> Result<Vec<(String, String)>, AppError> {
+        let span = create_db_span(
+            "find_all_role_permissions",
+            "SELECT role_name, permission_name FROM role_permissions",
+            "",
+        );
+
+        DbMetrics::track("SELECT", "role_permissions", || async {
+            let grants = sqlx::query_as::<_, (String, String)>(
+                "SELECT role_name, permission_name FROM role_permissions ORDER BY role_name, permission_name"
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(grants)
+        }).instrument(span).await
+    }
+
+    // Returns false if the role already has the permission
+    pub async fn grant(&self, role: &str, permission: &str) -> Result<bool, AppError> {
+        let params = format!("role={}, permission={}", role, permission);
+        let span = create_db_span(
+            "grant_role_permission",
+            "INSERT INTO role_permissions (role_name, permission_name) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "role_permissions", || async {
+            let result = sqlx::query(
+                "INSERT INTO role_permissions (role_name, permission_name) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+            )
+            .bind(role)
+            .bind(permission)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+
+    // Returns false if the role did not have the permission
+    pub async fn revoke(&self, role: &str, permission: &str) -> Result<bool, AppError> {
+        let params = format!("role={}, permission={}", role, permission);
+        let span = create_db_span(
+            "revoke_role_permission",
+            "DELETE FROM role_permissions WHERE role_name = $1 AND permission_name = $2",
+            &params,
+        );
+
+        DbMetrics::track("DELETE", "role_permissions", || async {
+            let result = sqlx::query("DELETE FROM role_permissions WHERE role_name = $1 AND permission_name = $2")
+                .bind(role)
+                .bind(permission)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+}
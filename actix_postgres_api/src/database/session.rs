@@ -0,0 +1,184 @@
+use crate::error::AppError;
+use crate::models::Session;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct SessionRepository {
+    pool: PgPool,
+}
+
+impl SessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        user_id: Uuid,
+        user_agent: Option<&str>,
+        ip_address: Option<&str>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, AppError> {
+        let params = format!("user_id={}, ip={:?}", user_id, ip_address);
+        let span = create_db_span(
+            "create_session",
+            "INSERT INTO sessions (user_id, user_agent, ip_address, expires_at) VALUES ($1, $2, $3, $4) RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "sessions", || async {
+            let session = sqlx::query_as::<_, Session>(
+                r#"
+                INSERT INTO sessions (user_id, user_agent, ip_address, expires_at)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+                "#
+            )
+            .bind(user_id)
+            .bind(user_agent)
+            .bind(ip_address)
+            .bind(expires_at)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(session)
+        }).instrument(span).await
+    }
+
+    // Active (not revoked, not expired) sessions of the user, most recently used first
+    pub async fn find_active_by_user(&self, user_id: Uuid) -> Result<Vec<Session>, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "find_user_sessions",
+            "SELECT * FROM sessions WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW() ORDER BY last_active_at DESC",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "sessions", || async {
+            let sessions = sqlx::query_as::<_, Session>(
+                r#"
+                SELECT * FROM sessions
+                WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+                ORDER BY last_active_at DESC
+                "#
+            )
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(sessions)
+        }).instrument(span).await
+    }
+
+    // Records activity of an active session; returns false for unknown, revoked or expired sessions
+    // Returns whether the session is active; last_active_at is written only when it is older
+    // than `stale_before`, so most requests do not update the row
+    pub async fn touch(&self, id: Uuid, user_id: Uuid, stale_before: DateTime<Utc>) -> Result<bool, AppError> {
+        let params = format!("id={}, user_id={}", id, user_id);
+        let span = create_db_span(
+            "touch_session",
+            "WITH active AS (SELECT ... WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()), touched AS (UPDATE sessions SET last_active_at = NOW() WHERE ... last_active_at < $3) SELECT EXISTS (...)",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "sessions", || async {
+            sqlx::query_scalar::<_, bool>(
+                r#"
+                WITH active AS (
+                    SELECT id, last_active_at FROM sessions
+                    WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()
+                ), touched AS (
+                    UPDATE sessions
+                    SET last_active_at = NOW()
+                    WHERE id IN (SELECT id FROM active WHERE last_active_at < $3)
+                )
+                SELECT EXISTS (SELECT 1 FROM active)
+                "#
+            )
+            .bind(id)
+            .bind(user_id)
+            .bind(stale_before)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+        }).instrument(span).await
+    }
+
+    // Moves the expiry of an active session forward after its refresh token was rotated;
+    // returns false when the session is no longer active
+    pub async fn extend(&self, id: Uuid, expires_at: DateTime<Utc>) -> Result<bool, AppError> {
+        let params = format!("id={}, expires_at={}", id, expires_at);
+        let span = create_db_span(
+            "extend_session",
+            "UPDATE sessions SET expires_at = $2, last_active_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "sessions", || async {
+            let result = sqlx::query(
+                "UPDATE sessions SET expires_at = $2, last_active_at = NOW() WHERE id = $1 AND revoked_at IS NULL"
+            )
+            .bind(id)
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+
+    // Revokes active sessions of the user together with their refresh tokens.
+    // `session_id` limits it to one session, `except` keeps one session alive.
+    // Returns the ids of the revoked sessions.
+    pub async fn revoke(
+        &self,
+        user_id: Uuid,
+        session_id: Option<Uuid>,
+        except: Option<Uuid>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        let params = format!("user_id={}, session_id={:?}, except={:?}", user_id, session_id, except);
+        let span = create_db_span(
+            "revoke_sessions",
+            "UPDATE sessions SET revoked_at = NOW() WHERE user_id = $1 AND ($2::uuid IS NULL OR id = $2) AND ($3::uuid IS NULL OR id <> $3) AND revoked_at IS NULL RETURNING id",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "sessions", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            let revoked: Vec<Uuid> = sqlx::query_scalar(
+                r#"
+                UPDATE sessions
+                SET revoked_at = NOW()
+                WHERE user_id = $1
+                  AND ($2::uuid IS NULL OR id = $2)
+                  AND ($3::uuid IS NULL OR id <> $3)
+                  AND revoked_at IS NULL
+                RETURNING id
+                "#
+            )
+            .bind(user_id)
+            .bind(session_id)
+            .bind(except)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            // The session id is the refresh token family id
+            sqlx::query("UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = ANY($1) AND revoked_at IS NULL")
+                .bind(&revoked)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(revoked)
+        }).instrument(span).await
+    }
+}
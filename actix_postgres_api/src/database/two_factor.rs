@@ -0,0 +1,211 @@
+use crate::error::AppError;
+use crate::models::UserTotp;
+use crate::monitoring::DbMetrics;
+use crate::logging::create_db_span;
+use sqlx::{postgres::PgPool, types::Uuid};
+use tracing::Instrument;
+
+pub struct TwoFactorRepository {
+    pool: PgPool,
+}
+
+impl TwoFactorRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_by_user(&self, user_id: Uuid) -> Result<Option<UserTotp>, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "find_user_totp",
+            "SELECT * FROM user_totp WHERE user_id = $1",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "user_totp", || async {
+            let totp = sqlx::query_as::<_, UserTotp>(
+                "SELECT * FROM user_totp WHERE user_id = $1"
+            )
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(totp)
+        }).instrument(span).await
+    }
+
+    // Stores a pending (not yet confirmed) secret, replacing an earlier unconfirmed one.
+    // Returns None if two-factor authentication is already enabled for the user.
+    pub async fn upsert_pending(&self, user_id: Uuid, secret: &str) -> Result<Option<UserTotp>, AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "upsert_pending_user_totp",
+            "INSERT INTO user_totp (user_id, secret) VALUES ($1, $2) ON CONFLICT (user_id) DO UPDATE ... WHERE user_totp.enabled_at IS NULL RETURNING *",
+            &params,
+        );
+
+        DbMetrics::track("INSERT", "user_totp", || async {
+            let totp = sqlx::query_as::<_, UserTotp>(
+                r#"
+                INSERT INTO user_totp (user_id, secret)
+                VALUES ($1, $2)
+                ON CONFLICT (user_id) DO UPDATE
+                SET secret = EXCLUDED.secret, last_used_step = NULL, updated_at = NOW()
+                WHERE user_totp.enabled_at IS NULL
+                RETURNING *
+                "#
+            )
+            .bind(user_id)
+            .bind(secret)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(totp)
+        }).instrument(span).await
+    }
+
+    // Enables two-factor authentication and replaces the recovery codes in one transaction
+    pub async fn enable(
+        &self,
+        user_id: Uuid,
+        used_step: i64,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "enable_user_totp",
+            "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $2 WHERE user_id = $1",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "user_totp", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            sqlx::query(
+                "UPDATE user_totp SET enabled_at = NOW(), last_used_step = $2, updated_at = NOW() WHERE user_id = $1"
+            )
+            .bind(user_id)
+            .bind(used_step)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Self::replace_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // Atomically records the time step of an accepted code.
+    // Returns false if the same or a later step has already been used (replayed code).
+    pub async fn mark_step_used(&self, user_id: Uuid, step: i64) -> Result<bool, AppError> {
+        let params = format!("user_id={}, step={}", user_id, step);
+        let span = create_db_span(
+            "mark_totp_step_used",
+            "UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "user_totp", || async {
+            let result = sqlx::query(
+                r#"
+                UPDATE user_totp
+                SET last_used_step = $2, updated_at = NOW()
+                WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)
+                "#
+            )
+            .bind(user_id)
+            .bind(step)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+
+    // Atomically marks an unused recovery code as used; returns false if it does not match
+    pub async fn consume_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, AppError> {
+        let params = format!("user_id={}, code_hash=<redacted>", user_id);
+        let span = create_db_span(
+            "consume_totp_recovery_code",
+            "UPDATE totp_recovery_codes SET used_at = NOW() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+            &params,
+        );
+
+        DbMetrics::track("UPDATE", "totp_recovery_codes", || async {
+            let result = sqlx::query(
+                r#"
+                UPDATE totp_recovery_codes
+                SET used_at = NOW()
+                WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                "#
+            )
+            .bind(user_id)
+            .bind(code_hash)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+
+    // Removes the secret together with all recovery codes
+    pub async fn disable(&self, user_id: Uuid) -> Result<(), AppError> {
+        let params = format!("user_id={}", user_id);
+        let span = create_db_span(
+            "disable_user_totp",
+            "DELETE FROM user_totp WHERE user_id = $1",
+            &params,
+        );
+
+        DbMetrics::track("DELETE", "user_totp", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            sqlx::query("DELETE FROM user_totp WHERE user_id = $1")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(())
+        }).instrument(span).await
+    }
+
+    async fn replace_recovery_codes(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: Uuid,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO totp_recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::VARCHAR[])
+            "#
+        )
+        .bind(user_id)
+        .bind(recovery_code_hashes)
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
@@ -1,11 +1,65 @@
 use crate::error::AppError;
-use crate::models::{CreateUserRequest, UpdateUserRequest, User, LoginRequest};
-use crate::auth_utils::{hash_password, verify_password, validate_role};
+use crate::models::{CreateUserRequest, UpdateUserRequest, User, LoginRequest, PageRequest, SortOrder, UserCursor, UserListQuery};
+use crate::models::role::UserRole;
+use crate::auth_utils::{hash_password, password_needs_rehash, verify_password, DEFAULT_PASSWORD};
+use crate::auth_utils::lockout::{retry_after_seconds, LockoutConfig};
+use chrono::{DateTime, Utc};
 use crate::monitoring::DbMetrics;
 use crate::logging::create_db_span;
-use sqlx::{postgres::PgPool, types::Uuid};
+use sqlx::{postgres::{PgPool, Postgres}, types::Uuid, PgExecutor, QueryBuilder};
+use crate::database::InvitationRepository;
 use tracing::Instrument;
 
+// Name shown instead of an erased user, also as the sender of their chat messages
+pub const ERASED_USER_NAME: &str = "Deleted user";
+
+// Tables with credentials and device data removed on erasure (refresh tokens before their sessions)
+const ERASED_CREDENTIAL_TABLES: [&str; 8] = [
+    "refresh_tokens",
+    "sessions",
+    "user_identities",
+    "api_keys",
+    "totp_recovery_codes",
+    "user_totp",
+    "password_reset_tokens",
+    "email_verification_tokens",
+];
+
+// Warunki listy użytkowników wspólne dla zapytania o stronę i o liczbę wyników
+fn push_user_filters<'a>(builder: &mut QueryBuilder<'a, Postgres>, filter: &'a UserListQuery) {
+    builder.push(" WHERE deleted_at IS NULL");
+
+    if let Some(role) = &filter.role {
+        builder.push(" AND role = ").push_bind(role.as_str());
+    }
+
+    if let Some(active) = filter.active {
+        builder.push(" AND active = ").push_bind(active);
+    }
+
+    if let Some(created_from) = filter.created_from {
+        builder.push(" AND created_at >= ").push_bind(created_from);
+    }
+
+    if let Some(created_to) = filter.created_to {
+        builder.push(" AND created_at <= ").push_bind(created_to);
+    }
+
+    if let Some(search) = filter.search.as_deref().map(str::trim).filter(|search| !search.is_empty()) {
+        let pattern = format!("%{}%", escape_like(search));
+        builder
+            .push(" AND (username ILIKE ").push_bind(pattern.clone())
+            .push(" OR email ILIKE ").push_bind(pattern.clone())
+            .push(" OR full_name ILIKE ").push_bind(pattern)
+            .push(")");
+    }
+}
+
+// Wildcards typed by the user are matched literally
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
 pub struct UserRepository {
     pool: PgPool,
 }
@@ -15,24 +69,70 @@ impl UserRepository {
         Self { pool }
     }
 
-    pub async fn find_all(&self) -> Result<Vec<User>, AppError> {
-        // Create a span for this database operation
+    // Jedna strona użytkowników spełniających filtry oraz łączna liczba dopasowań. Z kursorem strona
+    // zaczyna się za wierszem kursora, bez niego od przesunięcia strony `page`. Pobierany jest jeden
+    // wiersz więcej, żeby wywołujący wiedział, czy istnieje następna strona.
+    pub async fn find_page(
+        &self,
+        filter: &UserListQuery,
+        page: PageRequest,
+        cursor: Option<&UserCursor>,
+    ) -> Result<(Vec<User>, i64), AppError> {
+        let params = format!(
+            "role={:?}, active={:?}, created_from={:?}, created_to={:?}, search={:?}, sort={:?}, order={:?}, page={}, per_page={}, cursor={}",
+            filter.role, filter.active, filter.created_from, filter.created_to, filter.search, filter.sort, filter.order,
+            page.page, page.per_page, cursor.is_some()
+        );
         let span = create_db_span(
-            "find_all_users",
-            "SELECT * FROM users ORDER BY created_at DESC",
-            "None",
+            "find_users_page",
+            "SELECT * FROM users WHERE deleted_at IS NULL AND <filters> ORDER BY <sort>, id LIMIT $n OFFSET $m",
+            &params,
         );
         
-        // Track DB metrics and wrap in a span
         DbMetrics::track("SELECT", "users", || async {
-            let users = sqlx::query_as::<_, User>(
-                "SELECT * FROM users ORDER BY created_at DESC"
-            )
-            .fetch_all(&self.pool)
-            .await
-            .map_err(AppError::DatabaseError)?;
+            let column = filter.sort.column();
+            let order = filter.order.as_sql();
+
+            let mut select = QueryBuilder::<Postgres>::new("SELECT * FROM users");
+            push_user_filters(&mut select, filter);
+
+            if let Some(cursor) = cursor {
+                // Porównanie wierszowe (kolumna, id) - id rozstrzyga remisy, więc żaden wiersz nie jest pomijany
+                let operator = if filter.order == SortOrder::Asc { ">" } else { "<" };
+                select.push(format!(" AND ({}, id) {} (", column, operator));
+                if filter.sort.is_timestamp() {
+                    let value = DateTime::parse_from_rfc3339(&cursor.value)
+                        .map_err(|_| AppError::BadRequest("Invalid cursor".to_string()))?
+                        .with_timezone(&Utc);
+                    select.push_bind(value);
+                } else {
+                    select.push_bind(cursor.value.clone());
+                }
+                select.push(", ").push_bind(cursor.id).push(")");
+            }
+
+            select.push(format!(" ORDER BY {} {}, id {}", column, order, order));
+            select.push(" LIMIT ").push_bind(page.per_page + 1);
+            if cursor.is_none() {
+                select.push(" OFFSET ").push_bind(page.offset());
+            }
+
+            let users = select
+                .build_query_as::<User>()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM users");
+            push_user_filters(&mut count, filter);
+
+            let total = count
+                .build_query_scalar::<i64>()
+                .fetch_one(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
 
-            Ok(users)
+            Ok((users, total))
         }).instrument(span).await
     }
 
@@ -41,13 +141,13 @@ impl UserRepository {
         let params = format!("id={}", id);
         let span = create_db_span(
             "find_user_by_id",
-            "SELECT * FROM users WHERE id = $1",
+            "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL",
             &params,
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let user = sqlx::query_as::<_, User>(
-                "SELECT * FROM users WHERE id = $1"
+                "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL"
             )
             .bind(id)
             .fetch_optional(&self.pool)
@@ -62,13 +162,13 @@ impl UserRepository {
         let params = format!("email={}", email);
         let span = create_db_span(
             "find_user_by_email",
-            "SELECT * FROM users WHERE email = $1",
+            "SELECT * FROM users WHERE email = $1 AND deleted_at IS NULL",
             &params,
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let user = sqlx::query_as::<_, User>(
-                "SELECT * FROM users WHERE email = $1"
+                "SELECT * FROM users WHERE email = $1 AND deleted_at IS NULL"
             )
             .bind(email)
             .fetch_optional(&self.pool)
@@ -79,6 +179,28 @@ impl UserRepository {
         }).instrument(span).await
     }
 
+    // Includes soft-deleted accounts - their usernames stay taken until the purge
+    pub async fn username_exists(&self, username: &str) -> Result<bool, AppError> {
+        let params = format!("username={}", username);
+        let span = create_db_span(
+            "check_username_exists",
+            "SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)",
+            &params,
+        );
+
+        DbMetrics::track("SELECT", "users", || async {
+            let exists = sqlx::query_scalar::<_, bool>(
+                "SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)"
+            )
+            .bind(username)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(exists)
+        }).instrument(span).await
+    }
+
     pub async fn create(&self, user: CreateUserRequest) -> Result<User, AppError> {
         let params = format!("username={}, email={}", user.username, user.email);
         let span = create_db_span(
@@ -88,133 +210,516 @@ impl UserRepository {
         );
         
         DbMetrics::track("INSERT", "users", || async {
-            // Hashuj hasło przed zapisaniem
-            let password_hash = hash_password(&user.password)?;
-            
-            // Ustaw domyślną rolę client, jeśli nie podano
-            let role = match user.role {
-                Some(role) => role,
-                None => "client".to_string()
-            };
-            
+            Self::insert(&self.pool, user, None).await
+        }).instrument(span).await
+    }
+
+    // Account from an invitation, with a verified email. The user is created and the invitation
+    // marked as accepted in one transaction, so an invitation accepted or revoked in the meantime
+    // leaves no account behind.
+    pub async fn create_from_invitation(&self, user: CreateUserRequest, invitation_id: Uuid) -> Result<User, AppError> {
+        let params = format!("username={}, email={}, invitation_id={}", user.username, user.email, invitation_id);
+        let span = create_db_span(
+            "create_user_from_invitation",
+            "INSERT INTO users (username, email, password_hash, full_name, phone_number, role, email_verified_at) VALUES ($1, $2, $3, $4, $5, $6, $7); UPDATE invitations SET accepted_at = NOW() ... WHERE id = $1 AND accepted_at IS NULL",
+            &params,
+        );
+        
+        DbMetrics::track("INSERT", "users", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            let user = Self::insert(&mut *tx, user, Some(Utc::now())).await?;
+
+            if !InvitationRepository::mark_accepted(&mut tx, invitation_id, user.id).await? {
+                return Err(AppError::ValidationError("Invalid or expired invitation".to_string()));
+            }
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+            Ok(user)
+        }).instrument(span).await
+    }
+
+    async fn insert<'e>(
+        executor: impl PgExecutor<'e>,
+        user: CreateUserRequest,
+        email_verified_at: Option<DateTime<Utc>>,
+    ) -> Result<User, AppError> {
+        // Hashuj hasło przed zapisaniem
+        let password_hash = hash_password(&user.password)?;
+        
+        // Ustaw domyślną rolę client, jeśli nie podano
+        let role = match user.role {
+            Some(role) => role,
+            None => UserRole::Client.to_string()
+        };
+        
+        sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (username, email, password_hash, full_name, phone_number, role, email_verified_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#
+        )
+        .bind(&user.username)
+        .bind(&user.email)
+        .bind(&password_hash)
+        .bind(&user.full_name)
+        .bind(&user.phone_number)
+        .bind(&role)
+        .bind(email_verified_at)
+        .fetch_one(executor)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    // Writes only the fields present in the request; `phone_number: Some(None)` clears the column.
+    // With `expected_versions` the row is only updated while its `updated_at` is one of them
+    pub async fn update(&self, id: Uuid, user: UpdateUserRequest, expected_versions: Option<&[DateTime<Utc>]>) -> Result<User, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "update_user",
+            "UPDATE users SET ... WHERE id = $1 AND deleted_at IS NULL",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            // Hasło jest hashowane tylko wtedy, gdy podano nowe
+            let password_hash = user.password.as_deref().map(hash_password).transpose()?;
+
+            tracing::debug!(
+                "Updating user: id={}, username={:?}, email={:?}, active={:?}, role={:?}",
+                id, user.username, user.email, user.active, user.role
+            );
+
+            let mut builder = QueryBuilder::<Postgres>::new("UPDATE users SET updated_at = NOW()");
+            if let Some(username) = &user.username {
+                builder.push(", username = ").push_bind(username);
+            }
+            // Nowy adres musi zostać potwierdzony od nowa
+            if let Some(email) = &user.email {
+                builder.push(", email = ").push_bind(email).push(", email_verified_at = NULL");
+            }
+            // Nowe hasło spełnia też wymóg jego zmiany
+            if let Some(password_hash) = &password_hash {
+                builder.push(", password_hash = ").push_bind(password_hash).push(", must_change_password = FALSE");
+            }
+            if let Some(full_name) = &user.full_name {
+                builder.push(", full_name = ").push_bind(full_name);
+            }
+            if let Some(phone_number) = &user.phone_number {
+                builder.push(", phone_number = ").push_bind(phone_number);
+            }
+            if let Some(active) = user.active {
+                builder.push(", active = ").push_bind(active);
+            }
+            if let Some(role) = &user.role {
+                builder.push(", role = ").push_bind(role);
+            }
+            builder.push(" WHERE id = ").push_bind(id).push(" AND deleted_at IS NULL");
+            if let Some(versions) = expected_versions {
+                builder.push(" AND updated_at = ANY(").push_bind(versions.to_vec()).push(")");
+            }
+            builder.push(" RETURNING *");
+
+            let updated = builder
+                .build_query_as::<User>()
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            match updated {
+                Some(user) => Ok(user),
+                None => Err(self.version_conflict(id).await),
+            }
+        }).instrument(span).await
+    }
+
+    // Nothing was written: either the user is gone or it no longer has the expected version
+    async fn version_conflict(&self, id: Uuid) -> AppError {
+        match self.find_by_id(id).await {
+            Ok(_) => AppError::PreconditionFailed(format!("User with id {} has been modified by another request", id)),
+            Err(e) => e,
+        }
+    }
+
+    // Sets a new password chosen by the user, which also clears must_change_password
+    pub async fn update_password(&self, id: Uuid, password_hash: &str) -> Result<(), AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "update_user_password",
+            "UPDATE users SET password_hash = $1, must_change_password = FALSE, updated_at = NOW() WHERE id = $2 AND deleted_at IS NULL",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            let result = sqlx::query("UPDATE users SET password_hash = $1, must_change_password = FALSE, updated_at = NOW() WHERE id = $2 AND deleted_at IS NULL")
+                .bind(password_hash)
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            if result.rows_affected() == 0 {
+                return Err(AppError::NotFoundError(format!("User with id {} not found", id)));
+            }
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // Flags the account so that the next login only gets a password change token
+    pub async fn require_password_change(&self, id: Uuid) -> Result<(), AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "require_user_password_change",
+            "UPDATE users SET must_change_password = TRUE WHERE id = $1 AND deleted_at IS NULL",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            sqlx::query("UPDATE users SET must_change_password = TRUE WHERE id = $1 AND deleted_at IS NULL")
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(())
+        }).instrument(span).await
+    }
+
+    // Replaces the hash only if it has not changed in the meantime; does not touch updated_at
+    pub async fn rehash_password(&self, id: Uuid, current_hash: &str, new_hash: &str) -> Result<bool, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "rehash_user_password",
+            "UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3 AND deleted_at IS NULL",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            let result = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3 AND deleted_at IS NULL")
+                .bind(new_hash)
+                .bind(id)
+                .bind(current_hash)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            Ok(result.rows_affected() > 0)
+        }).instrument(span).await
+    }
+
+    // A failed upgrade is only logged - the login itself has succeeded
+    async fn upgrade_password_hash(&self, user: &User, password: &str) {
+        let result = match hash_password(password) {
+            Ok(new_hash) => self.rehash_password(user.id, &user.password_hash, &new_hash).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(true) => tracing::info!("Password hash of user {} upgraded", user.id),
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to upgrade password hash of user {}: {}", user.id, e),
+        }
+    }
+
+    pub async fn mark_email_verified(&self, id: Uuid) -> Result<User, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "mark_user_email_verified",
+            "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW() WHERE id = $1 AND deleted_at IS NULL RETURNING *",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
             let user = sqlx::query_as::<_, User>(
                 r#"
-                INSERT INTO users (username, email, password_hash, full_name, phone_number, role)
-                VALUES ($1, $2, $3, $4, $5, $6)
+                UPDATE users
+                SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW()
+                WHERE id = $1 AND deleted_at IS NULL
                 RETURNING *
                 "#
             )
-            .bind(&user.username)
-            .bind(&user.email)
-            .bind(&password_hash)
-            .bind(&user.full_name)
-            .bind(&user.phone_number)
-            .bind(&role)
-            .fetch_one(&self.pool)
+            .bind(id)
+            .fetch_optional(&self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
 
-            Ok(user)
+            user.ok_or_else(|| AppError::NotFoundError(format!("User with id {} not found", id)))
         }).instrument(span).await
     }
 
-    pub async fn update(&self, id: Uuid, user: UpdateUserRequest) -> Result<User, AppError> {
+    // Soft delete: the account disappears from all queries, but its row and appointments are kept
+    // until the purge, so it can still be restored
+    pub async fn delete(&self, id: Uuid, expected_versions: Option<&[DateTime<Utc>]>) -> Result<(), AppError> {
         let params = format!("id={}", id);
         let span = create_db_span(
-            "update_user",
-            "UPDATE users SET ... WHERE id = $1",
+            "delete_user",
+            "UPDATE users SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL AND ($2::timestamptz[] IS NULL OR updated_at = ANY($2))",
             &params,
         );
         
         DbMetrics::track("UPDATE", "users", || async {
-            // Najpierw sprawdzamy, czy użytkownik istnieje
-            let existing = self.find_by_id(id).await?;
-
-            // Przygotowanie wartości do aktualizacji
-            let username = user.username.unwrap_or(existing.username);
-            let email = user.email.unwrap_or(existing.email);
-            let full_name = user.full_name.unwrap_or(existing.full_name);
-            let phone_number = user.phone_number.or(existing.phone_number);
-            let active = user.active.unwrap_or(existing.active);
-            let role = user.role.unwrap_or(existing.role);
-            
-            // Aktualizacja hasła tylko jeśli podano nowe
-            let password_hash = match user.password {
-                Some(new_password) => hash_password(&new_password)?,
-                None => existing.password_hash
-            };
+            tracing::info!("Deleting user with id={}", id);
 
-            tracing::debug!(
-                "Updating user: id={}, username={}, email={}, active={}, role={}",
-                id, username, email, active, role
-            );
+            let result = sqlx::query(
+                "UPDATE users SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL AND ($2::timestamptz[] IS NULL OR updated_at = ANY($2))"
+            )
+            .bind(id)
+            .bind(expected_versions)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            if result.rows_affected() == 0 {
+                return Err(self.version_conflict(id).await);
+            }
+
+            Ok(())
+        }).instrument(span).await
+    }
 
-            let updated_user = sqlx::query_as::<_, User>(
+    // Brings back a soft-deleted account; other accounts are not found
+    pub async fn restore(&self, id: Uuid) -> Result<User, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "restore_user",
+            "UPDATE users SET deleted_at = NULL, updated_at = NOW() WHERE id = $1 AND deleted_at IS NOT NULL AND erased_at IS NULL RETURNING *",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            let user = sqlx::query_as::<_, User>(
                 r#"
                 UPDATE users
-                SET username = $1, email = $2, password_hash = $3, full_name = $4, 
-                    phone_number = $5, active = $6, role = $7, updated_at = NOW()
-                WHERE id = $8
+                SET deleted_at = NULL, updated_at = NOW()
+                WHERE id = $1 AND deleted_at IS NOT NULL AND erased_at IS NULL
                 RETURNING *
                 "#
             )
-            .bind(&username)
-            .bind(&email)
-            .bind(&password_hash)
-            .bind(&full_name)
-            .bind(&phone_number)
-            .bind(&active)
-            .bind(&role)
             .bind(id)
-            .fetch_one(&self.pool)
+            .fetch_optional(&self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
 
-            Ok(updated_user)
+            user.ok_or_else(|| AppError::NotFoundError(format!("Deleted user with id {} not found", id)))
         }).instrument(span).await
     }
 
-    pub async fn delete(&self, id: Uuid) -> Result<(), AppError> {
+    // Kończy okres przechowywania kont usuniętych przed `deleted_before`: dane osobowe są anonimizowane,
+    // a wiersz zostaje, więc wizyty i wiadomości czatu nie są usuwane kaskadowo; zwraca id kont
+    pub async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Uuid>, AppError> {
+        let params = format!("deleted_before={}", deleted_before);
+        let span = create_db_span(
+            "purge_deleted_users",
+            "SELECT id FROM users WHERE deleted_at < $1 AND erased_at IS NULL",
+            &params,
+        );
+        
+        let ids = DbMetrics::track("SELECT", "users", || async {
+            sqlx::query_scalar::<_, Uuid>("SELECT id FROM users WHERE deleted_at < $1 AND erased_at IS NULL")
+                .bind(deleted_before)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)
+        }).instrument(span).await?;
+
+        for id in &ids {
+            self.anonymize(*id).await?;
+        }
+
+        Ok(ids)
+    }
+    
+    // Marks the account for erasure at `scheduled_at`; accounts already erased are not found
+    pub async fn schedule_erasure(&self, id: Uuid, scheduled_at: DateTime<Utc>) -> Result<User, AppError> {
+        let params = format!("id={}, scheduled_at={}", id, scheduled_at);
+        let span = create_db_span(
+            "schedule_user_erasure",
+            "UPDATE users SET erasure_scheduled_at = $2, updated_at = NOW() WHERE id = $1 AND erased_at IS NULL AND deleted_at IS NULL RETURNING *",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users
+                SET erasure_scheduled_at = $2, updated_at = NOW()
+                WHERE id = $1 AND erased_at IS NULL AND deleted_at IS NULL
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .bind(scheduled_at)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            user.ok_or_else(|| AppError::NotFoundError(format!("User with id {} not found", id)))
+        }).instrument(span).await
+    }
+
+    pub async fn cancel_erasure(&self, id: Uuid) -> Result<User, AppError> {
         let params = format!("id={}", id);
         let span = create_db_span(
-            "delete_user",
-            "DELETE FROM users WHERE id = $1",
+            "cancel_user_erasure",
+            "UPDATE users SET erasure_scheduled_at = NULL, updated_at = NOW() WHERE id = $1 AND erased_at IS NULL AND deleted_at IS NULL RETURNING *",
             &params,
         );
         
-        DbMetrics::track("DELETE", "users", || async {
-            // Najpierw sprawdzamy, czy użytkownik istnieje
-            let _ = self.find_by_id(id).await?;
+        DbMetrics::track("UPDATE", "users", || async {
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users
+                SET erasure_scheduled_at = NULL, updated_at = NOW()
+                WHERE id = $1 AND erased_at IS NULL AND deleted_at IS NULL
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
 
-            tracing::info!("Deleting user with id={}", id);
+            user.ok_or_else(|| AppError::NotFoundError(format!("User with id {} not found", id)))
+        }).instrument(span).await
+    }
 
-            sqlx::query("DELETE FROM users WHERE id = $1")
+    // Accounts whose grace period has ended
+    pub async fn find_due_erasures(&self) -> Result<Vec<Uuid>, AppError> {
+        let span = create_db_span(
+            "find_due_user_erasures",
+            "SELECT id FROM users WHERE erasure_scheduled_at <= NOW() AND erased_at IS NULL AND deleted_at IS NULL",
+            "None",
+        );
+        
+        DbMetrics::track("SELECT", "users", || async {
+            let ids = sqlx::query_scalar::<_, Uuid>(
+                "SELECT id FROM users WHERE erasure_scheduled_at <= NOW() AND erased_at IS NULL AND deleted_at IS NULL"
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            Ok(ids)
+        }).instrument(span).await
+    }
+
+    // Zastępuje dane osobowe konta zanonimizowanymi wartościami zamiast usuwać wiersz, więc wizyty
+    // i wiadomości czatu (z anonimowym nadawcą) zostają zachowane. Dane logowania są usuwane.
+    // Also used for soft-deleted accounts once their retention period ends.
+    pub async fn anonymize(&self, id: Uuid) -> Result<User, AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "anonymize_user",
+            "UPDATE users SET username = ..., email = ..., full_name = $2, phone_number = NULL, role = $3, active = FALSE, erased_at = NOW() WHERE id = $1 AND erased_at IS NULL RETURNING *",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+            let email = sqlx::query_scalar::<_, String>("SELECT email FROM users WHERE id = $1 AND erased_at IS NULL FOR UPDATE")
                 .bind(id)
-                .execute(&self.pool)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?
+                .ok_or_else(|| AppError::NotFoundError(format!("User with id {} not found", id)))?;
+
+            // Unikalne wartości zastępcze pochodzą z id - hasło "!" nie pasuje do żadnego hasha
+            let user = sqlx::query_as::<_, User>(
+                r#"
+                UPDATE users
+                SET username = 'erased_' || replace(id::text, '-', ''),
+                    email = 'erased-' || id::text || '@erased.invalid',
+                    full_name = $2,
+                    phone_number = NULL,
+                    role = $3,
+                    password_hash = '!',
+                    active = FALSE,
+                    email_verified_at = NULL,
+                    must_change_password = FALSE,
+                    failed_login_count = 0,
+                    locked_until = NULL,
+                    erasure_scheduled_at = NULL,
+                    erased_at = NOW(),
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING *
+                "#
+            )
+            .bind(id)
+            .bind(ERASED_USER_NAME)
+            .bind(UserRole::Client.to_string())  // no custom role stays referenced by an erased account
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+            sqlx::query("UPDATE chat_messages SET sender_name = $2 WHERE sender_id = $1")
+                .bind(id)
+                .bind(ERASED_USER_NAME)
+                .execute(&mut *tx)
                 .await
                 .map_err(AppError::DatabaseError)?;
 
-            Ok(())
+            sqlx::query("UPDATE invitations SET email = $2 WHERE accepted_user_id = $1 OR email = $3")
+                .bind(id)
+                .bind(&user.email)
+                .bind(&email)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            sqlx::query("DELETE FROM login_attempts WHERE email = $1")
+                .bind(&email)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            // Credentials and device data (sessions keep the IP address and user agent)
+            for table in ERASED_CREDENTIAL_TABLES {
+                sqlx::query(&format!("DELETE FROM {} WHERE user_id = $1", table))
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(AppError::DatabaseError)?;
+            }
+
+            tx.commit().await.map_err(AppError::DatabaseError)?;
+
+            tracing::info!("Personal data of user {} anonymized", id);
+            Ok(user)
         }).instrument(span).await
     }
     
-    pub async fn authenticate(&self, login: LoginRequest) -> Result<User, AppError> {
+    // `require_verified_email` rejects accounts which have not confirmed their email address yet,
+    // `lockout` decides when repeated wrong passwords lock the account
+    pub async fn authenticate(
+        &self,
+        login: LoginRequest,
+        require_verified_email: bool,
+        lockout: &LockoutConfig,
+    ) -> Result<User, AppError> {
         let params = format!("email={}", login.email);
         let span = create_db_span(
             "authenticate_user",
-            "SELECT * FROM users WHERE email = $1",
+            "SELECT * FROM users WHERE email = $1 AND deleted_at IS NULL",
             &params,
         );
         
         DbMetrics::track("SELECT", "users", || async {
             // Znajdź użytkownika po emailu
-            let user = self.find_by_email(&login.email).await?;
+            let mut user = self.find_by_email(&login.email).await?;
+            
+            // Zablokowane konto - hasło nie jest nawet sprawdzane
+            Self::ensure_not_locked(&user)?;
             
             // Zweryfikuj hasło
             let is_valid = verify_password(&login.password, &user.password_hash)?;
             
             if !is_valid {
                 tracing::warn!("Authentication failed for user: {}", login.email);
+                self.register_failed_login(user.id, lockout).await?;
                 return Err(AppError::ValidationError("Invalid credentials".to_string()));
             }
             
@@ -224,35 +729,106 @@ impl UserRepository {
                 return Err(AppError::ValidationError("Account is inactive".to_string()));
             }
             
+            // Jeśli wymagana jest weryfikacja, odrzuć konta z niepotwierdzonym emailem
+            if require_verified_email && user.email_verified_at.is_none() {
+                tracing::warn!("Attempt to log in with unverified email: {}", login.email);
+                return Err(AppError::Forbidden("Email address has not been verified".to_string()));
+            }
+            
+            // Licznik błędów jest zerowany dopiero po pełnym logowaniu (także po 2FA),
+            // inaczej poprawne hasło odblokowywałoby zgadywanie kodów TOTP
+            
+            // Domyślne hasło z pierwszej migracji wymusza zmianę także wtedy, gdy jego hash
+            // został już zastąpiony (migracja oznacza tylko konta z pierwotnym hashem bcrypt)
+            if !user.must_change_password && login.password == DEFAULT_PASSWORD {
+                self.require_password_change(user.id).await?;
+                user.must_change_password = true;
+            }
+            
+            // Hasło jest teraz znane, więc przestarzały hash (inny algorytm lub parametry) zastępujemy nowym
+            if password_needs_rehash(&user.password_hash) {
+                self.upgrade_password_hash(&user, &login.password).await;
+            }
+            
             tracing::info!("User authenticated successfully: {}", login.email);
             Ok(user)
         }).instrument(span).await
     }
     
-    pub async fn find_by_role(&self, role: &str) -> Result<Vec<User>, AppError> {
-        let params = format!("role={}", role);
+    // Zwraca błąd 429, jeśli konto jest tymczasowo zablokowane
+    pub fn ensure_not_locked(user: &User) -> Result<(), AppError> {
+        match user.locked_until {
+            Some(until) if until > Utc::now() => Err(AppError::TooManyRequests {
+                message: "Account is temporarily locked due to too many failed login attempts".to_string(),
+                retry_after: retry_after_seconds(until),
+            }),
+            _ => Ok(()),
+        }
+    }
+    
+    // Counts a failed login; once the threshold is reached the account is locked,
+    // and every further failure doubles the lock duration (up to the configured maximum)
+    pub async fn register_failed_login(&self, id: Uuid, lockout: &LockoutConfig) -> Result<(), AppError> {
+        let params = format!("id={}", id);
         let span = create_db_span(
-            "find_users_by_role",
-            "SELECT * FROM users WHERE role = $1 ORDER BY created_at DESC",
+            "register_failed_login",
+            "UPDATE users SET failed_login_count = failed_login_count + 1, locked_until = ... WHERE id = $1 AND deleted_at IS NULL RETURNING failed_login_count, locked_until",
             &params,
         );
         
-        DbMetrics::track("SELECT", "users", || async {
-            // Waliduj rolę
-            let valid_role = validate_role(role)?;
-            
-            tracing::debug!("Finding users with role: {}", valid_role);
-
-            let users = sqlx::query_as::<_, User>(
-                "SELECT * FROM users WHERE role = $1 ORDER BY created_at DESC"
+        DbMetrics::track("UPDATE", "users", || async {
+            let (failed_login_count, locked_until) = sqlx::query_as::<_, (i32, Option<DateTime<Utc>>)>(
+                r#"
+                UPDATE users
+                SET failed_login_count = failed_login_count + 1,
+                    locked_until = CASE
+                        WHEN failed_login_count + 1 >= $2
+                        THEN NOW() + LEAST($3 * POWER(2, failed_login_count + 1 - $2), $4) * INTERVAL '1 second'
+                        ELSE locked_until
+                    END
+                WHERE id = $1 AND deleted_at IS NULL
+                RETURNING failed_login_count, locked_until
+                "#
             )
-            .bind(&valid_role)
-            .fetch_all(&self.pool)
+            .bind(id)
+            .bind(lockout.max_failed_logins)
+            .bind(lockout.base_lockout_seconds)
+            .bind(lockout.max_lockout_seconds)
+            .fetch_one(&self.pool)
             .await
             .map_err(AppError::DatabaseError)?;
             
-            tracing::debug!("Found {} users with role '{}'", users.len(), valid_role);
-            Ok(users)
+            if let Some(until) = locked_until.filter(|until| *until > Utc::now()) {
+                tracing::warn!(
+                    target: "security",
+                    event = "account_locked",
+                    user_id = %id,
+                    failed_login_count,
+                    locked_until = %until,
+                    "Account locked after repeated failed login attempts"
+                );
+            }
+            
+            Ok(())
+        }).instrument(span).await
+    }
+    
+    pub async fn reset_failed_logins(&self, id: Uuid) -> Result<(), AppError> {
+        let params = format!("id={}", id);
+        let span = create_db_span(
+            "reset_failed_logins",
+            "UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = $1 AND deleted_at IS NULL",
+            &params,
+        );
+        
+        DbMetrics::track("UPDATE", "users", || async {
+            sqlx::query("UPDATE users SET failed_login_count = 0, locked_until = NULL WHERE id = $1 AND deleted_at IS NULL")
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+            
+            Ok(())
         }).instrument(span).await
     }
     
@@ -260,13 +836,13 @@ impl UserRepository {
     pub async fn count_users_by_role(&self) -> Result<Vec<(String, i64)>, AppError> {
         let span = create_db_span(
             "count_users_by_role",
-            "SELECT role, COUNT(*) FROM users GROUP BY role",
+            "SELECT role, COUNT(*) FROM users WHERE deleted_at IS NULL GROUP BY role",
             "None",
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let counts = sqlx::query!(
-                "SELECT role, COUNT(*) as count FROM users GROUP BY role"
+                "SELECT role, COUNT(*) as count FROM users WHERE deleted_at IS NULL GROUP BY role"
             )
             .fetch_all(&self.pool)
             .await
@@ -285,13 +861,13 @@ impl UserRepository {
     pub async fn count_inactive_users(&self) -> Result<i64, AppError> {
         let span = create_db_span(
             "count_inactive_users",
-            "SELECT COUNT(*) FROM users WHERE active = false",
+            "SELECT COUNT(*) FROM users WHERE active = false AND deleted_at IS NULL",
             "None",
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let result = sqlx::query!(
-                "SELECT COUNT(*) as count FROM users WHERE active = false"
+                "SELECT COUNT(*) as count FROM users WHERE active = false AND deleted_at IS NULL"
             )
             .fetch_one(&self.pool)
             .await
@@ -305,13 +881,13 @@ impl UserRepository {
     pub async fn count_registrations_last_24h(&self) -> Result<i64, AppError> {
         let span = create_db_span(
             "count_registrations_last_24h",
-            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '24 hours'",
+            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '24 hours' AND deleted_at IS NULL",
             "None",
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let result = sqlx::query!(
-                "SELECT COUNT(*) as count FROM users WHERE created_at > NOW() - INTERVAL '24 hours'"
+                "SELECT COUNT(*) as count FROM users WHERE created_at > NOW() - INTERVAL '24 hours' AND deleted_at IS NULL"
             )
             .fetch_one(&self.pool)
             .await
@@ -325,13 +901,13 @@ impl UserRepository {
     pub async fn count_registrations_last_7d(&self) -> Result<i64, AppError> {
         let span = create_db_span(
             "count_registrations_last_7d",
-            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '7 days'",
+            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '7 days' AND deleted_at IS NULL",
             "None",
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let result = sqlx::query!(
-                "SELECT COUNT(*) as count FROM users WHERE created_at > NOW() - INTERVAL '7 days'"
+                "SELECT COUNT(*) as count FROM users WHERE created_at > NOW() - INTERVAL '7 days' AND deleted_at IS NULL"
             )
             .fetch_one(&self.pool)
             .await
@@ -345,13 +921,13 @@ impl UserRepository {
     pub async fn count_registrations_last_30d(&self) -> Result<i64, AppError> {
         let span = create_db_span(
             "count_registrations_last_30d",
-            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '30 days'",
+            "SELECT COUNT(*) FROM users WHERE created_at > NOW() - INTERVAL '30 days' AND deleted_at IS NULL",
             "None",
         );
         
         DbMetrics::track("SELECT", "users", || async {
             let result = sqlx::query!(
-                "SELECT COUNT(*) as count FROM users WHERE created_at > NOW() - INTERVAL '30 days'"
+                "SELECT COUNT(*) as count FROM users WHERE created_at > NOW() - INTERVAL '30 days' AND deleted_at IS NULL"
             )
             .fetch_one(&self.pool)
             .await
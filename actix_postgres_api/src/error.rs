@@ -1,8 +1,10 @@
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use actix_web::{http::{header, StatusCode}, HttpResponse, ResponseError};
 use serde::Serialize;
 use sqlx::error::Error as SqlxError;
 use thiserror::Error;
 
+use crate::auth_utils::PasswordViolation;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -17,20 +19,38 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
     
+    // Missing, malformed or invalid access token
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    
     #[error("Forbidden: {0}")]
     Forbidden(String),
     
     #[error("Validation error: {0}")]
     ValidationError(String),
     
+    // Every rule of the password policy the password breaks
+    #[error("Validation error: Password does not meet the password policy")]
+    PasswordPolicy(Vec<PasswordViolation>),
+    
+    // If-Match nie pasuje do bieżącej wersji zasobu - ktoś zmienił go w międzyczasie
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+    
     #[error("Internal server error: {0}")]
     InternalServerError(String),
+    
+    // Too many attempts - the client should retry after `retry_after` seconds
+    #[error("Too many requests: {message}")]
+    TooManyRequests { message: String, retry_after: u64 },
 }
 
 #[derive(Serialize)]
 struct ErrorResponse {
     status: String,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    violations: Option<Vec<PasswordViolation>>,
 }
 
 impl ResponseError for AppError {
@@ -40,9 +60,13 @@ impl ResponseError for AppError {
             AppError::NotFoundError(_) => StatusCode::NOT_FOUND,
             AppError::NotFound(_) => StatusCode::NOT_FOUND,
             AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
             AppError::Forbidden(_) => StatusCode::FORBIDDEN,
             AppError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            AppError::PasswordPolicy(_) => StatusCode::BAD_REQUEST,
+            AppError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
             AppError::InternalServerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
@@ -51,7 +75,21 @@ impl ResponseError for AppError {
         let error_response = ErrorResponse {
             status: status.to_string(),
             message: self.to_string(),
+            violations: match self {
+                AppError::PasswordPolicy(violations) => Some(violations.clone()),
+                _ => None,
+            },
         };
-        HttpResponse::build(status).json(error_response)
+        let mut response = HttpResponse::build(status);
+        match self {
+            AppError::TooManyRequests { retry_after, .. } => {
+                response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+            }
+            AppError::Unauthorized(_) => {
+                response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+            }
+            _ => {}
+        }
+        response.json(error_response)
     }
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::middleware::{ManageApiKeys, Permitted};
+use crate::models::{ApiKeyQuery, ApiKeyResponse, CreateApiKeyRequest, IssuedApiKeyResponse};
+use crate::services::ApiKeyService;
+
+// Handler wystawiający klucz API - pełny klucz jest zwracany tylko w tej odpowiedzi
+pub async fn issue_api_key(
+    auth: Permitted<ManageApiKeys>,
+    request: web::Json<CreateApiKeyRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    // Klucze nie mogą wystawiać kolejnych kluczy
+    auth.require_session()?;
+
+    let service = ApiKeyService::new(db_pool.get_ref().clone());
+    let (key, api_key) = service.issue(request.into_inner(), auth.id).await?;
+
+    Ok(HttpResponse::Created().json(IssuedApiKeyResponse {
+        key,
+        api_key: ApiKeyResponse::from(api_key),
+        message: "Store the key in a safe place, it will not be shown again".to_string(),
+    }))
+}
+
+pub async fn list_api_keys(
+    _auth: Permitted<ManageApiKeys>,
+    query: web::Query<ApiKeyQuery>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = ApiKeyService::new(db_pool.get_ref().clone());
+    let keys = service.list(query.user_id).await?;
+    let response: Vec<ApiKeyResponse> = keys.into_iter().map(ApiKeyResponse::from).collect();
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn revoke_api_key(
+    _auth: Permitted<ManageApiKeys>,
+    id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = ApiKeyService::new(db_pool.get_ref().clone());
+    let api_key = service.revoke(*id).await?;
+
+    Ok(HttpResponse::Ok().json(ApiKeyResponse::from(api_key)))
+}
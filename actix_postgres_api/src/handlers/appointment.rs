@@ -1,20 +1,18 @@
-use actix_web::{web, HttpResponse, HttpRequest, get, post, put, delete};
-use crate::middleware::auth_middleware::Auth;
-use crate::models::role::UserRole;
+use actix_web::{web, HttpResponse, get, post, put, delete};
+use crate::middleware::{entity_tag, AuthUser, ClientInfo, CreateAppointment, IfMatch, Permitted, ReadAnyAppointment};
+use crate::auth_utils::permissions::{APPOINTMENTS_COMPLETE, APPOINTMENTS_DELETE, APPOINTMENTS_READ, APPOINTMENTS_WRITE};
+use uuid::Uuid;
 use crate::error::AppError;
 use sqlx::postgres::PgPool;
 
 use crate::models::appointment::{CreateAppointmentRequest, UpdateAppointmentRequest, AppointmentResponse, AppointmentWithNames};
-use crate::services::AppointmentService;
+use crate::services::{AppointmentService, AuditContext};
 
 #[get("/appointments")]
 pub async fn get_all_appointments(
-    req: HttpRequest,
+    _auth: Permitted<ReadAnyAppointment>,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Only admins can see all appointments
-    Auth::validate_request(&req, UserRole::Admin)?;
-    
     let service = AppointmentService::new(db_pool.get_ref().clone());
     let appointments = service.get_all_appointments().await?;
     let response: Vec<AppointmentResponse> = appointments.into_iter().map(AppointmentResponse::from).collect();
@@ -24,47 +22,37 @@ pub async fn get_all_appointments(
 
 #[get("/appointments/{id}")]
 pub async fn get_appointment_by_id(
-    req: HttpRequest,
+    auth: AuthUser,
     id: web::Path<String>,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Get the user ID from the token
-    let user_id = Auth::extract_user_id(&req)?;
-    
     // Get the appointment
     let service = AppointmentService::new(db_pool.get_ref().clone());
     let appointment = service.get_appointment_by_id(&id).await?;
     
-    // Check if the user is authorized to view this appointment
-    // Allow if user is the client, the trainer, or an admin
-    let user_role = Auth::extract_role(&req)?;
-    let is_client = appointment.client_id.to_string() == user_id;
-    let is_trainer = appointment.trainer_id.to_string() == user_id;
+    // Participants (client and trainer) need appointments:read:own, everybody else appointments:read:any
+    let is_participant = appointment.client_id == auth.id || appointment.trainer_id == auth.id;
     
-    if !is_client && !is_trainer && user_role != UserRole::Admin {
+    if !auth.can(APPOINTMENTS_READ, is_participant) {
         return Err(AppError::Forbidden("You are not authorized to view this appointment".to_string()));
     }
     
-    Ok(HttpResponse::Ok().json(AppointmentResponse::from(appointment)))
+    Ok(HttpResponse::Ok()
+        .insert_header(entity_tag(&appointment.updated_at))
+        .json(AppointmentResponse::from(appointment)))
 }
 
 #[get("/appointments/client/{id}")]
 pub async fn get_client_appointments(
-    req: HttpRequest,
-    id: web::Path<String>,
+    auth: AuthUser,
+    id: web::Path<Uuid>,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Get the user ID from the token
-    let user_id = Auth::extract_user_id(&req)?;
-    let requested_id = id.to_string();
-    
-    // If not the same user, require admin role
-    if user_id != requested_id {
-        Auth::validate_request(&req, UserRole::Admin)?;
-    }
+    // Appointments of other users require appointments:read:any
+    auth.require_scoped_user(APPOINTMENTS_READ, *id)?;
     
     let service = AppointmentService::new(db_pool.get_ref().clone());
-    let appointments = service.get_client_appointments(&requested_id).await?;
+    let appointments = service.get_client_appointments(&id.to_string()).await?;
     let response: Vec<AppointmentResponse> = appointments.into_iter().map(AppointmentResponse::from).collect();
     
     Ok(HttpResponse::Ok().json(response))
@@ -72,21 +60,15 @@ pub async fn get_client_appointments(
 
 #[get("/appointments/trainer/{id}")]
 pub async fn get_trainer_appointments(
-    req: HttpRequest,
-    id: web::Path<String>,
+    auth: AuthUser,
+    id: web::Path<Uuid>,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Get the user ID from the token
-    let user_id = Auth::extract_user_id(&req)?;
-    let requested_id = id.to_string();
-    
-    // If not the same user, require admin role
-    if user_id != requested_id {
-        Auth::validate_request(&req, UserRole::Admin)?;
-    }
+    // Appointments of other users require appointments:read:any
+    auth.require_scoped_user(APPOINTMENTS_READ, *id)?;
     
     let service = AppointmentService::new(db_pool.get_ref().clone());
-    let appointments = service.get_trainer_appointments(&requested_id).await?;
+    let appointments = service.get_trainer_appointments(&id.to_string()).await?;
     let response: Vec<AppointmentResponse> = appointments.into_iter().map(AppointmentResponse::from).collect();
     
     Ok(HttpResponse::Ok().json(response))
@@ -94,21 +76,18 @@ pub async fn get_trainer_appointments(
 
 #[post("/appointments")]
 pub async fn create_appointment(
-    req: HttpRequest,
+    client: Permitted<CreateAppointment>,
     appointment: web::Json<CreateAppointmentRequest>,
+    client_info: ClientInfo,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Only clients can create appointments
-    Auth::validate_request(&req, UserRole::Client)?;
-    
-    // Get the client ID from the token
-    let client_id = Auth::extract_user_id(&req)?;
-    
     let service = AppointmentService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::new(&client, client_info);
     let created_appointment = service.create_appointment(
-        &client_id, 
+        &client.id.to_string(), 
         appointment.into_inner(),
-        db_pool.get_ref()
+        db_pool.get_ref(),
+        &audit
     ).await?;
     
     Ok(HttpResponse::Created().json(AppointmentResponse::from(created_appointment)))
@@ -116,63 +95,61 @@ pub async fn create_appointment(
 
 #[put("/appointments/{id}")]
 pub async fn update_appointment(
-    req: HttpRequest,
+    auth: AuthUser,
     id: web::Path<String>,
     appointment: web::Json<UpdateAppointmentRequest>,
+    client: ClientInfo,
+    if_match: IfMatch,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Get the user ID from the token
-    let user_id = Auth::extract_user_id(&req)?;
-    let user_role = Auth::extract_role(&req)?;
-    
     // Get the appointment to check ownership
     let service = AppointmentService::new(db_pool.get_ref().clone());
     let existing_appointment = service.get_appointment_by_id(&id).await?;
     
-    // Check if the user is authorized to update this appointment
-    // Allow if user is the client, the trainer, or an admin
-    let is_client = existing_appointment.client_id.to_string() == user_id;
-    let is_trainer = existing_appointment.trainer_id.to_string() == user_id;
+    // Participants need appointments:write:own, everybody else appointments:write:any
+    let is_client = existing_appointment.client_id == auth.id;
+    let is_trainer = existing_appointment.trainer_id == auth.id;
     
-    if !is_client && !is_trainer && user_role != UserRole::Admin {
+    if !auth.can(APPOINTMENTS_WRITE, is_client || is_trainer) {
         return Err(AppError::Forbidden("You are not authorized to update this appointment".to_string()));
     }
     
-    // If trying to change status to completed, only trainer or admin can do that
+    // Completing is a separate permission; of the participants only the trainer owns it
     if let Some(status) = &appointment.status {
-        if status == "completed" && !is_trainer && user_role != UserRole::Admin {
-            return Err(AppError::Forbidden("Only trainers or admins can mark appointments as completed".to_string()));
+        if status == "completed" && !auth.can(APPOINTMENTS_COMPLETE, is_trainer) {
+            return Err(AppError::Forbidden("You are not authorized to mark this appointment as completed".to_string()));
         }
     }
     
-    let updated_appointment = service.update_appointment(&id, appointment.into_inner()).await?;
+    let audit = AuditContext::new(&auth, client);
+    let updated_appointment = service.update_appointment(&id, appointment.into_inner(), &audit, &if_match).await?;
     
-    Ok(HttpResponse::Ok().json(AppointmentResponse::from(updated_appointment)))
+    Ok(HttpResponse::Ok()
+        .insert_header(entity_tag(&updated_appointment.updated_at))
+        .json(AppointmentResponse::from(updated_appointment)))
 }
 
 #[delete("/appointments/{id}")]
 pub async fn delete_appointment(
-    req: HttpRequest,
+    auth: AuthUser,
     id: web::Path<String>,
+    client: ClientInfo,
+    if_match: IfMatch,
     db_pool: web::Data<PgPool>
 ) -> Result<HttpResponse, AppError> {
-    // Get the user ID from the token
-    let user_id = Auth::extract_user_id(&req)?;
-    let user_role = Auth::extract_role(&req)?;
-    
     // Get the appointment to check ownership
     let service = AppointmentService::new(db_pool.get_ref().clone());
     let existing_appointment = service.get_appointment_by_id(&id).await?;
     
-    // Check if the user is authorized to delete this appointment
-    // Allow if user is the client, or an admin
-    let is_client = existing_appointment.client_id.to_string() == user_id;
+    // The booking client needs appointments:delete:own, everybody else appointments:delete:any
+    let is_client = existing_appointment.client_id == auth.id;
     
-    if !is_client && user_role != UserRole::Admin {
+    if !auth.can(APPOINTMENTS_DELETE, is_client) {
         return Err(AppError::Forbidden("You are not authorized to delete this appointment".to_string()));
     }
     
-    service.delete_appointment(&id).await?;
+    let audit = AuditContext::new(&auth, client);
+    service.delete_appointment(&id, &audit, &if_match).await?;
     
     Ok(HttpResponse::NoContent().finish())
 }
@@ -0,0 +1,19 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+
+use crate::error::AppError;
+use crate::middleware::{Permitted, ReadAuditLog};
+use crate::models::AuditEventQuery;
+use crate::services::AuditService;
+
+// Handler przeglądania dziennika audytu z filtrami i stronicowaniem
+pub async fn list_audit_events(
+    _auth: Permitted<ReadAuditLog>,
+    query: web::Query<AuditEventQuery>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuditService::new(db_pool.get_ref().clone());
+    let events = service.list(&query).await?;
+
+    Ok(HttpResponse::Ok().json(events))
+}
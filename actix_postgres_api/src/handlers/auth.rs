@@ -1,25 +1,206 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{http::header, web, FromRequest, HttpRequest, HttpResponse};
 use sqlx::postgres::PgPool;
+use uuid::Uuid;
 
+use crate::auth_utils::jwt::{extract_token_from_header, verify_password_change_token};
+use crate::auth_utils::keys::jwt_keys;
 use crate::error::AppError;
-use crate::models::{LoginRequest, LoginResponse};
+use crate::handlers::chat::close_session_connections;
+use crate::mail::MailSender;
+use crate::middleware::{AuthUser, ClientInfo};
+use crate::models::{
+    ChangePasswordRequest, ForgotPasswordRequest, LoginOutcome, LoginRequest, LoginResponse, RefreshTokenRequest, ResendVerificationRequest,
+    ResetPasswordRequest, TokenResponse, TwoFactorLoginRequest, UserResponse, VerifyEmailRequest,
+};
 use crate::services::AuthService;
 
 pub async fn login(
     login: web::Json<LoginRequest>,
+    client: ClientInfo,
     db_pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
     let service = AuthService::new(db_pool.get_ref().clone());
-    
+
     // Authenticate user
-    let (user, token) = service.login(login.into_inner()).await?;
-    
-    // Create success response
-    let response = LoginResponse {
-        user: user,
-        token: token,
-        message: "Login successful".to_string(),
+    match service.login(login.into_inner(), &client).await? {
+        LoginOutcome::Authenticated(user, tokens) => {
+            // Create success response
+            let response = LoginResponse {
+                user,
+                tokens,
+                message: "Login successful".to_string(),
+            };
+
+            Ok(HttpResponse::Ok().json(response))
+        }
+        // Konto z 2FA - klient musi dokończyć logowanie kodem z aplikacji
+        LoginOutcome::TwoFactorRequired(challenge) => Ok(HttpResponse::Ok().json(challenge)),
+        // Wymuszona zmiana hasła - klient musi wywołać /api/auth/change-password
+        LoginOutcome::PasswordChangeRequired(required) => Ok(HttpResponse::Ok().json(required)),
+    }
+}
+
+// Drugi krok logowania - token wyzwania + kod TOTP lub kod odzyskiwania
+pub async fn login_two_factor(
+    request: web::Json<TwoFactorLoginRequest>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    match service.login_two_factor(&request.challenge_token, &request.code, &client).await? {
+        LoginOutcome::Authenticated(user, tokens) => {
+            let response = LoginResponse {
+                user,
+                tokens,
+                message: "Login successful".to_string(),
+            };
+
+            Ok(HttpResponse::Ok().json(response))
+        }
+        LoginOutcome::PasswordChangeRequired(required) => Ok(HttpResponse::Ok().json(required)),
+        LoginOutcome::TwoFactorRequired(_) => {
+            Err(AppError::InternalServerError("Unexpected two-factor challenge".to_string()))
+        }
+    }
+}
+
+// Handler zmiany hasła - przyjmuje token zmiany hasła z logowania albo zwykły token dostępu.
+// Zawsze wymaga aktualnego hasła; kończy wszystkie sesje i zwraca tokeny nowej.
+pub async fn change_password(
+    req: HttpRequest,
+    request: web::Json<ChangePasswordRequest>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let password_change_claims = req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| extract_token_from_header(value).ok())
+        .and_then(|token| verify_password_change_token(token).ok());
+
+    let user_id = match password_change_claims {
+        Some(claims) => Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::Unauthorized("Invalid password change token".to_string()))?,
+        None => {
+            let auth = AuthUser::extract(&req).await?;
+            auth.require_session()?;
+            auth.id
+        }
+    };
+
+    let service = AuthService::new(db_pool.get_ref().clone());
+    let (user, tokens, revoked_sessions) = service
+        .change_password(user_id, &request.current_password, &request.new_password, &client)
+        .await?;
+    close_session_connections(&revoked_sessions);
+
+    Ok(HttpResponse::Ok().json(LoginResponse {
+        user,
+        tokens,
+        message: "Password changed successfully".to_string(),
+    }))
+}
+
+// Handler wymieniający refresh token na nową parę tokenów
+pub async fn refresh_token(
+    request: web::Json<RefreshTokenRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    let tokens = service.refresh(&request.refresh_token).await?;
+
+    let response = TokenResponse {
+        tokens,
+        message: "Token refreshed successfully".to_string(),
     };
-    
+
     Ok(HttpResponse::Ok().json(response))
-}
\ No newline at end of file
+}
+
+// Handler wylogowania - kończy sesję i unieważnia całą rodzinę refresh tokenów
+pub async fn logout(
+    request: web::Json<RefreshTokenRequest>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    let session_id = service.logout(&request.refresh_token, &client).await?;
+    close_session_connections(&[session_id]);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler rozpoczynający reset hasła - wysyła link z jednorazowym tokenem
+pub async fn forgot_password(
+    request: web::Json<ForgotPasswordRequest>,
+    db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn MailSender>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    service.forgot_password(&request.email, mailer.get_ref()).await?;
+
+    // Ta sama odpowiedź niezależnie od tego, czy konto istnieje
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "If an account with this email exists, a password reset link has been sent"
+    })))
+}
+
+// Handler ustawiający nowe hasło na podstawie tokenu z wiadomości email
+pub async fn reset_password(
+    request: web::Json<ResetPasswordRequest>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    let revoked_sessions = service.reset_password(&request.token, &request.new_password, &client).await?;
+    close_session_connections(&revoked_sessions);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Password has been reset successfully"
+    })))
+}
+
+// Handler potwierdzający adres email na podstawie tokenu z wiadomości
+pub async fn verify_email(
+    request: web::Json<VerifyEmailRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    let user = service.verify_email(&request.token).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "user": UserResponse::from(user),
+        "message": "Email address verified successfully"
+    })))
+}
+
+// Handler ponownie wysyłający link weryfikacyjny
+pub async fn resend_verification(
+    request: web::Json<ResendVerificationRequest>,
+    db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn MailSender>,
+) -> Result<HttpResponse, AppError> {
+    let service = AuthService::new(db_pool.get_ref().clone());
+
+    service.resend_verification_email(&request.email, mailer.get_ref()).await?;
+
+    // Ta sama odpowiedź niezależnie od tego, czy konto istnieje i czy jest już zweryfikowane
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "If an unverified account with this email exists, a verification link has been sent"
+    })))
+}
+
+// Handler publikujący klucze publiczne JWT (JWKS) dla innych usług
+pub async fn jwks() -> Result<HttpResponse, AppError> {
+    let keys = jwt_keys()?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(keys.jwks()))
+}
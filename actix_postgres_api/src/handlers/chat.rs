@@ -1,4 +1,4 @@
-use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Message, StreamHandler};
+use actix::{Actor, ActorContext, ActorFutureExt, Addr, AsyncContext, Handler, Message, StreamHandler, WrapFuture};
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
 use serde_json::json;
@@ -8,9 +8,11 @@ use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
 use crate::auth_utils::jwt::{extract_token_from_header, verify_token};
+use crate::auth_utils::sessions;
 use crate::database::ChatRepository;
 use crate::error::AppError;
 use crate::models::chat::{ChatMessage, ChatMessageResponse, ChatRoom, WsMessage};
+use crate::services::SessionService;
 
 // Define the WebSocket connection actor
 pub struct ChatWebSocket {
@@ -18,6 +20,8 @@ pub struct ChatWebSocket {
     user_id: Uuid,
     // User's display name
     user_name: String,
+    // Login session of the token the connection was authenticated with
+    session_id: Option<Uuid>,
     // Current chat room
     room_id: Option<String>,
     // Database connection pool
@@ -32,6 +36,8 @@ pub struct ChatServer {
     rooms: HashMap<String, HashSet<Uuid>>,
     // Map of user_id -> session address
     sessions: HashMap<Uuid, Addr<ChatWebSocket>>,
+    // Map of login session id -> connections authenticated with its tokens
+    connections: HashMap<Uuid, Vec<Addr<ChatWebSocket>>>,
 }
 
 impl ChatServer {
@@ -39,6 +45,32 @@ impl ChatServer {
         Self {
             rooms: HashMap::new(),
             sessions: HashMap::new(),
+            connections: HashMap::new(),
+        }
+    }
+
+    // Remember a connection authenticated with a token of the login session
+    pub fn register_connection(&mut self, session_id: Uuid, addr: Addr<ChatWebSocket>) {
+        self.connections.entry(session_id).or_default().push(addr);
+    }
+
+    pub fn unregister_connection(&mut self, session_id: &Uuid, addr: &Addr<ChatWebSocket>) {
+        if let Some(connections) = self.connections.get_mut(session_id) {
+            connections.retain(|connection| connection != addr);
+            if connections.is_empty() {
+                self.connections.remove(session_id);
+            }
+        }
+    }
+
+    // Close all connections of the revoked login sessions
+    pub fn close_sessions(&self, session_ids: &[Uuid]) {
+        for session_id in session_ids {
+            if let Some(connections) = self.connections.get(session_id) {
+                for addr in connections {
+                    addr.do_send(SessionRevoked);
+                }
+            }
         }
     }
 
@@ -95,6 +127,92 @@ impl ChatServer {
 #[rtype(result = "()")]
 struct ChatMessageBroadcast(ChatMessage);
 
+// Message closing a connection whose login session was revoked
+#[derive(Message)]
+#[rtype(result = "()")]
+struct SessionRevoked;
+
+impl ChatWebSocket {
+    fn close_revoked(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        tracing::info!("Closing WebSocket of revoked session for user: {}", self.user_id);
+        ctx.text(json!({
+            "type": "session_revoked"
+        }).to_string());
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Policy,
+            description: Some("Session has been revoked".to_string()),
+        }));
+        ctx.stop();
+    }
+
+    // Verify the token and check in the database that its session is still active
+    fn authenticate(&mut self, token: &str, ctx: &mut ws::WebsocketContext<Self>) {
+        let claims = match verify_token(token) {
+            Ok(claims) => claims,
+            Err(e) => {
+                tracing::error!("Invalid token in WebSocket connection: {}", e);
+                ctx.text(json!({
+                    "error": "Invalid authentication token"
+                }).to_string());
+                ctx.stop();
+                return;
+            }
+        };
+
+        // Chat messages cannot be audited per request, so impersonation tokens are not accepted
+        if claims.act.is_some() {
+            ctx.text(json!({
+                "error": "Impersonation tokens cannot be used for chat"
+            }).to_string());
+            ctx.stop();
+            return;
+        }
+
+        let (Ok(user_id), Ok(session_id)) = (Uuid::parse_str(&claims.sub), Uuid::parse_str(&claims.sid)) else {
+            ctx.text(json!({
+                "error": "Invalid user ID in token"
+            }).to_string());
+            ctx.stop();
+            return;
+        };
+
+        let service = SessionService::new(self.db_pool.get_ref().clone());
+        let check = async move { service.touch(session_id, user_id).await };
+
+        // Kolejne wiadomości czekają na wynik sprawdzenia sesji
+        ctx.wait(check.into_actor(self).map(move |result, actor, ctx| match result {
+            Ok(true) => {
+                // Set the authenticated user information
+                actor.user_id = user_id;
+                actor.user_name = claims.name;
+                actor.session_id = Some(session_id);
+                actor.server.lock().unwrap().register_connection(session_id, ctx.address());
+
+                // Send confirmation to the client
+                ctx.text(json!({
+                    "type": "authenticated",
+                    "user_id": user_id
+                }).to_string());
+
+                tracing::info!("WebSocket authenticated for user: {}", user_id);
+            }
+            Ok(false) => {
+                ctx.text(json!({
+                    "error": "Session has been revoked"
+                }).to_string());
+                ctx.stop();
+            }
+            Err(e) => {
+                tracing::error!("Failed to check WebSocket session: {}", e);
+                ctx.text(json!({
+                    "error": "Invalid authentication token"
+                }).to_string());
+                ctx.stop();
+            }
+        }));
+    }
+}
+
 impl Actor for ChatWebSocket {
     type Context = ws::WebsocketContext<Self>;
 
@@ -102,8 +220,12 @@ impl Actor for ChatWebSocket {
         tracing::info!("WebSocket connection started for user: {}", self.user_id);
     }
 
-    fn stopped(&mut self, _ctx: &mut Self::Context) {
+    fn stopped(&mut self, ctx: &mut Self::Context) {
         tracing::info!("WebSocket connection closed for user: {}", self.user_id);
+
+        if let Some(session_id) = &self.session_id {
+            self.server.lock().unwrap().unregister_connection(session_id, &ctx.address());
+        }
         
         // Remove user from room when connection closes
         if let Some(room_id) = &self.room_id {
@@ -133,45 +255,16 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ChatWebSocket {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
         match msg {
             Ok(ws::Message::Text(text)) => {
+                // The session may have been revoked on another device in the meantime
+                if self.session_id.is_some_and(|session_id| sessions::is_revoked(&session_id)) {
+                    self.close_revoked(ctx);
+                    return;
+                }
+
                 // Parse the incoming message
                 match serde_json::from_str::<WsMessage>(&text) {
                     Ok(ws_message) => match ws_message {
-                        WsMessage::Authorization { token } => {
-                            // Verify the token
-                            match verify_token(&token) {
-                                Ok(claims) => {
-                                    // Extract user information from claims
-                                    match Uuid::parse_str(&claims.sub) {
-                                        Ok(user_id) => {
-                                            // Set the authenticated user information
-                                            self.user_id = user_id;
-                                            self.user_name = claims.name;
-                                            
-                                            // Send confirmation to the client
-                                            ctx.text(json!({
-                                                "type": "authenticated",
-                                                "user_id": user_id
-                                            }).to_string());
-                                            
-                                            tracing::info!("WebSocket authenticated for user: {}", user_id);
-                                        },
-                                        Err(_) => {
-                                            ctx.text(json!({
-                                                "error": "Invalid user ID in token"
-                                            }).to_string());
-                                            ctx.stop();
-                                        }
-                                    }
-                                },
-                                Err(e) => {
-                                    tracing::error!("Invalid token in WebSocket connection: {}", e);
-                                    ctx.text(json!({
-                                        "error": "Invalid authentication token"
-                                    }).to_string());
-                                    ctx.stop();
-                                }
-                            }
-                        },
+                        WsMessage::Authorization { token } => self.authenticate(&token, ctx),
                         WsMessage::Connect { user_id, room_id } => {
                             // Check if user is authenticated
                             if self.user_id == Uuid::nil() {
@@ -324,6 +417,14 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for ChatWebSocket {
     }
 }
 
+impl Handler<SessionRevoked> for ChatWebSocket {
+    type Result = ();
+
+    fn handle(&mut self, _msg: SessionRevoked, ctx: &mut Self::Context) {
+        self.close_revoked(ctx);
+    }
+}
+
 // Handle broadcast messages
 impl Handler<ChatMessageBroadcast> for ChatWebSocket {
     type Result = ();
@@ -348,6 +449,11 @@ lazy_static::lazy_static! {
     static ref CHAT_SERVER: Arc<Mutex<ChatServer>> = Arc::new(Mutex::new(ChatServer::new()));
 }
 
+// Close chat connections opened with tokens of the revoked login sessions
+pub fn close_session_connections(session_ids: &[Uuid]) {
+    CHAT_SERVER.lock().unwrap().close_sessions(session_ids);
+}
+
 // WebSocket connection handler
 pub async fn ws_connect(req: HttpRequest, stream: web::Payload, db_pool: web::Data<PgPool>) -> Result<HttpResponse, Error> {
     // Create the WebSocket actor with unauthenticated state
@@ -355,6 +461,7 @@ pub async fn ws_connect(req: HttpRequest, stream: web::Payload, db_pool: web::Da
     let ws = ChatWebSocket {
         user_id: Uuid::nil(), // Will be set after authentication
         user_name: String::new(), // Will be set after authentication
+        session_id: None, // Will be set after authentication
         room_id: None,
         db_pool,
         server: CHAT_SERVER.clone(),
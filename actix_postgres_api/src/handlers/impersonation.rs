@@ -0,0 +1,40 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::middleware::{ClientInfo, ImpersonateUsers, Permitted, ReadAuditLog};
+use crate::models::{ImpersonationLogQuery, ImpersonationResponse, UserResponse};
+use crate::services::ImpersonationService;
+
+// Handler wydający administratorowi ograniczony czasowo token innego użytkownika
+pub async fn impersonate_user(
+    auth: Permitted<ImpersonateUsers>,
+    user_id: web::Path<Uuid>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    // Tylko z własnej sesji - nie kluczem API ani z innej impersonacji
+    let session_id = auth.require_session()?;
+
+    let service = ImpersonationService::new(db_pool.get_ref().clone());
+    let (token, expires_at, user) = service.start(auth.id, session_id, *user_id, &client).await?;
+
+    Ok(HttpResponse::Created().json(ImpersonationResponse {
+        token,
+        expires_at,
+        user: UserResponse::from(user),
+        message: "Impersonation started. Every request made with this token is audited".to_string(),
+    }))
+}
+
+pub async fn list_impersonation_log(
+    _auth: Permitted<ReadAuditLog>,
+    query: web::Query<ImpersonationLogQuery>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = ImpersonationService::new(db_pool.get_ref().clone());
+    let entries = service.list(&query).await?;
+
+    Ok(HttpResponse::Ok().json(entries))
+}
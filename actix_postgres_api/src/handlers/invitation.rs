@@ -0,0 +1,73 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::auth_utils::permissions::{INVITATIONS_READ, INVITATIONS_REVOKE, USERS_ROLE_ASSIGN};
+use crate::error::AppError;
+use crate::mail::MailSender;
+use crate::middleware::{AuthUser, ClientInfo, CreateInvitation, Permitted};
+use crate::models::role::UserRole;
+use crate::models::{AcceptInvitationRequest, CreateInvitationRequest, InvitationResponse, UserResponse};
+use crate::services::{AuditContext, InvitationService};
+
+// Handler tworzący zaproszenie - trenerzy zapraszają klientów, inne role wymagają users:role:assign
+pub async fn create_invitation(
+    auth: Permitted<CreateInvitation>,
+    request: web::Json<CreateInvitationRequest>,
+    db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn MailSender>,
+) -> Result<HttpResponse, AppError> {
+    let role = InvitationService::requested_role(request.role.as_deref())?;
+    if role != UserRole::Client.to_string() {
+        auth.require_permission(USERS_ROLE_ASSIGN)?;
+    }
+
+    let service = InvitationService::new(db_pool.get_ref().clone());
+    let invitation = service.create(auth.id, &request.email, &role, mailer.get_ref()).await?;
+
+    Ok(HttpResponse::Created().json(InvitationResponse::from(invitation)))
+}
+
+// Handler zwracający zaproszenia - wszystkie (invitations:read:any) albo wysłane przez użytkownika
+pub async fn list_invitations(auth: AuthUser, db_pool: web::Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let invited_by = if auth.can(INVITATIONS_READ, false) {
+        None
+    } else {
+        auth.require_scoped(INVITATIONS_READ, true)?;
+        Some(auth.id)
+    };
+
+    let service = InvitationService::new(db_pool.get_ref().clone());
+    let invitations = service.list(invited_by).await?;
+    let response: Vec<InvitationResponse> = invitations.into_iter().map(InvitationResponse::from).collect();
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn revoke_invitation(
+    auth: AuthUser,
+    id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = InvitationService::new(db_pool.get_ref().clone());
+    let invitation = service.get(*id).await?;
+
+    auth.require_scoped(INVITATIONS_REVOKE, invitation.invited_by == Some(auth.id))?;
+
+    let invitation = service.revoke(invitation.id).await?;
+
+    Ok(HttpResponse::Ok().json(InvitationResponse::from(invitation)))
+}
+
+// Publiczny handler przyjmujący zaproszenie - zakłada konto z rolą z zaproszenia
+pub async fn accept_invitation(
+    request: web::Json<AcceptInvitationRequest>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = InvitationService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::anonymous(client);
+    let user = service.accept(request.into_inner(), db_pool.get_ref(), &audit).await?;
+
+    Ok(HttpResponse::Created().json(UserResponse::from(user)))
+}
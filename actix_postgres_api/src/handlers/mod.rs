@@ -4,12 +4,28 @@ pub mod statistics;
 pub mod oauth;
 pub mod chat;
 pub mod appointment;
+pub mod two_factor;
+pub mod role;
+pub mod invitation;
+pub mod api_key;
+pub mod session;
+pub mod impersonation;
+pub mod audit;
+pub mod privacy;
 
 pub use oauth::*;
-pub use user::{get_all_users, get_user_by_id, create_user, update_user, delete_user, get_users_by_role};
+pub use user::{get_all_users, get_user_by_id, create_user, register, update_user, delete_user, restore_user, get_users_by_role};
 pub use chat::{ws_connect, get_chat_rooms, get_room_messages, create_chat_room};
-pub use auth::login;
+pub use two_factor::{enroll_two_factor, confirm_two_factor, disable_two_factor};
+pub use auth::{jwks, login, login_two_factor, refresh_token, logout, forgot_password, reset_password, change_password, verify_email, resend_verification};
 pub use statistics::get_user_statistics;
+pub use invitation::{create_invitation, list_invitations, revoke_invitation, accept_invitation};
+pub use api_key::{issue_api_key, list_api_keys, revoke_api_key};
+pub use session::{list_sessions, revoke_session, revoke_all_sessions};
+pub use impersonation::{impersonate_user, list_impersonation_log};
+pub use audit::list_audit_events;
+pub use privacy::{export_user_data, request_erasure, cancel_erasure};
+pub use role::{list_roles, create_role, update_role, delete_role, list_permissions, grant_permission, revoke_permission};
 
 // Re-export handler configuration functions
 pub use appointment::configure_routes as configure_appointment_routes;
\ No newline at end of file
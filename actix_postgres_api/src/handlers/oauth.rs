@@ -1,34 +1,55 @@
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
 use actix_web::{web, HttpResponse, HttpRequest};
 use sqlx::postgres::PgPool;
 
 use crate::error::AppError;
-use crate::models::LoginResponse;
-use crate::models::user::UserResponse;
-use crate::auth_utils::oauth::{OAuthService, OAuthProvider};
+use crate::middleware::{AuthUser, ClientInfo};
+use crate::models::{LinkIdentityResponse, LoginOutcome, LoginResponse};
+use crate::auth_utils::oauth::{OAuthCallbackOutcome, OAuthConfig, OAuthService, OAuthProvider};
+
+// Cookie wiążący rozpoczęte logowanie OAuth z przeglądarką, która je rozpoczęła
+const OAUTH_STATE_COOKIE: &str = "oauth_state";
+const OAUTH_STATE_COOKIE_PATH: &str = "/api/auth/oauth";
+
+fn state_cookie(value: String, max_age_seconds: i64) -> Cookie<'static> {
+    let config = OAuthConfig::from_env();
+
+    Cookie::build(OAUTH_STATE_COOKIE, value)
+        .path(OAUTH_STATE_COOKIE_PATH)
+        .http_only(true)
+        .secure(config.redirect_url.starts_with("https://"))
+        // Lax still sends the cookie on the top-level redirect back from the provider
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::seconds(max_age_seconds))
+        .finish()
+}
 
 // Handler to initiate OAuth login - redirects to provider's authorization page
 pub async fn oauth_login(
     provider_name: web::Path<String>,
     db_pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
-    // Convert provider name to OAuthProvider enum
-    let provider = OAuthProvider::from(provider_name.as_str());
+    // Convert provider name to OAuthProvider enum (unknown names are rejected)
+    let provider = OAuthProvider::from_name(&provider_name)?;
     
     // Create OAuth service
     let oauth_service = OAuthService::new(db_pool.get_ref().clone());
     
-    // Get authorization URL
-    let auth_url = oauth_service.get_authorization_url(provider);
+    // Persist state + PKCE verifier and get authorization URL
+    let authorization = oauth_service.start_authorization(provider, None).await?;
+    let cookie = state_cookie(authorization.state_id.to_string(), OAuthConfig::from_env().state_expiration);
     
     // Redirect to provider's authorization page
     Ok(HttpResponse::Found()
-        .append_header(("Location", auth_url))
+        .append_header(("Location", authorization.url))
+        .cookie(cookie)
         .finish())
 }
 
 // Handler for OAuth callback - processes the authorization code
 pub async fn oauth_callback(
     req: HttpRequest,
+    client: ClientInfo,
     db_pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
     // Extract query parameters
@@ -37,32 +58,109 @@ pub async fn oauth_callback(
         .into_owned()
         .collect();
     
-    // Extract code and provider from query parameters
-    let code = query_params.iter()
-        .find(|(key, _)| key == "code")
-        .map(|(_, value)| value.clone())
+    let param = |name: &str| query_params.iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone());
+
+    // The user denied access or the provider rejected the request
+    if let Some(error) = param("error") {
+        return Err(AppError::BadRequest(format!("OAuth authorization failed: {}", error)));
+    }
+
+    // Extract code and state from query parameters
+    let code = param("code")
         .ok_or_else(|| AppError::ValidationError("Missing authorization code".to_string()))?;
     
-    let provider_name = query_params.iter()
-        .find(|(key, _)| key == "provider")
-        .map(|(_, value)| value.clone())
-        .ok_or_else(|| AppError::ValidationError("Missing provider".to_string()))?;
+    let state = param("state")
+        .ok_or_else(|| AppError::ValidationError("Missing OAuth state".to_string()))?;
     
-    let provider = OAuthProvider::from(provider_name.as_str());
+    let browser_state_id = req.cookie(OAUTH_STATE_COOKIE).map(|cookie| cookie.value().to_string());
     
     // Create OAuth service
     let oauth_service = OAuthService::new(db_pool.get_ref().clone());
     
     // Process OAuth login
-    let (user, token) = oauth_service.process_oauth_login(provider, &code).await?;
-    
-    // Create success response
-    let response = LoginResponse {
-        user: UserResponse::from(user),
-        token,
-        message: "OAuth login successful".to_string(),
-    };
-    
-    // Return success response
-    Ok(HttpResponse::Ok().json(response))
+    let outcome = oauth_service.process_oauth_login(&state, &code, browser_state_id.as_deref(), &client).await?;
+
+    // The state is single-use, so the cookie is no longer needed
+    let expired_cookie = state_cookie(String::new(), 0);
+
+    match outcome {
+        OAuthCallbackOutcome::Login(LoginOutcome::Authenticated(user, tokens)) => {
+            // Create success response
+            let response = LoginResponse {
+                user,
+                tokens,
+                message: "OAuth login successful".to_string(),
+            };
+
+            // Return success response
+            Ok(HttpResponse::Ok().cookie(expired_cookie).json(response))
+        }
+        // Second step continues at /api/auth/login/2fa
+        OAuthCallbackOutcome::Login(LoginOutcome::TwoFactorRequired(challenge)) => {
+            Ok(HttpResponse::Ok().cookie(expired_cookie).json(challenge))
+        }
+        // The account must set a new password at /api/auth/change-password first
+        OAuthCallbackOutcome::Login(LoginOutcome::PasswordChangeRequired(required)) => {
+            Ok(HttpResponse::Ok().cookie(expired_cookie).json(required))
+        }
+        OAuthCallbackOutcome::Linked(identity) => Ok(HttpResponse::Ok().cookie(expired_cookie).json(serde_json::json!({
+            "message": format!("{} account linked", identity.provider),
+            "identity": identity,
+        }))),
+    }
+}
+
+// Handler listing provider accounts linked to the logged-in user
+pub async fn list_identities(
+    auth: AuthUser,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let user_id = auth.id;
+    let oauth_service = OAuthService::new(db_pool.get_ref().clone());
+
+    let identities = oauth_service.list_identities(user_id).await?;
+
+    Ok(HttpResponse::Ok().json(identities))
+}
+
+// Handler starting the linking of a provider account. The client sends the browser to `authorization_url`;
+// the callback then links the account instead of logging in.
+pub async fn link_identity(
+    auth: AuthUser,
+    provider_name: web::Path<String>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let user_id = auth.id;
+    let provider = OAuthProvider::from_name(&provider_name)?;
+    let oauth_service = OAuthService::new(db_pool.get_ref().clone());
+
+    let authorization = oauth_service.start_authorization(provider, Some(user_id)).await?;
+    let cookie = state_cookie(authorization.state_id.to_string(), OAuthConfig::from_env().state_expiration);
+
+    Ok(HttpResponse::Ok().cookie(cookie).json(LinkIdentityResponse {
+        authorization_url: authorization.url,
+        message: "Continue at the provider to link the account".to_string(),
+    }))
+}
+
+// Handler unlinking a provider account from the logged-in user
+pub async fn unlink_identity(
+    auth: AuthUser,
+    provider_name: web::Path<String>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let user_id = auth.id;
+    let provider = OAuthProvider::from_name(&provider_name)?;
+    let oauth_service = OAuthService::new(db_pool.get_ref().clone());
+
+    oauth_service.unlink_identity(user_id, provider).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Account unlinked"
+    })))
 }
\ No newline at end of file
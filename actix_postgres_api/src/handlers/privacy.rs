@@ -0,0 +1,85 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::auth_utils::permissions::{USERS_DELETE_ANY, USERS_READ, USERS_WRITE};
+use crate::error::AppError;
+use crate::middleware::{AuthUser, ClientInfo};
+use crate::models::{ErasureResponse, ExportFormat, ExportQuery};
+use crate::services::privacy::export_to_zip;
+use crate::services::{AuditContext, PrivacyService};
+
+// Handler eksportujący dane osobowe użytkownika jako JSON albo archiwum ZIP
+pub async fn export_user_data(
+    auth: AuthUser,
+    id: web::Path<Uuid>,
+    query: web::Query<ExportQuery>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    // Own data needs users:read:own, data of other users users:read:any
+    auth.require_scoped_user(USERS_READ, *id)?;
+
+    let service = PrivacyService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::new(&auth, client);
+    let export = service.export(*id, &audit).await?;
+
+    let response = match query.format {
+        ExportFormat::Json => HttpResponse::Ok()
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"user-data-{}.json\"", id)))
+            .json(export),
+        ExportFormat::Zip => HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"user-data-{}.zip\"", id)))
+            .body(export_to_zip(&export)?),
+    };
+
+    Ok(response)
+}
+
+// Handler zgłaszający żądanie usunięcia danych - konto jest anonimizowane po okresie karencji
+pub async fn request_erasure(
+    auth: AuthUser,
+    id: web::Path<Uuid>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_erasure_permission(&auth, *id)?;
+
+    let service = PrivacyService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::new(&auth, client);
+    let user = service.request_erasure(*id, &audit).await?;
+
+    Ok(HttpResponse::Accepted().json(ErasureResponse {
+        user_id: user.id,
+        erasure_scheduled_at: user.erasure_scheduled_at,
+    }))
+}
+
+// Handler wycofujący żądanie usunięcia danych przed upływem okresu karencji
+pub async fn cancel_erasure(
+    auth: AuthUser,
+    id: web::Path<Uuid>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    require_erasure_permission(&auth, *id)?;
+
+    let service = PrivacyService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::new(&auth, client);
+    let user = service.cancel_erasure(*id, &audit).await?;
+
+    Ok(HttpResponse::Ok().json(ErasureResponse {
+        user_id: user.id,
+        erasure_scheduled_at: user.erasure_scheduled_at,
+    }))
+}
+
+// Users manage erasure of their own account, other accounts need users:delete:any
+fn require_erasure_permission(auth: &AuthUser, user_id: Uuid) -> Result<(), AppError> {
+    if auth.id == user_id {
+        auth.require_scoped_user(USERS_WRITE, user_id)
+    } else {
+        auth.require_permission(USERS_DELETE_ANY)
+    }
+}
@@ -0,0 +1,81 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+
+use crate::error::AppError;
+use crate::middleware::{ManageRoles, Permitted};
+use crate::models::role::{CreateRoleRequest, UpdateRoleRequest};
+use crate::services::PermissionService;
+
+// Handler zwracający wszystkie role wraz z uprawnieniami (bezpośrednimi i odziedziczonymi)
+pub async fn list_roles(_auth: Permitted<ManageRoles>, db_pool: web::Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    let roles = service.list_roles().await?;
+
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+pub async fn create_role(
+    _auth: Permitted<ManageRoles>,
+    request: web::Json<CreateRoleRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    let role = service.create_role(request.into_inner()).await?;
+
+    Ok(HttpResponse::Created().json(role))
+}
+
+pub async fn update_role(
+    _auth: Permitted<ManageRoles>,
+    name: web::Path<String>,
+    request: web::Json<UpdateRoleRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    let role = service.update_role(&name, request.into_inner()).await?;
+
+    Ok(HttpResponse::Ok().json(role))
+}
+
+// Handler usuwający rolę - role systemowe i role przypisane użytkownikom nie mogą zostać usunięte
+pub async fn delete_role(
+    _auth: Permitted<ManageRoles>,
+    name: web::Path<String>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    service.delete_role(&name).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn list_permissions(_auth: Permitted<ManageRoles>, db_pool: web::Data<PgPool>) -> Result<HttpResponse, AppError> {
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    let permissions = service.list_permissions().await?;
+
+    Ok(HttpResponse::Ok().json(permissions))
+}
+
+pub async fn grant_permission(
+    _auth: Permitted<ManageRoles>,
+    path: web::Path<(String, String)>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (role, permission) = path.into_inner();
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    let role = service.grant_permission(&role, &permission).await?;
+
+    Ok(HttpResponse::Ok().json(role))
+}
+
+pub async fn revoke_permission(
+    _auth: Permitted<ManageRoles>,
+    path: web::Path<(String, String)>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let (role, permission) = path.into_inner();
+    let service = PermissionService::new(db_pool.get_ref().clone());
+    let role = service.revoke_permission(&role, &permission).await?;
+
+    Ok(HttpResponse::Ok().json(role))
+}
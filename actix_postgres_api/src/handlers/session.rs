@@ -0,0 +1,60 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::chat::close_session_connections;
+use crate::middleware::AuthUser;
+use crate::models::{RevokeSessionsQuery, SessionResponse};
+use crate::services::SessionService;
+
+// Handler listujący aktywne sesje (urządzenia) zalogowanego użytkownika
+pub async fn list_sessions(
+    auth: AuthUser,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let service = SessionService::new(db_pool.get_ref().clone());
+
+    let sessions = service.list(auth.id).await?;
+    let response: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, auth.session_id))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Handler wylogowujący jedno urządzenie
+pub async fn revoke_session(
+    auth: AuthUser,
+    id: web::Path<Uuid>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let service = SessionService::new(db_pool.get_ref().clone());
+
+    service.revoke(auth.id, *id).await?;
+    close_session_connections(&[*id]);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler wylogowujący wszystkie urządzenia (z `keep_current=true` - poza bieżącym)
+pub async fn revoke_all_sessions(
+    auth: AuthUser,
+    query: web::Query<RevokeSessionsQuery>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let service = SessionService::new(db_pool.get_ref().clone());
+
+    let except = if query.keep_current { auth.session_id } else { None };
+    let revoked = service.revoke_all(auth.id, except).await?;
+    close_session_connections(&revoked);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": format!("{} session(s) revoked", revoked.len()),
+        "revoked_sessions": revoked,
+    })))
+}
@@ -0,0 +1,56 @@
+use actix_web::{web, HttpResponse};
+use sqlx::postgres::PgPool;
+
+use crate::error::AppError;
+use crate::middleware::{AuthUser, EnrollTwoFactor, Permitted};
+use crate::models::{TwoFactorCodeRequest, TwoFactorRecoveryCodesResponse};
+use crate::services::TwoFactorService;
+
+// Handler rozpoczynający konfigurację 2FA - zwraca sekret i URI do kodu QR
+pub async fn enroll_two_factor(
+    auth: Permitted<EnrollTwoFactor>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let user_id = auth.id;
+    let service = TwoFactorService::new(db_pool.get_ref().clone());
+
+    let response = service.enroll(user_id).await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Handler potwierdzający konfigurację 2FA pierwszym kodem z aplikacji
+pub async fn confirm_two_factor(
+    auth: AuthUser,
+    request: web::Json<TwoFactorCodeRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let user_id = auth.id;
+    let service = TwoFactorService::new(db_pool.get_ref().clone());
+
+    let recovery_codes = service.confirm(user_id, &request.code).await?;
+
+    Ok(HttpResponse::Ok().json(TwoFactorRecoveryCodesResponse {
+        recovery_codes,
+        message: "Two-factor authentication enabled. Store the recovery codes in a safe place".to_string(),
+    }))
+}
+
+// Handler wyłączający 2FA - wymaga aktualnego kodu lub kodu odzyskiwania
+pub async fn disable_two_factor(
+    auth: AuthUser,
+    request: web::Json<TwoFactorCodeRequest>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    auth.require_session()?;
+    let user_id = auth.id;
+    let service = TwoFactorService::new(db_pool.get_ref().clone());
+
+    service.disable(user_id, &request.code).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Two-factor authentication disabled"
+    })))
+}
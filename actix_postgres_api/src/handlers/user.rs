@@ -1,106 +1,162 @@
-use actix_web::{web, HttpResponse, HttpRequest, post, delete};
+use actix_web::{web, HttpResponse, post, delete};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
-use crate::middleware::auth_middleware::Auth;
-use crate::models::role::UserRole;
+use crate::middleware::{entity_tag, AuthUser, ClientInfo, CreateUser, DeleteAnyUser, IfMatch, Permitted, ReadAnyUser};
+use crate::auth_utils::permissions::{USERS_READ, USERS_ROLE_ASSIGN, USERS_WRITE};
 use crate::error::AppError;
+use crate::handlers::chat::close_session_connections;
 use sqlx::postgres::PgPool;
+use uuid::Uuid;
 
-use crate::models::{CreateUserRequest, UpdateUserRequest, UserResponse};
-use crate::services::UserService;
+use crate::models::{CreateUserRequest, RegisterRequest, UpdateUserRequest, User, UserListQuery, UserResponse};
+use crate::mail::MailSender;
+use crate::services::{AuditContext, AuthService, UserService};
 
-pub async fn get_all_users(req: HttpRequest, db_pool: web::Data<PgPool>) -> Result<HttpResponse, AppError> {
-    Auth::validate_request(&req, UserRole::Admin)?;
+// Lista użytkowników z filtrami, sortowaniem, wyszukiwaniem i stronicowaniem
+pub async fn get_all_users(
+    _auth: Permitted<ReadAnyUser>,
+    query: web::Query<UserListQuery>,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
     let service = UserService::new(db_pool.get_ref().clone());
-    let users = service.get_all_users().await?;
-    let response: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
+    let users = service.list_users(query.into_inner()).await?;
     
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok().json(users.map(UserResponse::from)))
 }
 
 pub async fn get_user_by_id(
-    req: HttpRequest,
-    id: web::Path<String>,
+    auth: AuthUser,
+    id: web::Path<Uuid>,
     db_pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
-    // Allow both Admin and the user themselves to access their data
-    let user_id = Auth::extract_user_id(&req)?;
-    let requested_id = id.to_string();
-    
-    // If not the same user, require admin role
-    if user_id != requested_id {
-        Auth::validate_request(&req, UserRole::Admin)?;
-    }
+    // Own data needs users:read:own, data of other users users:read:any
+    auth.require_scoped_user(USERS_READ, *id)?;
     
     let service = UserService::new(db_pool.get_ref().clone());
-    let user = service.get_user_by_id(&id).await?;
+    let user = service.get_user_by_id(&id.to_string()).await?;
     
-    Ok(HttpResponse::Ok().json(UserResponse::from(user)))
+    Ok(HttpResponse::Ok()
+        .insert_header(entity_tag(&user.updated_at))
+        .json(UserResponse::from(user)))
 }
 
+// Zakładanie kont przez administratora - z dowolną rolą, jeśli ma też uprawnienie users:role:assign
 pub async fn create_user(
+    auth: Permitted<CreateUser>,
     user: web::Json<CreateUserRequest>,
+    client: ClientInfo,
     db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn MailSender>,
 ) -> Result<HttpResponse, AppError> {
+    if user.role.is_some() {
+        auth.require_permission(USERS_ROLE_ASSIGN)?;
+    }
+
     let service = UserService::new(db_pool.get_ref().clone());
-    let created_user = service.create_user(user.into_inner(), db_pool.get_ref()).await?;
+    let audit = AuditContext::new(&auth, client);
+    let created_user = service.create_user(user.into_inner(), db_pool.get_ref(), &audit).await?;
+
+    send_verification_email(&created_user, &db_pool, mailer.get_ref()).await;
     
     Ok(HttpResponse::Created().json(UserResponse::from(created_user)))
 }
 
-pub async fn update_user(
-    req: HttpRequest,
-    id: web::Path<String>,
-    user: web::Json<UpdateUserRequest>,
+// Publiczna rejestracja - zawsze tworzy klienta, o ile pozwala na to SIGNUP_POLICY
+pub async fn register(
+    user: web::Json<RegisterRequest>,
+    client: ClientInfo,
     db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn MailSender>,
 ) -> Result<HttpResponse, AppError> {
-    // Allow both Admin and the user themselves to update their data
-    let user_id = Auth::extract_user_id(&req)?;
-    let requested_id = id.to_string();
+    let service = UserService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::anonymous(client);
+    let created_user = service.register(user.into_inner(), db_pool.get_ref(), &audit).await?;
+
+    send_verification_email(&created_user, &db_pool, mailer.get_ref()).await;
     
-    // If not the same user, require admin role
-    if user_id != requested_id {
-        Auth::validate_request(&req, UserRole::Admin)?;
+    Ok(HttpResponse::Created().json(UserResponse::from(created_user)))
+}
+
+async fn send_verification_email(user: &User, db_pool: &PgPool, mailer: &dyn MailSender) {
+    // Konto zostało już utworzone - błąd wysyłki nie powinien go wycofywać, użytkownik może poprosić o ponowne wysłanie linku
+    let auth_service = AuthService::new(db_pool.clone());
+    if let Err(e) = auth_service.send_verification_email(user, mailer).await {
+        tracing::error!("Failed to send verification email to user {}: {}", user.id, e);
     }
+}
+
+pub async fn update_user(
+    auth: AuthUser,
+    id: web::Path<Uuid>,
+    user: web::Json<UpdateUserRequest>,
+    client: ClientInfo,
+    if_match: IfMatch,
+    db_pool: web::Data<PgPool>,
+    mailer: web::Data<dyn MailSender>,
+) -> Result<HttpResponse, AppError> {
+    // Own data needs users:write:own, data of other users users:write:any
+    auth.require_scoped_user(USERS_WRITE, *id)?;
     
-    // If trying to change role, require admin privileges
+    // Changing a role (also one's own) is a separate permission
     if user.role.is_some() {
-        Auth::validate_request(&req, UserRole::Admin)?;
+        auth.require_permission(USERS_ROLE_ASSIGN)?;
     }
     
     let service = UserService::new(db_pool.get_ref().clone());
-    let updated_user = service.update_user(&id, user.into_inner(), db_pool.get_ref()).await?;
+    let audit = AuditContext::new(&auth, client);
+    let email_requested = user.email.is_some();
+    let (updated_user, revoked_sessions) = service.update_user(&id.to_string(), user.into_inner(), db_pool.get_ref(), &audit, &if_match).await?;
+    close_session_connections(&revoked_sessions);
+
+    // Zmiana adresu zeruje jego weryfikację - nowy adres dostaje świeży link
+    if email_requested && updated_user.email_verified_at.is_none() {
+        send_verification_email(&updated_user, &db_pool, mailer.get_ref()).await;
+    }
     
-    Ok(HttpResponse::Ok().json(UserResponse::from(updated_user)))
+    Ok(HttpResponse::Ok()
+        .insert_header(entity_tag(&updated_user.updated_at))
+        .json(UserResponse::from(updated_user)))
 }
 
 pub async fn delete_user(
-    req: HttpRequest,
-    id: web::Path<String>,
+    auth: Permitted<DeleteAnyUser>,
+    id: web::Path<Uuid>,
+    client: ClientInfo,
+    if_match: IfMatch,
     db_pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
-    // Only Admin can delete users
-    Auth::validate_request(&req, UserRole::Admin)?;
-    
     let service = UserService::new(db_pool.get_ref().clone());
-    service.delete_user(&id).await?;
+    let audit = AuditContext::new(&auth, client);
+    let revoked_sessions = service.delete_user(&id.to_string(), &audit, &if_match).await?;
+    close_session_connections(&revoked_sessions);
     
     Ok(HttpResponse::NoContent().finish())
 }
 
+// Handler przywracający konto usunięte miękko
+pub async fn restore_user(
+    auth: Permitted<DeleteAnyUser>,
+    id: web::Path<Uuid>,
+    client: ClientInfo,
+    db_pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let service = UserService::new(db_pool.get_ref().clone());
+    let audit = AuditContext::new(&auth, client);
+    let restored_user = service.restore_user(*id, &audit).await?;
+    
+    Ok(HttpResponse::Ok().json(UserResponse::from(restored_user)))
+}
+
 // Handler do filtrowania użytkowników wg roli
 pub async fn get_users_by_role(
-    req: HttpRequest,
+    _auth: Permitted<ReadAnyUser>,
     role: web::Path<String>,
+    query: web::Query<UserListQuery>,
     db_pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AppError> {
-    // Only Admin can list users by role
-    Auth::validate_request(&req, UserRole::Admin)?;
-    
     let service = UserService::new(db_pool.get_ref().clone());
-    let users = service.get_users_by_role(&role).await?;
-    
-    let response: Vec<UserResponse> = 
-        users.into_iter().map(UserResponse::from).collect();
+    let mut query = query.into_inner();
+    query.role = Some(role.into_inner());
+    let users = service.list_users(query).await?;
     
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok().json(users.map(UserResponse::from)))
 }
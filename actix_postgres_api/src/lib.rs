@@ -8,3 +8,4 @@ pub mod monitoring;  // New monitoring module
 pub mod logging;     // New logging module
 pub mod middleware;  // New middleware module
 pub mod services;    // New services module
+pub mod mail;        // Outgoing mail module
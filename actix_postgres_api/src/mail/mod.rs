@@ -0,0 +1,95 @@
+use futures::future::{self, BoxFuture};
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use crate::error::AppError;
+
+// Mail configuration
+#[derive(Debug, Clone)]
+pub struct MailConfig {
+    pub from_address: String,
+    pub app_url: String, // Base URL of the frontend used to build links in emails
+}
+
+impl MailConfig {
+    pub fn from_env() -> Self {
+        Self {
+            from_address: env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@localhost".to_string()),
+            app_url: env::var("APP_URL").unwrap_or_else(|_| "http://localhost:1420".to_string()),
+        }
+    }
+
+    // Build an absolute frontend link carrying a one-time token
+    pub fn link(&self, path: &str, token: &str) -> String {
+        format!("{}/{}?token={}", self.app_url.trim_end_matches('/'), path.trim_start_matches('/'), token)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Pluggable outgoing mail transport
+///
+/// Handlers receive the sender as `web::Data<dyn MailSender>`, so the production
+/// transport and the in-memory one used by tests are interchangeable.
+pub trait MailSender: Send + Sync {
+    fn send(&self, message: MailMessage) -> BoxFuture<'_, Result<(), AppError>>;
+}
+
+/// Default sender which only writes messages to the log
+pub struct LogMailSender {
+    config: MailConfig,
+}
+
+impl LogMailSender {
+    pub fn new(config: MailConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl MailSender for LogMailSender {
+    fn send(&self, message: MailMessage) -> BoxFuture<'_, Result<(), AppError>> {
+        tracing::info!(
+            from = %self.config.from_address,
+            to = %message.to,
+            subject = %message.subject,
+            "Outgoing email:\n{}",
+            message.body
+        );
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+/// Sender which keeps every message in memory so tests can inspect them
+#[derive(Default, Clone)]
+pub struct InMemoryMailSender {
+    messages: Arc<Mutex<Vec<MailMessage>>>,
+}
+
+// Used by the integration tests
+#[allow(dead_code)]
+impl InMemoryMailSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn messages(&self) -> Vec<MailMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    // Most recent message sent to the given address
+    pub fn last_message_to(&self, to: &str) -> Option<MailMessage> {
+        self.messages.lock().unwrap().iter().rev().find(|m| m.to == to).cloned()
+    }
+}
+
+impl MailSender for InMemoryMailSender {
+    fn send(&self, message: MailMessage) -> BoxFuture<'_, Result<(), AppError>> {
+        self.messages.lock().unwrap().push(message);
+        Box::pin(future::ready(Ok(())))
+    }
+}
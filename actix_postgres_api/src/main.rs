@@ -8,6 +8,7 @@ mod monitoring;  // New monitoring module
 mod logging;     // New logging module
 mod middleware;  // New middleware module
 mod services;    // New services module
+mod mail;        // Outgoing mail module
 
 use actix_web::{middleware::Logger, web, App, HttpServer, HttpResponse};
 use actix_cors::Cors;
@@ -24,15 +25,20 @@ use std::io::BufReader;
 
 
 use crate::config::Config;
-use crate::handlers::{create_user, delete_user, get_all_users, get_user_by_id, update_user, login, get_users_by_role, get_user_statistics, oauth_login, oauth_callback, ws_connect, get_chat_rooms, get_room_messages, create_chat_room};
+use crate::handlers::{create_user, register, delete_user, get_all_users, get_user_by_id, update_user, restore_user, login, login_two_factor, enroll_two_factor, confirm_two_factor, disable_two_factor, jwks, refresh_token, logout, forgot_password, reset_password, change_password, verify_email, resend_verification, get_users_by_role, get_user_statistics, oauth_login, oauth_callback, list_identities, link_identity, unlink_identity, ws_connect, get_chat_rooms, get_room_messages, create_chat_room, list_roles, create_role, update_role, delete_role, list_permissions, grant_permission, revoke_permission, create_invitation, list_invitations, revoke_invitation, accept_invitation, issue_api_key, list_api_keys, revoke_api_key, list_sessions, revoke_session, revoke_all_sessions, impersonate_user, list_impersonation_log, list_audit_events, export_user_data, request_erasure, cancel_erasure};
 // These imports are kept for potential future use
 #[allow(unused_imports)]
 use crate::database::user::UserRepository;
 #[allow(unused_imports)]
 use crate::error::AppError;
 use crate::logging::init_logging;
-use crate::middleware::{CustomRootSpanBuilder, PerformanceMetrics, cors_middleware};
+use crate::middleware::{CustomRootSpanBuilder, ImpersonationAudit, PerformanceMetrics, cors_middleware};
 use crate::monitoring::update_memory_usage;
+use crate::mail::{LogMailSender, MailConfig, MailSender};
+use crate::auth_utils::account::AccountConfig;
+use crate::handlers::chat::close_session_connections;
+use crate::services::{PrivacyService, UserService};
+use std::sync::Arc;
 
 
 
@@ -125,6 +131,14 @@ async fn main() -> std::io::Result<()> {
     let config = Config::from_env().expect("Failed to load configuration");
     tracing::info!("Configuration loaded: {:?}", config);
     
+    // Refuse to start with an insecure or broken JWT setup
+    auth_utils::jwt::validate_jwt_configuration(config.is_development())
+        .expect("Invalid JWT configuration");
+    auth_utils::account::validate_account_configuration()
+        .expect("Invalid account configuration");
+    auth_utils::validate_password_hash_configuration()
+        .expect("Invalid password hashing configuration");
+    
     // Create database connection pool using our new database module
     let db_pool = database::connection::DatabasePool::new(&config)
         .await
@@ -132,6 +146,9 @@ async fn main() -> std::io::Result<()> {
     
     let pool = db_pool.get_pool();
     
+    // Outgoing mail transport shared by all workers
+    let mailer: Arc<dyn MailSender> = Arc::new(LogMailSender::new(MailConfig::from_env()));
+    
     // Setup Prometheus metrics
     let prometheus = PrometheusMetricsBuilder::new("api")
         .endpoint("/metrics")
@@ -149,10 +166,40 @@ async fn main() -> std::io::Result<()> {
         }
     });
     
+    // Anonymize accounts whose erasure grace period has ended
+    let erasure_pool = pool.clone();
+    let erasure_interval = AccountConfig::from_env().erasure_check_interval;
+    task::spawn(async move {
+        let service = PrivacyService::new(erasure_pool);
+        let mut interval = time::interval(Duration::from_secs(erasure_interval.max(1)));
+        loop {
+            interval.tick().await;
+            match service.erase_due().await {
+                Ok(revoked_sessions) => close_session_connections(&revoked_sessions),
+                Err(e) => tracing::error!("Failed to process due erasure requests: {}", e),
+            }
+        }
+    });
+    
+    // Permanently remove accounts soft-deleted longer than the retention period
+    let purge_pool = pool.clone();
+    let purge_interval = AccountConfig::from_env().purge_check_interval;
+    task::spawn(async move {
+        let service = UserService::new(purge_pool);
+        let mut interval = time::interval(Duration::from_secs(purge_interval.max(1)));
+        loop {
+            interval.tick().await;
+            if let Err(e) = service.purge_deleted_users().await {
+                tracing::error!("Failed to purge deleted users: {}", e);
+            }
+        }
+    });
+    
     // Create the HTTP server
     let mut server = HttpServer::new(move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::from(mailer.clone()))
             // Add Prometheus metrics
             .wrap(prometheus.clone())
             // Add CORS middleware
@@ -163,8 +210,12 @@ async fn main() -> std::io::Result<()> {
             .wrap(PerformanceMetrics)
             // Add standard logger as a fallback
             .wrap(Logger::default())
+            // Audit requests made with impersonation tokens
+            .wrap(ImpersonationAudit)
             // Health check endpoint
             .route("/health", web::get().to(health_check))
+            // Public keys for verifying access tokens in other services
+            .route("/.well-known/jwks.json", web::get().to(jwks))
             // API routes
             .service(
                 web::scope("/api")
@@ -176,13 +227,36 @@ async fn main() -> std::io::Result<()> {
                             .route("/statistics", web::get().to(get_user_statistics))
                             .route("/{id}", web::get().to(get_user_by_id))
                             .route("/{id}", web::put().to(update_user))
+                            .route("/{id}", web::patch().to(update_user))
                             .route("/{id}", web::delete().to(delete_user))
+                            .route("/{id}/export", web::get().to(export_user_data))
+                            .route("/{id}/erasure", web::post().to(request_erasure))
+                            .route("/{id}/erasure", web::delete().to(cancel_erasure))
                     )
                     .service(
                         web::scope("/auth")
+                            .route("/register", web::post().to(register))
                             .route("/login", web::post().to(login))
-                            .route("/oauth/{provider}", web::get().to(oauth_login))
+                            .route("/login/2fa", web::post().to(login_two_factor))
+                            .route("/refresh", web::post().to(refresh_token))
+                            .route("/logout", web::post().to(logout))
+                            .route("/sessions", web::get().to(list_sessions))
+                            .route("/sessions", web::delete().to(revoke_all_sessions))
+                            .route("/sessions/{id}", web::delete().to(revoke_session))
+                            .route("/forgot-password", web::post().to(forgot_password))
+                            .route("/reset-password", web::post().to(reset_password))
+                            .route("/change-password", web::post().to(change_password))
+                            .route("/verify-email", web::post().to(verify_email))
+                            .route("/verify-email/resend", web::post().to(resend_verification))
+                            .route("/2fa/enroll", web::post().to(enroll_two_factor))
+                            .route("/2fa/confirm", web::post().to(confirm_two_factor))
+                            .route("/2fa/disable", web::post().to(disable_two_factor))
+                            // The static callback path must be registered before the `{provider}` pattern
                             .route("/oauth/callback", web::get().to(oauth_callback))
+                            .route("/oauth/{provider}", web::get().to(oauth_login))
+                            .route("/identities", web::get().to(list_identities))
+                            .route("/identities/{provider}", web::post().to(link_identity))
+                            .route("/identities/{provider}", web::delete().to(unlink_identity))
                     )
                     .service(
                         web::scope("/chat")
@@ -191,6 +265,30 @@ async fn main() -> std::io::Result<()> {
                             .route("/rooms", web::post().to(create_chat_room))
                             .route("/rooms/{room_id}/messages", web::get().to(get_room_messages))
                     )
+                    .service(
+                        web::scope("/invitations")
+                            .route("", web::get().to(list_invitations))
+                            .route("", web::post().to(create_invitation))
+                            .route("/accept", web::post().to(accept_invitation))
+                            .route("/{id}", web::delete().to(revoke_invitation))
+                    )
+                    .service(
+                        web::scope("/admin")
+                            .route("/roles", web::get().to(list_roles))
+                            .route("/roles", web::post().to(create_role))
+                            .route("/roles/{name}", web::put().to(update_role))
+                            .route("/roles/{name}", web::delete().to(delete_role))
+                            .route("/roles/{name}/permissions/{permission}", web::put().to(grant_permission))
+                            .route("/roles/{name}/permissions/{permission}", web::delete().to(revoke_permission))
+                            .route("/permissions", web::get().to(list_permissions))
+                            .route("/api-keys", web::get().to(list_api_keys))
+                            .route("/api-keys", web::post().to(issue_api_key))
+                            .route("/api-keys/{id}", web::delete().to(revoke_api_key))
+                            .route("/users/{id}/impersonate", web::post().to(impersonate_user))
+                            .route("/users/{id}/restore", web::post().to(restore_user))
+                            .route("/impersonation-log", web::get().to(list_impersonation_log))
+                            .route("/audit-events", web::get().to(list_audit_events))
+                    )
                     // Configure appointment routes
                     .configure(handlers::configure_appointment_routes)
             )
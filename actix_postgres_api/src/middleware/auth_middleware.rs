@@ -1,79 +1,290 @@
-use actix_web::{dev::ServiceRequest, Error, HttpMessage, HttpRequest};
-use actix_web::error::ErrorUnauthorized;
-use actix_web_httpauth::extractors::bearer::BearerAuth;
-use futures::future::{ok, Ready};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+use actix_web::{dev::Payload, http::{header, Method}, web, FromRequest, HttpMessage, HttpRequest};
+use futures::future::LocalBoxFuture;
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
 
 use crate::auth_utils::jwt::{verify_token, extract_token_from_header};
+use crate::auth_utils::impersonation::{is_mutating, ImpersonationConfig};
+use crate::auth_utils::permissions::{self, is_granted};
+use crate::database::user::UserRepository;
 use crate::error::AppError;
-use crate::models::role::UserRole;
-
-pub struct Auth;
-
-impl Auth {
-    // Validate request based on JWT token and required role
-    pub fn validate_request(req: &HttpRequest, required_role: UserRole) -> Result<String, AppError> {
-        // Extract the Authorization header
-        let auth_header = req.headers().get("Authorization")
-            .ok_or_else(|| AppError::ValidationError("Missing authorization header".to_string()))?;
-        
-        // Convert header to string
-        let auth_str = auth_header.to_str()
-            .map_err(|_| AppError::ValidationError("Invalid authorization header format".to_string()))?;
-        
-        // Extract token from header
-        let token = extract_token_from_header(auth_str)?;
-        
-        // Verify token
-        let claims = verify_token(token)?;
-        
-        // Check if user has required role
-        let user_role = UserRole::from(claims.role.as_str());
-        
-        if user_role != required_role {
-            return Err(AppError::Forbidden("Insufficient permissions".to_string()));
+use crate::services::{ApiKeyService, PermissionService, SessionService};
+
+// Header carrying an API key, accepted instead of `Authorization: Bearer <JWT>`
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+// Zalogowany użytkownik z tokenu dostępu (albo właściciel klucza API) wraz z efektywnymi
+// uprawnieniami. Token jest weryfikowany raz na żądanie - wynik trafia do rozszerzeń żądania,
+// więc kolejne ekstraktory w tym samym handlerze korzystają z niego ponownie.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub session_id: Option<Uuid>,  // Login session of the access token (None for API keys)
+    pub api_key_id: Option<Uuid>,  // Set when the request was authenticated with an API key
+    pub impersonator_id: Option<Uuid>,  // Admin acting as this user with an impersonation token
+    permissions: Arc<HashSet<String>>,
+}
+
+impl AuthUser {
+    // Account security settings (2FA, linked accounts, sessions) can only be changed by the user themselves;
+    // returns the id of the login session
+    pub fn require_session(&self) -> Result<Uuid, AppError> {
+        if self.api_key_id.is_some() {
+            return Err(AppError::Forbidden("This operation is not available with an API key".to_string()));
+        }
+
+        if self.impersonator_id.is_some() {
+            return Err(AppError::Forbidden("This operation is not available while impersonating".to_string()));
         }
-        
-        // Return user ID if validation successful
-        Ok(claims.sub)
+
+        self.session_id
+            .ok_or_else(|| AppError::Forbidden("This operation requires a login session".to_string()))
     }
-    
-    // Helper method to extract user ID from token without role check
-    pub fn extract_user_id(req: &HttpRequest) -> Result<String, AppError> {
-        // Extract the Authorization header
-        let auth_header = req.headers().get("Authorization")
-            .ok_or_else(|| AppError::ValidationError("Missing authorization header".to_string()))?;
-        
-        // Convert header to string
-        let auth_str = auth_header.to_str()
-            .map_err(|_| AppError::ValidationError("Invalid authorization header format".to_string()))?;
-        
-        // Extract token from header
-        let token = extract_token_from_header(auth_str)?;
-        
-        // Verify token
-        let claims = verify_token(token)?;
-        
-        // Return user ID
-        Ok(claims.sub)
+
+    // Mutating requests with an impersonation token are refused unless IMPERSONATION_ALLOW_WRITES is set
+    fn ensure_impersonation_allows(&self, method: &Method) -> Result<(), AppError> {
+        if self.impersonator_id.is_some() && is_mutating(method) && !ImpersonationConfig::from_env().allow_writes {
+            return Err(AppError::Forbidden("Impersonation tokens are read-only".to_string()));
+        }
+
+        Ok(())
     }
-    
-    // Helper method to extract user role from token
-    pub fn extract_role(req: &HttpRequest) -> Result<UserRole, AppError> {
-        // Extract the Authorization header
-        let auth_header = req.headers().get("Authorization")
-            .ok_or_else(|| AppError::ValidationError("Missing authorization header".to_string()))?;
-        
-        // Convert header to string
-        let auth_str = auth_header.to_str()
-            .map_err(|_| AppError::ValidationError("Invalid authorization header format".to_string()))?;
-        
-        // Extract token from header
-        let token = extract_token_from_header(auth_str)?;
-        
-        // Verify token
-        let claims = verify_token(token)?;
-        
-        // Convert role string to UserRole enum and return
-        Ok(UserRole::from(claims.role.as_str()))
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        is_granted(&self.permissions, permission)
+    }
+
+    pub fn require_permission(&self, permission: &str) -> Result<(), AppError> {
+        if self.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden("Insufficient permissions".to_string()))
+        }
+    }
+
+    // Scoped action (e.g. `appointments:write`): the owner needs `:own`, everybody else `:any`
+    pub fn can(&self, action: &str, is_owner: bool) -> bool {
+        (is_owner && self.has_permission(&format!("{}:own", action)))
+            || self.has_permission(&format!("{}:any", action))
+    }
+
+    pub fn require_scoped(&self, action: &str, is_owner: bool) -> Result<(), AppError> {
+        if self.can(action, is_owner) {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden("Insufficient permissions".to_string()))
+        }
+    }
+
+    // Shorthand for actions on user accounts, where the owner is the user themselves
+    pub fn require_scoped_user(&self, action: &str, user_id: Uuid) -> Result<(), AppError> {
+        self.require_scoped(action, self.id == user_id)
+    }
+
+    async fn from_http_request(req: HttpRequest) -> Result<Self, AppError> {
+        let cached = req.extensions().get::<AuthUser>().cloned();
+        if let Some(user) = cached {
+            user.ensure_impersonation_allows(req.method())?;
+            return Ok(user);
+        }
+
+        let pool = req.app_data::<web::Data<PgPool>>()
+            .ok_or_else(|| AppError::InternalServerError("Database pool is not configured".to_string()))?
+            .get_ref()
+            .clone();
+
+        let user = match req.headers().get(API_KEY_HEADER) {
+            Some(api_key) => {
+                let api_key = api_key.to_str()
+                    .map_err(|_| AppError::Unauthorized("Invalid API key".to_string()))?;
+                Self::from_api_key(pool, api_key).await?
+            }
+            None => Self::from_bearer_token(&req, pool).await?,
+        };
+
+        // Stored before the impersonation check, so refused requests are audited as well
+        req.extensions_mut().insert(user.clone());
+        user.ensure_impersonation_allows(req.method())?;
+        Ok(user)
+    }
+
+    async fn from_bearer_token(req: &HttpRequest, pool: PgPool) -> Result<Self, AppError> {
+        let auth_str = req.headers().get(header::AUTHORIZATION)
+            .ok_or_else(|| AppError::Unauthorized("Missing authorization header".to_string()))?
+            .to_str()
+            .map_err(|_| AppError::Unauthorized("Invalid authorization header format".to_string()))?;
+
+        let token = extract_token_from_header(auth_str)
+            .map_err(|_| AppError::Unauthorized("Invalid authorization header format".to_string()))?;
+
+        let claims = verify_token(token).map_err(|e| match e {
+            AppError::ValidationError(message) => AppError::Unauthorized(message),
+            other => other,
+        })?;
+
+        let id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::Unauthorized("Invalid token".to_string()))?;
+        let session_id = Uuid::parse_str(&claims.sid)
+            .map_err(|_| AppError::Unauthorized("Invalid token".to_string()))?;
+        let impersonator_id = claims.act
+            .map(|actor| Uuid::parse_str(&actor.sub))
+            .transpose()
+            .map_err(|_| AppError::Unauthorized("Invalid token".to_string()))?;
+
+        // Tokeny wylogowanych urządzeń są odrzucane, zanim wygasną.
+        // Token impersonacji należy do sesji administratora.
+        if !SessionService::new(pool.clone()).touch(session_id, impersonator_id.unwrap_or(id)).await? {
+            return Err(AppError::Unauthorized("Session has been revoked".to_string()));
+        }
+
+        // Rola z bazy, a nie z tokenu - zmiana roli działa od razu, także dla wydanych już tokenów
+        let user = UserRepository::new(pool.clone())
+            .find_by_id(id)
+            .await
+            .map_err(|e| match e {
+                AppError::NotFoundError(_) => AppError::Unauthorized("Invalid token".to_string()),
+                other => other,
+            })?;
+
+        let permissions = PermissionService::new(pool)
+            .permissions_for_role(&user.role)
+            .await?;
+
+        Ok(AuthUser {
+            id,
+            session_id: Some(session_id),
+            api_key_id: None,
+            impersonator_id,
+            permissions,
+        })
+    }
+
+    // Klucz API działa w imieniu właściciela, ale tylko w zakresie swoich uprawnień (scopes),
+    // które rola właściciela nadal przyznaje
+    async fn from_api_key(pool: PgPool, api_key: &str) -> Result<Self, AppError> {
+        let (api_key, owner) = ApiKeyService::new(pool.clone())
+            .authenticate(api_key)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("Invalid API key".to_string()))?;
+
+        let role_permissions = PermissionService::new(pool)
+            .permissions_for_role(&owner.role)
+            .await?;
+
+        let permissions = api_key.scopes
+            .into_iter()
+            .filter(|scope| is_granted(&role_permissions, scope))
+            .collect();
+
+        Ok(AuthUser {
+            id: owner.id,
+            session_id: None,
+            api_key_id: Some(api_key.id),
+            impersonator_id: None,
+            permissions: Arc::new(permissions),
+        })
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        Box::pin(AuthUser::from_http_request(req.clone()))
+    }
+}
+
+// Permission required by a `Permitted` extractor
+pub trait RequiredPermission {
+    const NAME: &'static str;
+}
+
+pub struct ReadAnyUser;
+pub struct CreateUser;
+pub struct DeleteAnyUser;
+pub struct ReadAnyAppointment;
+pub struct CreateAppointment;
+pub struct EnrollTwoFactor;
+pub struct ManageRoles;
+pub struct CreateInvitation;
+pub struct ManageApiKeys;
+pub struct ImpersonateUsers;
+pub struct ReadAuditLog;
+
+impl RequiredPermission for ReadAnyUser {
+    const NAME: &'static str = permissions::USERS_READ_ANY;
+}
+
+impl RequiredPermission for CreateUser {
+    const NAME: &'static str = permissions::USERS_CREATE;
+}
+
+impl RequiredPermission for DeleteAnyUser {
+    const NAME: &'static str = permissions::USERS_DELETE_ANY;
+}
+
+impl RequiredPermission for ReadAnyAppointment {
+    const NAME: &'static str = permissions::APPOINTMENTS_READ_ANY;
+}
+
+impl RequiredPermission for CreateAppointment {
+    const NAME: &'static str = permissions::APPOINTMENTS_CREATE;
+}
+
+impl RequiredPermission for EnrollTwoFactor {
+    const NAME: &'static str = permissions::TWO_FACTOR_ENROLL;
+}
+
+impl RequiredPermission for CreateInvitation {
+    const NAME: &'static str = permissions::INVITATIONS_CREATE;
+}
+
+impl RequiredPermission for ManageApiKeys {
+    const NAME: &'static str = permissions::API_KEYS_MANAGE;
+}
+
+impl RequiredPermission for ImpersonateUsers {
+    const NAME: &'static str = permissions::USERS_IMPERSONATE;
+}
+
+impl RequiredPermission for ReadAuditLog {
+    const NAME: &'static str = permissions::AUDIT_READ;
+}
+
+impl RequiredPermission for ManageRoles {
+    const NAME: &'static str = permissions::ROLES_MANAGE;
+}
+
+// Zalogowany użytkownik z określonym uprawnieniem - brak uprawnienia kończy się 403 przed wejściem do handlera
+pub struct Permitted<P: RequiredPermission> {
+    user: AuthUser,
+    _permission: PhantomData<P>,
+}
+
+impl<P: RequiredPermission> Deref for Permitted<P> {
+    type Target = AuthUser;
+
+    fn deref(&self) -> &AuthUser {
+        &self.user
+    }
+}
+
+impl<P: RequiredPermission> FromRequest for Permitted<P> {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let user = AuthUser::from_http_request(req).await?;
+            user.require_permission(P::NAME)?;
+
+            Ok(Permitted { user, _permission: PhantomData })
+        })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,57 @@
+use actix_web::{dev::Payload, http::header, FromRequest, HttpMessage, HttpRequest};
+use futures::future::{ok, Ready};
+use std::env;
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+// Dane klienta wykonującego żądanie (adres IP i User-Agent) - do limitów logowania i audytu
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<Uuid>,  // Set by the tracing root span (CustomRootSpanBuilder)
+}
+
+impl ClientInfo {
+    // X-Forwarded-For / Forwarded can be set by anyone, so they are honoured only behind a trusted proxy
+    fn trust_proxy_headers() -> bool {
+        env::var("TRUST_PROXY_HEADERS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false)
+    }
+
+    pub fn from_http_request(req: &HttpRequest) -> Self {
+        let ip = if Self::trust_proxy_headers() {
+            req.connection_info().realip_remote_addr().map(|addr| addr.to_string())
+        } else {
+            req.peer_addr().map(|addr| addr.ip().to_string())
+        };
+
+        // realip_remote_addr may still contain the port
+        let ip = ip.map(|addr| match addr.parse::<std::net::SocketAddr>() {
+            Ok(socket) => socket.ip().to_string(),
+            Err(_) => addr,
+        });
+
+        let user_agent = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+
+        let request_id = req.extensions().get::<Uuid>().copied();
+
+        Self { ip, user_agent, request_id }
+    }
+}
+
+impl FromRequest for ClientInfo {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ok(ClientInfo::from_http_request(req))
+    }
+}
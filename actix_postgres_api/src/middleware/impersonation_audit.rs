@@ -0,0 +1,92 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage,
+};
+use futures::future::{ready, Ready, LocalBoxFuture};
+use sqlx::postgres::PgPool;
+use std::rc::Rc;
+
+use crate::middleware::{AuthUser, ClientInfo};
+use crate::services::ImpersonationService;
+
+// Zapisuje w dzienniku impersonacji każde żądanie wykonane tokenem impersonacji (wraz z odrzuconymi).
+// Użytkownika z tokenem zostawia w rozszerzeniach żądania ekstraktor AuthUser.
+pub struct ImpersonationAudit;
+
+impl<S, B> Transform<S, ServiceRequest> for ImpersonationAudit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ImpersonationAuditMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ImpersonationAuditMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct ImpersonationAuditMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for ImpersonationAuditMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let res = service.call(req).await?;
+
+            let request = res.request();
+            let user = request.extensions().get::<AuthUser>().cloned();
+            let Some((user, actor_id)) = user.and_then(|user| user.impersonator_id.map(|actor_id| (user, actor_id))) else {
+                return Ok(res);
+            };
+
+            let Some(pool) = request.app_data::<web::Data<PgPool>>() else {
+                tracing::error!("Impersonated request to {} could not be audited: no database pool", request.path());
+                return Ok(res);
+            };
+
+            let result = ImpersonationService::new(pool.get_ref().clone())
+                .record_request(
+                    actor_id,
+                    user.id,
+                    request.method().as_str(),
+                    request.path(),
+                    res.status().as_u16(),
+                    &ClientInfo::from_http_request(request),
+                )
+                .await;
+
+            if let Err(e) = result {
+                tracing::error!(
+                    target: "security",
+                    event = "impersonation_audit_failed",
+                    actor_id = %actor_id,
+                    user_id = %user.id,
+                    "Failed to audit impersonated request: {}", e
+                );
+            }
+
+            Ok(res)
+        })
+    }
+}
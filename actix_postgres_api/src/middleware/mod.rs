@@ -3,7 +3,17 @@ pub mod performance_metrics;
 pub mod tracing;
 pub mod auth_middleware;
 pub mod cors;
+pub mod client_info;
+pub mod impersonation_audit;
+pub mod precondition;
 // Re-export middleware components for easier imports
 pub use performance_metrics::PerformanceMetrics;
 pub use tracing::CustomRootSpanBuilder;
 pub use cors::cors_middleware;
+pub use client_info::ClientInfo;
+pub use precondition::{entity_tag, IfMatch};
+pub use impersonation_audit::ImpersonationAudit;
+pub use auth_middleware::{
+    AuthUser, Permitted, CreateAppointment, CreateInvitation, CreateUser, ManageApiKeys, DeleteAnyUser, EnrollTwoFactor, ImpersonateUsers, ManageRoles,
+    ReadAnyAppointment, ReadAnyUser, ReadAuditLog,
+};
@@ -0,0 +1,58 @@
+use actix_web::{dev::Payload, http::header::{self, EntityTag, Header}, FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
+use futures::future::{ready, Ready};
+
+use crate::error::AppError;
+
+// ETag zasobu to jego `updated_at` w mikrosekundach - zmienia się przy każdym zapisie
+pub fn entity_tag(updated_at: &DateTime<Utc>) -> header::ETag {
+    header::ETag(EntityTag::new_strong(updated_at.timestamp_micros().to_string()))
+}
+
+// Warunek `If-Match` żądania modyfikującego zasób (optymistyczna kontrola współbieżności)
+#[derive(Debug, Clone, Default)]
+pub struct IfMatch {
+    versions: Option<Vec<DateTime<Utc>>>,  // None without the header or for `If-Match: *`
+}
+
+impl IfMatch {
+    pub fn from_http_request(req: &HttpRequest) -> Result<Self, AppError> {
+        if !req.headers().contains_key(header::IF_MATCH) {
+            return Ok(Self::default());
+        }
+
+        let versions = match header::IfMatch::parse(req)
+            .map_err(|_| AppError::BadRequest("Invalid If-Match header".to_string()))?
+        {
+            header::IfMatch::Any => None,
+            // Weak or foreign tags never match, so the request ends with 412
+            header::IfMatch::Items(tags) => Some(
+                tags.iter()
+                    .filter(|tag| !tag.weak)
+                    .filter_map(|tag| tag.tag().parse().ok())
+                    .filter_map(DateTime::from_timestamp_micros)
+                    .collect(),
+            ),
+        };
+
+        Ok(Self { versions })
+    }
+
+    // Wersje (`updated_at`), na które klient zgadza się nadpisać zasób; None to dowolna
+    pub fn versions(&self) -> Option<&[DateTime<Utc>]> {
+        self.versions.as_deref()
+    }
+
+    pub fn matches(&self, updated_at: &DateTime<Utc>) -> bool {
+        self.versions().is_none_or(|versions| versions.contains(updated_at))
+    }
+}
+
+impl FromRequest for IfMatch {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(IfMatch::from_http_request(req))
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+
+// Klucz API integracji działającej w imieniu użytkownika (np. kiosku lub skryptu raportowego)
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,   // First characters of the key, to recognise it in listings
+    pub key_hash: String,     // SHA-256 of the key (never exposed)
+    pub scopes: Vec<String>,  // Permissions the key may use
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub user_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,  // Optional - keys without expiry are valid until revoked
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(key: ApiKey) -> Self {
+        Self {
+            id: key.id,
+            user_id: key.user_id,
+            name: key.name,
+            key_prefix: key.key_prefix,
+            scopes: key.scopes,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+            created_by: key.created_by,
+            created_at: key.created_at,
+        }
+    }
+}
+
+// Pełny klucz zwracany tylko raz, przy wystawieniu
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IssuedApiKeyResponse {
+    pub key: String,
+    pub api_key: ApiKeyResponse,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyQuery {
+    pub user_id: Option<Uuid>,
+}
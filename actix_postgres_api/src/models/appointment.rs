@@ -7,6 +7,7 @@ pub struct Appointment {
     pub id: Uuid,
     pub client_id: Uuid,
     pub trainer_id: Uuid,
+    #[sqlx(rename = "type")]
     pub type_: String,
     pub appointment_date: NaiveDate,
     pub start_time: NaiveTime,
@@ -24,6 +25,7 @@ pub struct AppointmentWithNames {
     pub trainer_id: Uuid,
     pub client_name: String,
     pub trainer_name: String,
+    #[sqlx(rename = "type")]
     pub type_: String,
     pub appointment_date: NaiveDate,
     pub start_time: NaiveTime,
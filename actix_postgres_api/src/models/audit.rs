@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+
+// Nazwy akcji zapisywanych w audit_events
+pub const AUTH_LOGIN: &str = "auth.login";
+pub const AUTH_LOGIN_FAILED: &str = "auth.login_failed";
+pub const AUTH_LOGOUT: &str = "auth.logout";
+pub const AUTH_PASSWORD_RESET: &str = "auth.password_reset";
+pub const AUTH_PASSWORD_CHANGED: &str = "auth.password_changed";
+pub const USER_CREATED: &str = "user.created";
+pub const USER_REGISTERED: &str = "user.registered";
+pub const USER_UPDATED: &str = "user.updated";
+pub const USER_ROLE_CHANGED: &str = "user.role_changed";
+pub const USER_DELETED: &str = "user.deleted";
+pub const USER_RESTORED: &str = "user.restored";
+pub const USER_PURGED: &str = "user.purged";
+pub const USER_DATA_EXPORTED: &str = "user.data_exported";
+pub const USER_ERASURE_REQUESTED: &str = "user.erasure_requested";
+pub const USER_ERASURE_CANCELLED: &str = "user.erasure_cancelled";
+pub const USER_ERASED: &str = "user.erased";
+pub const APPOINTMENT_CREATED: &str = "appointment.created";
+pub const APPOINTMENT_UPDATED: &str = "appointment.updated";
+pub const APPOINTMENT_STATUS_CHANGED: &str = "appointment.status_changed";
+pub const APPOINTMENT_DELETED: &str = "appointment.deleted";
+
+// Target types
+pub const TARGET_USER: &str = "user";
+pub const TARGET_APPOINTMENT: &str = "appointment";
+
+// Zdarzenie audytu - kto (actor), co (action), na czym (target) i jakie pola się zmieniły
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,         // None for anonymous requests (e.g. failed logins)
+    pub impersonator_id: Option<Uuid>,  // Admin acting as the actor with an impersonation token
+    pub api_key_id: Option<Uuid>,       // Set when the actor authenticated with an API key
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub changes: Option<serde_json::Value>,  // {"field": {"from": ..., "to": ...}}
+    pub ip_address: Option<String>,
+    pub request_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Zdarzenie do zapisania (id i created_at nadaje baza)
+#[derive(Debug)]
+pub struct NewAuditEvent {
+    pub actor_id: Option<Uuid>,
+    pub impersonator_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub action: &'static str,
+    pub target_type: Option<&'static str>,
+    pub target_id: Option<Uuid>,
+    pub changes: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub request_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventQuery {
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
@@ -1,4 +1,6 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
 use super::user::UserResponse;
 
 // Model uwierzytelniania
@@ -8,9 +10,176 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+// Para tokenów zwracana po zalogowaniu lub odświeżeniu sesji
+#[derive(Debug, Serialize)]
+pub struct AuthTokens {
+    pub token: String,          // Short-lived JWT access token
+    pub refresh_token: String,  // Opaque refresh token (rotated on every use)
+}
+
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
     pub user: UserResponse,
+    #[serde(flatten)]
+    pub tokens: AuthTokens,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    #[serde(flatten)]
+    pub tokens: AuthTokens,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RefreshToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub family_id: Uuid,
+    pub token_hash: String,  // SHA-256 of the token (the token itself is never stored)
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub replaced_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordRequest {
     pub token: String,
+    pub new_password: String,
+}
+
+// Zmiana hasła zalogowanego użytkownika (albo z tokenem zmiany hasła z logowania)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PasswordResetToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct EmailVerificationToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Wynik logowania hasłem - przy włączonym 2FA zamiast tokenów zwracany jest token wyzwania,
+// a przy wymuszonej zmianie hasła - token przyjmowany tylko przez /api/auth/change-password
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Authenticated(UserResponse, AuthTokens),
+    TwoFactorRequired(TwoFactorChallengeResponse),
+    PasswordChangeRequired(PasswordChangeRequiredResponse),
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordChangeRequiredResponse {
+    pub password_change_required: bool,
+    pub password_change_token: String,  // Short-lived token accepted only by /api/auth/change-password
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_required: bool,
+    pub challenge_token: String,  // Short-lived token accepted only by /api/auth/login/2fa
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    pub code: String,  // TOTP code or one of the recovery codes
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollResponse {
+    pub secret: String,       // Base32 secret for manual entry
+    pub otpauth_uri: String,  // Provisioning URI to be rendered as a QR code
     pub message: String,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorRecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,  // Shown only once
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserTotp {
+    pub user_id: Uuid,
+    pub secret: String,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OAuthState {
+    pub id: Uuid,
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub link_user_id: Option<Uuid>,  // Set when a logged-in user is linking a new provider account
+    pub nonce: Option<String>,       // Expected ID token nonce (OpenID Connect providers only)
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Konto zewnętrznego dostawcy OAuth powiązane z użytkownikiem
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinkIdentityResponse {
+    pub authorization_url: String,
+    pub message: String,
+}
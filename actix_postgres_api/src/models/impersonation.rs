@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+use super::user::UserResponse;
+
+// Wpis dziennika impersonacji - rozpoczęcie albo pojedyncze żądanie wykonane tokenem impersonacji
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ImpersonationLogEntry {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,  // The admin acting as the user
+    pub user_id: Option<Uuid>,   // The impersonated user
+    pub event: String,           // "started" or "request"
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub status_code: Option<i32>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub token: String,  // Access token of the user carrying the admin in the `act` claim
+    pub expires_at: DateTime<Utc>,
+    pub user: UserResponse,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImpersonationLogQuery {
+    pub actor_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
@@ -1,13 +1,27 @@
 // Re-export all model components for easier imports
-pub use self::user::{User, CreateUserRequest, UpdateUserRequest, UserResponse};
-pub use self::auth::{LoginRequest, LoginResponse};
+pub use self::user::{User, CreateUserRequest, RegisterRequest, UpdateUserRequest, UserResponse, UserListQuery, UserCursor};
+pub use self::auth::{LoginRequest, LoginResponse, AuthTokens, RefreshTokenRequest, TokenResponse, RefreshToken, ForgotPasswordRequest, ResetPasswordRequest, ChangePasswordRequest, PasswordChangeRequiredResponse, PasswordResetToken, VerifyEmailRequest, ResendVerificationRequest, EmailVerificationToken, LoginOutcome, TwoFactorChallengeResponse, TwoFactorLoginRequest, TwoFactorCodeRequest, TwoFactorEnrollResponse, TwoFactorRecoveryCodesResponse, UserTotp, OAuthState, UserIdentity, LinkIdentityResponse};
 pub use self::statistics::{UserStatistics, UserRoleStatistics};
+pub use self::invitation::{Invitation, CreateInvitationRequest, AcceptInvitationRequest, InvitationResponse};
+pub use self::api_key::{ApiKey, CreateApiKeyRequest, ApiKeyResponse, IssuedApiKeyResponse, ApiKeyQuery};
+pub use self::impersonation::{ImpersonationLogEntry, ImpersonationResponse, ImpersonationLogQuery};
+pub use self::session::{Session, SessionResponse, RevokeSessionsQuery};
+pub use self::audit::{AuditEvent, AuditEventQuery, NewAuditEvent};
+pub use self::pagination::{PageRequest, PaginatedResponse, SortOrder};
+pub use self::privacy::{ExportFormat, ExportQuery, UserDataExport, ErasureResponse};
 pub use self::chat::{ChatMessage, ChatMessageResponse, CreateChatMessageRequest, ChatRoom, WsMessage};
 
 // Define submodules
 pub mod user;
 pub mod auth;
 pub mod role;
+pub mod invitation;
+pub mod api_key;
+pub mod session;
+pub mod impersonation;
+pub mod audit;
+pub mod pagination;
+pub mod privacy;
 pub mod chat;
 pub mod statistics;
 pub mod appointment;
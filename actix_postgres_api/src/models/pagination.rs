@@ -0,0 +1,115 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+// Default and maximum page size of paginated list endpoints
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+// Strona wyników wybrana parametrami `page` (od 1) i `per_page`
+#[derive(Debug, Clone, Copy)]
+pub struct PageRequest {
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl PageRequest {
+    // Out-of-range values are clamped instead of rejected
+    pub fn new(page: Option<i64>, per_page: Option<i64>) -> Self {
+        Self {
+            page: page.unwrap_or(1).max(1),
+            per_page: per_page.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page - 1) * self.per_page
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaginationMeta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,  // None for pages requested with a cursor
+    pub per_page: i64,
+    pub total: i64,
+    pub total_pages: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,  // Only on list endpoints with cursor pagination, None on the last page
+}
+
+// Wspólna koperta odpowiedzi list stronicowanych
+#[derive(Debug, Serialize)]
+pub struct PaginatedResponse<T> {
+    pub data: Vec<T>,
+    pub pagination: PaginationMeta,
+}
+
+impl<T> PaginatedResponse<T> {
+    pub fn new(data: Vec<T>, page: PageRequest, total: i64) -> Self {
+        Self {
+            data,
+            pagination: PaginationMeta {
+                page: Some(page.page),
+                per_page: page.per_page,
+                total,
+                total_pages: (total + page.per_page - 1) / page.per_page,
+                next_cursor: None,
+            },
+        }
+    }
+
+    // `page` is None when the page was requested with a cursor instead of a page number
+    pub fn with_cursor(data: Vec<T>, page: Option<PageRequest>, per_page: i64, total: i64, next_cursor: Option<String>) -> Self {
+        Self {
+            data,
+            pagination: PaginationMeta {
+                page: page.map(|page| page.page),
+                per_page,
+                total,
+                total_pages: (total + per_page - 1) / per_page,
+                next_cursor,
+            },
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PaginatedResponse<U> {
+        PaginatedResponse {
+            data: self.data.into_iter().map(f).collect(),
+            pagination: self.pagination,
+        }
+    }
+}
+
+// Kursor to nieprzezroczysty dla klienta JSON zakodowany w base64url
+pub fn encode_cursor<T: Serialize>(cursor: &T) -> String {
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+pub fn decode_cursor<T: DeserializeOwned>(cursor: &str) -> Result<T, AppError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+}
@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::types::Uuid;
+
+use super::appointment::Appointment;
+use super::chat::ChatMessage;
+use super::user::UserResponse;
+
+// Format archiwum z danymi użytkownika
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Zip,  // one JSON file per section
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+// Wszystkie dane osobowe użytkownika ("download my data")
+#[derive(Debug, Serialize)]
+pub struct UserDataExport {
+    pub exported_at: DateTime<Utc>,
+    pub profile: UserResponse,
+    pub appointments: Vec<Appointment>,   // as the client or the trainer
+    pub chat_messages: Vec<ChatMessage>,  // sent by the user
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErasureResponse {
+    pub user_id: Uuid,
+    pub erasure_scheduled_at: Option<DateTime<Utc>>,  // None once the request is cancelled
+}
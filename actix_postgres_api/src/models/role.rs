@@ -1,7 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 // Enum reprezentujący role użytkowników
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
 #[sqlx(type_name = "varchar", rename_all = "lowercase")]
 pub enum UserRole {
     Client,
@@ -34,4 +35,44 @@ impl From<&str> for UserRole {
             _ => UserRole::Client, // domyślnie ustawiamy Client
         }
     }
-}
\ No newline at end of file
+}
+// Rola przechowywana w bazie - uprawnienia dziedziczy po roli nadrzędnej
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Role {
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_role: Option<String>,
+    pub is_system: bool,  // client, trainer and admin cannot be deleted
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Permission {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_role: Option<String>,
+}
+
+// Replaces description and parent role (null removes the parent)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub description: Option<String>,
+    pub parent_role: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RoleResponse {
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_role: Option<String>,
+    pub is_system: bool,
+    pub permissions: Vec<String>,            // granted directly to the role
+    pub effective_permissions: Vec<String>,  // including permissions inherited from parent roles
+}
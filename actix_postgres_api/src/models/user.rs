@@ -1,7 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::types::Uuid;
 
+use super::pagination::SortOrder;
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
@@ -14,6 +17,13 @@ pub struct User {
     pub role: String,  // Role as string to simplify database interaction
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub email_verified_at: Option<DateTime<Utc>>,  // NULL until the email address is confirmed
+    pub failed_login_count: i32,  // Consecutive failed logins (reset on success)
+    pub locked_until: Option<DateTime<Utc>>,  // Login refused until this time
+    pub must_change_password: bool,  // Login only yields a password change token until a new password is set
+    pub erasure_scheduled_at: Option<DateTime<Utc>>,  // Pending erasure request - the account is anonymized at this time
+    pub erased_at: Option<DateTime<Utc>>,  // Set once the personal data has been anonymized
+    pub deleted_at: Option<DateTime<Utc>>,  // Soft delete - hidden from all queries until restored or purged
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,17 +36,93 @@ pub struct CreateUserRequest {
     pub role: Option<String>,  // Optional role - if not provided, default to CLIENT
 }
 
+// Public signup - the account is always created with the client role
 #[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterRequest {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+    pub full_name: String,
+    pub phone_number: Option<String>,
+}
+
+impl From<RegisterRequest> for CreateUserRequest {
+    fn from(request: RegisterRequest) -> Self {
+        Self {
+            username: request.username,
+            email: request.email,
+            password: request.password,
+            full_name: request.full_name,
+            phone_number: request.phone_number,
+            role: None,
+        }
+    }
+}
+
+// Merge-patch: a missing field is left unchanged, `null` clears a nullable field
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct UpdateUserRequest {
+    #[serde(default, deserialize_with = "non_null", skip_serializing_if = "Option::is_none")]
     pub username: Option<String>,
+    #[serde(default, deserialize_with = "non_null", skip_serializing_if = "Option::is_none")]
     pub email: Option<String>,
+    #[serde(default, deserialize_with = "non_null", skip_serializing_if = "Option::is_none")]
     pub password: Option<String>,  // Optional password update
+    #[serde(default, deserialize_with = "non_null", skip_serializing_if = "Option::is_none")]
     pub full_name: Option<String>,
-    pub phone_number: Option<String>,
+    #[serde(default, deserialize_with = "nullable", skip_serializing_if = "Option::is_none")]
+    pub phone_number: Option<Option<String>>,  // Some(None) clears the phone number
+    #[serde(default, deserialize_with = "non_null", skip_serializing_if = "Option::is_none")]
     pub active: Option<bool>,
+    #[serde(default, deserialize_with = "non_null", skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,  // Optional role update
 }
 
+impl UpdateUserRequest {
+    // Drops fields that already hold the requested value, so only real changes are written
+    pub fn retain_changes(self, current: &User) -> Self {
+        Self {
+            username: self.username.filter(|v| *v != current.username),
+            email: self.email.filter(|v| *v != current.email),
+            password: self.password,
+            full_name: self.full_name.filter(|v| *v != current.full_name),
+            phone_number: self.phone_number.filter(|v| *v != current.phone_number),
+            active: self.active.filter(|v| *v != current.active),
+            role: self.role.filter(|v| *v != current.role),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none()
+            && self.email.is_none()
+            && self.password.is_none()
+            && self.full_name.is_none()
+            && self.phone_number.is_none()
+            && self.active.is_none()
+            && self.role.is_none()
+    }
+}
+
+// Pole wymagane - jawny `null` jest błędem zamiast cichego pominięcia
+fn non_null<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer)?
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom("null is only allowed for nullable fields"))
+}
+
+// Present field (also `null`) becomes Some, so a missing field stays None
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: Uuid,
@@ -46,6 +132,8 @@ pub struct UserResponse {
     pub phone_number: Option<String>,
     pub active: bool,
     pub role: String,  // Included in response
+    pub email_verified: bool,
+    pub erasure_scheduled_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     // Nie zwracamy password_hash w odpowiedzi API
@@ -61,8 +149,84 @@ impl From<User> for UserResponse {
             phone_number: user.phone_number,
             active: user.active,
             role: user.role,
+            email_verified: user.email_verified_at.is_some(),
+            erasure_scheduled_at: user.erasure_scheduled_at,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
     }
-}
\ No newline at end of file
+}
+
+// Kolumny, po których można sortować listę użytkowników
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Username,
+    Email,
+    FullName,
+}
+
+impl UserSortField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            UserSortField::CreatedAt => "created_at",
+            UserSortField::UpdatedAt => "updated_at",
+            UserSortField::Username => "username",
+            UserSortField::Email => "email",
+            UserSortField::FullName => "full_name",
+        }
+    }
+
+    pub fn is_timestamp(&self) -> bool {
+        matches!(self, UserSortField::CreatedAt | UserSortField::UpdatedAt)
+    }
+
+    // Value of the column for a row, as stored in a cursor
+    pub fn value_of(&self, user: &User) -> String {
+        match self {
+            UserSortField::CreatedAt => user.created_at.to_rfc3339(),
+            UserSortField::UpdatedAt => user.updated_at.to_rfc3339(),
+            UserSortField::Username => user.username.clone(),
+            UserSortField::Email => user.email.clone(),
+            UserSortField::FullName => user.full_name.clone(),
+        }
+    }
+}
+
+// Query parameters of the user list; `cursor` takes precedence over `page`
+#[derive(Debug, Default, Deserialize)]
+pub struct UserListQuery {
+    pub role: Option<String>,
+    pub active: Option<bool>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub search: Option<String>,  // case-insensitive, matches username, email and full name
+    #[serde(default)]
+    pub sort: UserSortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+impl UserListQuery {
+    // Skrót filtrów zapisywany w kursorze - z innymi filtrami pozycja kursora nie ma sensu
+    pub fn filter_hash(&self) -> String {
+        let filters = serde_json::json!([self.search, self.role, self.active, self.created_from, self.created_to]);
+        hex::encode(Sha256::digest(filters.to_string().as_bytes()))
+    }
+}
+
+// Position after the last row of a page; only valid with the same filters, sort column and order
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserCursor {
+    pub sort: UserSortField,
+    pub order: SortOrder,
+    pub filters: String,  // UserListQuery::filter_hash of the listed query
+    pub value: String,
+    pub id: Uuid,
+}
@@ -0,0 +1,117 @@
+use std::collections::HashSet;
+use chrono::Utc;
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{ApiKey, CreateApiKeyRequest, User};
+use crate::database::user::UserRepository;
+use crate::database::{ApiKeyRepository, RoleRepository};
+use crate::auth_utils::permissions::is_granted;
+use crate::auth_utils::tokens::{generate_opaque_token, hash_token};
+use crate::services::PermissionService;
+
+// Prefix of every API key; lets the auth layer tell keys apart and makes leaked keys easy to find
+pub const API_KEY_PREFIX: &str = "ak_";
+
+// Number of characters stored in plain text to recognise the key in listings
+const DISPLAY_PREFIX_LEN: usize = 11;
+
+pub struct ApiKeyService {
+    repo: ApiKeyRepository,
+    user_repo: UserRepository,
+    role_repo: RoleRepository,
+    permission_service: PermissionService,
+}
+
+impl ApiKeyService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repo: ApiKeyRepository::new(pool.clone()),
+            user_repo: UserRepository::new(pool.clone()),
+            role_repo: RoleRepository::new(pool.clone()),
+            permission_service: PermissionService::new(pool),
+        }
+    }
+
+    // Issue a key for the user; returns the key in plain text (shown once) with its record
+    pub async fn issue(&self, mut request: CreateApiKeyRequest, created_by: Uuid) -> Result<(String, ApiKey), AppError> {
+        request.name = request.name.trim().to_string();
+        if request.name.is_empty() || request.name.len() > 100 {
+            return Err(AppError::ValidationError("Name must be between 1 and 100 characters".to_string()));
+        }
+
+        if request.expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(AppError::ValidationError("Expiry must be in the future".to_string()));
+        }
+
+        request.scopes.sort();
+        request.scopes.dedup();
+        if request.scopes.is_empty() {
+            return Err(AppError::ValidationError("At least one scope is required".to_string()));
+        }
+
+        let known: HashSet<String> = self.role_repo
+            .find_all_permissions()
+            .await?
+            .into_iter()
+            .map(|permission| permission.name)
+            .collect();
+
+        if let Some(unknown) = request.scopes.iter().find(|scope| !known.contains(*scope)) {
+            return Err(AppError::ValidationError(format!("Unknown scope {}", unknown)));
+        }
+
+        // Klucz nie może dawać więcej niż rola właściciela
+        let owner = self.user_repo.find_by_id(request.user_id).await?;
+        let granted = self.permission_service.permissions_for_role(&owner.role).await?;
+        if let Some(scope) = request.scopes.iter().find(|scope| !is_granted(&granted, scope)) {
+            return Err(AppError::BadRequest(format!("The role {} does not grant the scope {}", owner.role, scope)));
+        }
+
+        let key = format!("{}{}", API_KEY_PREFIX, generate_opaque_token());
+        let api_key = self.repo
+            .create(&request, &key[..DISPLAY_PREFIX_LEN], &hash_token(&key), created_by)
+            .await?;
+
+        tracing::info!("API key {} issued for user {} by user {}", api_key.id, api_key.user_id, created_by);
+        Ok((key, api_key))
+    }
+
+    pub async fn list(&self, user_id: Option<Uuid>) -> Result<Vec<ApiKey>, AppError> {
+        self.repo.find_all(user_id).await
+    }
+
+    pub async fn revoke(&self, id: Uuid) -> Result<ApiKey, AppError> {
+        let api_key = self.repo
+            .revoke(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Active API key with ID {} not found", id)))?;
+
+        tracing::info!("API key {} revoked", id);
+        Ok(api_key)
+    }
+
+    // Resolves a key presented by a client together with its owner; None for unknown, revoked
+    // or expired keys and for keys of inactive users
+    pub async fn authenticate(&self, key: &str) -> Result<Option<(ApiKey, User)>, AppError> {
+        if !key.starts_with(API_KEY_PREFIX) {
+            return Ok(None);
+        }
+
+        let Some(api_key) = self.repo.use_key(&hash_token(key)).await? else {
+            return Ok(None);
+        };
+
+        let owner = self.user_repo.find_by_id(api_key.user_id).await?;
+        if !owner.active {
+            return Ok(None);
+        }
+
+        if owner.must_change_password {
+            return Err(AppError::Forbidden("Password change required".to_string()));
+        }
+
+        Ok(Some((api_key, owner)))
+    }
+}
@@ -1,17 +1,22 @@
 use crate::error::AppError;
 use crate::models::appointment::{Appointment, AppointmentWithNames, CreateAppointmentRequest, UpdateAppointmentRequest};
 use crate::models::role::UserRole;
+use crate::models::audit::{APPOINTMENT_CREATED, APPOINTMENT_DELETED, APPOINTMENT_STATUS_CHANGED, APPOINTMENT_UPDATED, TARGET_APPOINTMENT};
 use crate::database::AppointmentRepository;
+use crate::services::audit::{diff, AuditContext, AuditService};
+use crate::middleware::IfMatch;
 use sqlx::{postgres::PgPool, types::Uuid};
 
 pub struct AppointmentService {
     repository: AppointmentRepository,
+    audit: AuditService,
 }
 
 impl AppointmentService {
     pub fn new(pool: PgPool) -> Self {
         Self {
-            repository: AppointmentRepository::new(pool),
+            repository: AppointmentRepository::new(pool.clone()),
+            audit: AuditService::new(pool),
         }
     }
     
@@ -44,7 +49,8 @@ impl AppointmentService {
         &self, 
         client_id: &str, 
         appointment: CreateAppointmentRequest,
-        db_pool: &PgPool
+        db_pool: &PgPool,
+        audit: &AuditContext
     ) -> Result<Appointment, AppError> {
         // Validate client ID
         let client_uuid = Uuid::parse_str(client_id)
@@ -56,12 +62,15 @@ impl AppointmentService {
         let trainer = user_repo.find_by_id(trainer_id).await?;
         
         // Check if the trainer has the correct role
-        if trainer.role != UserRole::Trainer.to_string() {
+        if UserRole::from(trainer.role.as_str()) != UserRole::Trainer {
             return Err(AppError::BadRequest("Selected user is not a trainer".to_string()));
         }
         
         // Create the appointment
-        self.repository.create(client_uuid, appointment).await
+        let created = self.repository.create(client_uuid, appointment).await?;
+        
+        self.audit.record(audit, APPOINTMENT_CREATED, Some((TARGET_APPOINTMENT, created.id)), diff(None, Some(&created))).await;
+        Ok(created)
     }
     
     pub async fn create_appointment_by_trainer(
@@ -69,7 +78,8 @@ impl AppointmentService {
         trainer_id: &str, 
         client_id: &str,
         appointment: CreateAppointmentRequest,
-        db_pool: &PgPool
+        db_pool: &PgPool,
+        audit: &AuditContext
     ) -> Result<Appointment, AppError> {
         // Validate trainer ID
         let trainer_uuid = Uuid::parse_str(trainer_id)
@@ -90,24 +100,42 @@ impl AppointmentService {
         };
         
         // Create the appointment
-        self.repository.create(client_uuid, modified_appointment).await
+        let created = self.repository.create(client_uuid, modified_appointment).await?;
+        
+        self.audit.record(audit, APPOINTMENT_CREATED, Some((TARGET_APPOINTMENT, created.id)), diff(None, Some(&created))).await;
+        Ok(created)
     }
     
     pub async fn update_appointment(
         &self,
         id: &str,
-        appointment: UpdateAppointmentRequest
+        appointment: UpdateAppointmentRequest,
+        audit: &AuditContext,
+        if_match: &IfMatch
     ) -> Result<Appointment, AppError> {
         let appointment_id = Uuid::parse_str(id)
             .map_err(|_| AppError::BadRequest("Invalid appointment ID format".to_string()))?;
+        let existing = self.repository.find_by_id(appointment_id).await?;
             
-        self.repository.update(appointment_id, appointment).await
+        let updated = self.repository.update(appointment_id, appointment, if_match.versions()).await?;
+        
+        // Status changes (e.g. cancellations) get their own action
+        let action = if updated.status != existing.status { APPOINTMENT_STATUS_CHANGED } else { APPOINTMENT_UPDATED };
+        if let Some(changes) = diff(Some(&existing), Some(&updated)) {
+            self.audit.record(audit, action, Some((TARGET_APPOINTMENT, appointment_id)), Some(changes)).await;
+        }
+        
+        Ok(updated)
     }
     
-    pub async fn delete_appointment(&self, id: &str) -> Result<(), AppError> {
+    pub async fn delete_appointment(&self, id: &str, audit: &AuditContext, if_match: &IfMatch) -> Result<(), AppError> {
         let appointment_id = Uuid::parse_str(id)
             .map_err(|_| AppError::BadRequest("Invalid appointment ID format".to_string()))?;
+        let existing = self.repository.find_by_id(appointment_id).await?;
             
-        self.repository.delete(appointment_id).await
+        self.repository.delete(appointment_id, if_match.versions()).await?;
+        
+        self.audit.record(audit, APPOINTMENT_DELETED, Some((TARGET_APPOINTMENT, appointment_id)), diff(Some(&existing), None)).await;
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,128 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{AuditEvent, AuditEventQuery, NewAuditEvent, PageRequest, PaginatedResponse};
+use crate::database::AuditEventRepository;
+use crate::middleware::{AuthUser, ClientInfo};
+
+// Fields never written to an audit diff as values - only the fact that they changed.
+// Personal data stays out as well, since the append-only log cannot be scrubbed after an erasure
+const REDACTED_FIELDS: [&str; 5] = ["password_hash", "email", "username", "full_name", "phone_number"];
+
+// Bookkeeping fields left out of diffs
+const IGNORED_FIELDS: [&str; 3] = ["updated_at", "failed_login_count", "locked_until"];
+
+// Kto wykonuje operację i skąd - przekazywane z handlera do serwisów zapisujących audyt
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<Uuid>,
+    pub impersonator_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub client: ClientInfo,
+}
+
+impl AuditContext {
+    pub fn new(auth: &AuthUser, client: ClientInfo) -> Self {
+        Self {
+            actor_id: Some(auth.id),
+            impersonator_id: auth.impersonator_id,
+            api_key_id: auth.api_key_id,
+            client,
+        }
+    }
+
+    // Request without a logged-in user (registration, login, password reset)
+    pub fn anonymous(client: ClientInfo) -> Self {
+        Self { client, ..Default::default() }
+    }
+
+    // The same request attributed to a user identified during it (e.g. by the login itself)
+    pub fn as_actor(&self, actor_id: Uuid) -> Self {
+        Self { actor_id: Some(actor_id), ..self.clone() }
+    }
+}
+
+pub struct AuditService {
+    repo: AuditEventRepository,
+}
+
+impl AuditService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repo: AuditEventRepository::new(pool),
+        }
+    }
+
+    // Appends an event. The audited operation has already happened at this point, so a failed
+    // write is logged instead of failing the request.
+    pub async fn record(
+        &self,
+        context: &AuditContext,
+        action: &'static str,
+        target: Option<(&'static str, Uuid)>,
+        changes: Option<Value>,
+    ) {
+        let event = NewAuditEvent {
+            actor_id: context.actor_id,
+            impersonator_id: context.impersonator_id,
+            api_key_id: context.api_key_id,
+            action,
+            target_type: target.map(|(target_type, _)| target_type),
+            target_id: target.map(|(_, target_id)| target_id),
+            changes,
+            ip_address: context.client.ip.clone(),
+            request_id: context.client.request_id,
+        };
+
+        if let Err(e) = self.repo.create(&event).await {
+            tracing::error!(action, "Failed to write audit event: {}", e);
+        }
+    }
+
+    pub async fn list(&self, query: &AuditEventQuery) -> Result<PaginatedResponse<AuditEvent>, AppError> {
+        let page = PageRequest::new(query.page, query.per_page);
+        let (events, total) = self.repo.find_page(query, page).await?;
+
+        Ok(PaginatedResponse::new(events, page, total))
+    }
+}
+
+// Zmienione pola w postaci {"pole": {"from": ..., "to": ...}}. `None` po którejś stronie oznacza
+// utworzenie albo usunięcie obiektu; zwraca None, gdy nic się nie zmieniło.
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Option<Value> {
+    let to_map = |value: Option<&T>| match value.map(serde_json::to_value) {
+        Some(Ok(Value::Object(map))) => map,
+        _ => Map::new(),
+    };
+    let before = to_map(before);
+    let after = to_map(after);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if changes.contains_key(key) || IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+
+        let from = before.get(key).cloned().unwrap_or(Value::Null);
+        let to = after.get(key).cloned().unwrap_or(Value::Null);
+        if from == to {
+            continue;
+        }
+
+        let change = if REDACTED_FIELDS.contains(&key.as_str()) {
+            serde_json::json!({ "changed": true })
+        } else {
+            serde_json::json!({ "from": from, "to": to })
+        };
+        changes.insert(key.clone(), change);
+    }
+
+    if changes.is_empty() {
+        None
+    } else {
+        Some(Value::Object(changes))
+    }
+}
@@ -1,37 +1,483 @@
+use chrono::{Duration, Utc};
 use sqlx::postgres::PgPool;
+use uuid::Uuid;
 
 use crate::error::AppError;
-use crate::models::{LoginRequest, UserResponse};
+use crate::models::{
+    AuthTokens, LoginOutcome, LoginRequest, PasswordChangeRequiredResponse, TwoFactorChallengeResponse, User, UserResponse,
+};
 use crate::database::user::UserRepository;
-use crate::auth_utils::validate_email;
-use crate::auth_utils::jwt::generate_token;
+use crate::database::{EmailVerificationRepository, LoginAttemptRepository, PasswordResetRepository, RefreshTokenRepository};
+use crate::auth_utils::{hash_password, validate_email, validate_password, verify_password, PasswordContext};
+use crate::auth_utils::jwt::{
+    generate_challenge_token, generate_password_change_token, generate_token, verify_challenge_token, JwtConfig,
+};
+use crate::auth_utils::account::AccountConfig;
+use crate::auth_utils::tokens::{generate_opaque_token, hash_token};
+use crate::auth_utils::totp::TotpConfig;
+use crate::auth_utils::lockout::{retry_after_seconds, LockoutConfig};
+use crate::middleware::ClientInfo;
+use crate::mail::{MailConfig, MailMessage, MailSender};
+use crate::models::audit::{AUTH_LOGIN, AUTH_LOGIN_FAILED, AUTH_LOGOUT, AUTH_PASSWORD_CHANGED, AUTH_PASSWORD_RESET, TARGET_USER};
+use crate::services::{AuditContext, AuditService, SessionService, TwoFactorService};
 
 pub struct AuthService {
     repo: UserRepository,
+    refresh_repo: RefreshTokenRepository,
+    password_reset_repo: PasswordResetRepository,
+    email_verification_repo: EmailVerificationRepository,
+    two_factor: TwoFactorService,
+    login_attempt_repo: LoginAttemptRepository,
+    sessions: SessionService,
+    audit: AuditService,
 }
 
 impl AuthService {
     pub fn new(pool: PgPool) -> Self {
         Self {
-            repo: UserRepository::new(pool),
+            repo: UserRepository::new(pool.clone()),
+            refresh_repo: RefreshTokenRepository::new(pool.clone()),
+            password_reset_repo: PasswordResetRepository::new(pool.clone()),
+            email_verification_repo: EmailVerificationRepository::new(pool.clone()),
+            two_factor: TwoFactorService::new(pool.clone()),
+            login_attempt_repo: LoginAttemptRepository::new(pool.clone()),
+            sessions: SessionService::new(pool.clone()),
+            audit: AuditService::new(pool),
         }
     }
 
-    pub async fn login(&self, login: LoginRequest) -> Result<(UserResponse, String), AppError> {
+    pub async fn login(&self, login: LoginRequest, client: &ClientInfo) -> Result<LoginOutcome, AppError> {
         // Walidacja danych logowania
         validate_email(&login.email)?;
-        
+
         if login.password.is_empty() {
             return Err(AppError::ValidationError("Password cannot be empty".to_string()));
         }
-        
+
+        // Zbyt wiele nieudanych prób z tego adresu IP
+        let lockout = LockoutConfig::from_env();
+        self.ensure_ip_not_blocked(client, &lockout).await?;
+
         // Authenticate user
-        let user = self.repo.authenticate(login).await?;
-        
-        // Generate JWT token
-        let token = generate_token(user.id, &user.username, &user.email, &user.role)?;
-        
-        // Return user response and token
-        Ok((UserResponse::from(user), token))
-    }
-}
\ No newline at end of file
+        let email = login.email.clone();
+        let require_verified_email = AccountConfig::from_env().require_email_verification;
+        let result = self.repo.authenticate(login, require_verified_email, &lockout).await;
+        self.record_login_attempt(&email, client, &result).await?;
+
+        self.complete_login(result?, client).await
+    }
+
+    // Rejects the request when the client IP has too many recent failed logins
+    async fn ensure_ip_not_blocked(&self, client: &ClientInfo, lockout: &LockoutConfig) -> Result<(), AppError> {
+        let Some(ip) = client.ip.as_deref() else {
+            return Ok(());
+        };
+
+        let window_start = Utc::now() - Duration::seconds(lockout.ip_window_seconds);
+        let (failures, oldest) = self.login_attempt_repo.recent_failures_by_ip(ip, window_start).await?;
+
+        if failures < lockout.ip_max_failed_logins {
+            return Ok(());
+        }
+
+        tracing::warn!(
+            target: "security",
+            event = "login_ip_blocked",
+            ip,
+            failed_attempts = failures,
+            "Login refused - too many failed attempts from this IP address"
+        );
+
+        // The block is lifted when the oldest failure leaves the window
+        let unblocked_at = oldest.unwrap_or_else(Utc::now) + Duration::seconds(lockout.ip_window_seconds);
+        Err(AppError::TooManyRequests {
+            message: "Too many failed login attempts, try again later".to_string(),
+            retry_after: retry_after_seconds(unblocked_at),
+        })
+    }
+
+    // Stores the attempt for per-IP limits and emits a security event on failure
+    async fn record_login_attempt<T>(
+        &self,
+        email: &str,
+        client: &ClientInfo,
+        result: &Result<T, AppError>,
+    ) -> Result<(), AppError> {
+        if let Err(e) = result {
+            tracing::warn!(
+                target: "security",
+                event = "login_failed",
+                email,
+                ip = client.ip.as_deref().unwrap_or("unknown"),
+                user_agent = client.user_agent.as_deref().unwrap_or("unknown"),
+                reason = %e,
+                "Failed login attempt"
+            );
+
+            // Dziennik audytu nie przechowuje adresu email, tylko jego hash; komunikat
+            // o nieznanym koncie zawiera adres, więc zastępuje go stały opis
+            let reason = match e {
+                AppError::NotFoundError(_) => "Unknown account".to_string(),
+                e => e.to_string(),
+            };
+            let details = serde_json::json!({ "email_hash": hash_token(email), "reason": reason });
+            self.audit.record(&AuditContext::anonymous(client.clone()), AUTH_LOGIN_FAILED, None, Some(details)).await;
+        }
+
+        self.login_attempt_repo
+            .record(email, client.ip.as_deref(), client.user_agent.as_deref(), result.is_ok())
+            .await
+    }
+
+    // Finish a successful first-factor login: issue tokens, or a challenge token when 2FA is enabled
+    pub async fn complete_login(&self, user: User, client: &ClientInfo) -> Result<LoginOutcome, AppError> {
+        // Password logins check this already, OAuth logins only get here
+        if !user.active {
+            return Err(AppError::ValidationError("Account is inactive".to_string()));
+        }
+
+        if self.two_factor.find_enabled(user.id).await?.is_some() {
+            let challenge_token = generate_challenge_token(user.id, TotpConfig::from_env().challenge_expiration)?;
+
+            return Ok(LoginOutcome::TwoFactorRequired(TwoFactorChallengeResponse {
+                two_factor_required: true,
+                challenge_token,
+                message: "Two-factor authentication code required".to_string(),
+            }));
+        }
+
+        self.finish_login(user, client).await
+    }
+
+    // Last login step once all factors are verified; accounts flagged with must_change_password
+    // get only a restricted password change token instead of a session
+    async fn finish_login(&self, user: User, client: &ClientInfo) -> Result<LoginOutcome, AppError> {
+        if user.failed_login_count > 0 || user.locked_until.is_some() {
+            self.repo.reset_failed_logins(user.id).await?;
+        }
+
+        if user.must_change_password {
+            let expiration = AccountConfig::from_env().password_change_token_expiration;
+            let password_change_token = generate_password_change_token(user.id, expiration)?;
+
+            tracing::info!("Password change required before login for user {}", user.id);
+            return Ok(LoginOutcome::PasswordChangeRequired(PasswordChangeRequiredResponse {
+                password_change_required: true,
+                password_change_token,
+                message: "Password change required".to_string(),
+            }));
+        }
+
+        // Generate access and refresh tokens
+        let tokens = self.issue_tokens(&user, client).await?;
+
+        let audit = AuditContext::anonymous(client.clone()).as_actor(user.id);
+        self.audit.record(&audit, AUTH_LOGIN, Some((TARGET_USER, user.id)), None).await;
+
+        // Return user response and tokens
+        Ok(LoginOutcome::Authenticated(UserResponse::from(user), tokens))
+    }
+
+    // Second login step: exchange the challenge token and a TOTP or recovery code for tokens
+    pub async fn login_two_factor(
+        &self,
+        challenge_token: &str,
+        code: &str,
+        client: &ClientInfo,
+    ) -> Result<LoginOutcome, AppError> {
+        let lockout = LockoutConfig::from_env();
+        self.ensure_ip_not_blocked(client, &lockout).await?;
+
+        let claims = verify_challenge_token(challenge_token)?;
+        let user_id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| AppError::ValidationError("Invalid challenge token".to_string()))?;
+
+        let user = self.repo.find_by_id(user_id).await?;
+        if !user.active {
+            return Err(AppError::ValidationError("Account is inactive".to_string()));
+        }
+
+        let totp = self.two_factor.find_enabled(user.id).await?
+            .ok_or_else(|| AppError::ValidationError("Invalid challenge token".to_string()))?;
+
+        // Wrong codes count towards the same account lockout as wrong passwords
+        let result = match UserRepository::ensure_not_locked(&user) {
+            Err(e) => Err(e),
+            Ok(()) if self.two_factor.verify_code(&totp, code).await? => Ok(()),
+            Ok(()) => {
+                tracing::warn!("Invalid two-factor code for user {}", user.id);
+                self.repo.register_failed_login(user.id, &lockout).await?;
+                Err(AppError::ValidationError("Invalid two-factor code".to_string()))
+            }
+        };
+        self.record_login_attempt(&user.email, client, &result).await?;
+        result?;
+
+        self.finish_login(user, client).await
+    }
+
+    // Start a new session and issue its access token together with the first refresh token;
+    // the session id is used as the refresh token family id
+    pub async fn issue_tokens(&self, user: &User, client: &ClientInfo) -> Result<AuthTokens, AppError> {
+        let session = self.sessions.start(user.id, client).await?;
+        let token = generate_token(user.id, &user.username, &user.email, &user.role, session.id)?;
+
+        let refresh_token = generate_opaque_token();
+        self.refresh_repo
+            .create(user.id, session.id, &hash_token(&refresh_token), session.expires_at)
+            .await?;
+
+        Ok(AuthTokens { token, refresh_token })
+    }
+
+    // Exchange a refresh token for a new token pair, rotating the refresh token
+    pub async fn refresh(&self, refresh_token: &str) -> Result<AuthTokens, AppError> {
+        let current = self.refresh_repo
+            .find_by_hash(&hash_token(refresh_token))
+            .await?
+            .ok_or_else(|| AppError::ValidationError("Invalid refresh token".to_string()))?;
+
+        // A revoked token being presented again means it was stolen or replayed,
+        // so the whole family is revoked and the user has to log in again
+        if current.revoked_at.is_some() {
+            self.revoke_reused_family(current.family_id, current.user_id).await?;
+            return Err(AppError::ValidationError("Invalid refresh token".to_string()));
+        }
+
+        if current.expires_at <= Utc::now() {
+            return Err(AppError::ValidationError("Refresh token expired".to_string()));
+        }
+
+        let user = self.repo.find_by_id(current.user_id).await?;
+        if !user.active {
+            self.end_session(current.family_id, current.user_id).await?;
+            return Err(AppError::ValidationError("Account is inactive".to_string()));
+        }
+
+        // Sessions are not extended until the flagged account sets a new password
+        if user.must_change_password {
+            return Err(AppError::Forbidden("Password change required".to_string()));
+        }
+
+        // The session was signed out (or the token predates sessions)
+        if !self.sessions.extend(current.family_id).await? {
+            self.refresh_repo.revoke_family(current.family_id).await?;
+            return Err(AppError::ValidationError("Session has been revoked".to_string()));
+        }
+
+        let new_refresh_token = generate_opaque_token();
+        let expires_at = Utc::now() + Duration::seconds(JwtConfig::from_env().refresh_expiration());
+        let rotated = self.refresh_repo
+            .rotate(&current, &hash_token(&new_refresh_token), expires_at)
+            .await?;
+
+        // Another request rotated this token first - treat it as reuse as well
+        if rotated.is_none() {
+            self.revoke_reused_family(current.family_id, current.user_id).await?;
+            return Err(AppError::ValidationError("Invalid refresh token".to_string()));
+        }
+
+        let token = generate_token(user.id, &user.username, &user.email, &user.role, current.family_id)?;
+
+        Ok(AuthTokens { token, refresh_token: new_refresh_token })
+    }
+
+    // End the session the given refresh token belongs to, revoking its whole token family;
+    // returns the session id
+    pub async fn logout(&self, refresh_token: &str, client: &ClientInfo) -> Result<Uuid, AppError> {
+        let current = self.refresh_repo
+            .find_by_hash(&hash_token(refresh_token))
+            .await?
+            .ok_or_else(|| AppError::ValidationError("Invalid refresh token".to_string()))?;
+
+        self.end_session(current.family_id, current.user_id).await?;
+        tracing::info!("Session {} ended on logout", current.family_id);
+
+        let audit = AuditContext::anonymous(client.clone()).as_actor(current.user_id);
+        self.audit.record(&audit, AUTH_LOGOUT, Some((TARGET_USER, current.user_id)), None).await;
+
+        Ok(current.family_id)
+    }
+
+    // Send a password reset link if an account with this email exists.
+    // Unknown emails are ignored silently so the endpoint cannot be used to enumerate accounts.
+    pub async fn forgot_password(&self, email: &str, mailer: &dyn MailSender) -> Result<(), AppError> {
+        validate_email(email)?;
+
+        let user = match self.repo.find_by_email(email).await {
+            Ok(user) => user,
+            Err(AppError::NotFoundError(_)) => {
+                // Only a hash is logged - the address may be mistyped personal data of someone else
+                tracing::info!("Password reset requested for unknown email (hash {})", hash_token(email));
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+
+        let token = generate_opaque_token();
+        let expiration = AccountConfig::from_env().password_reset_expiration;
+        let expires_at = Utc::now() + Duration::seconds(expiration);
+        self.password_reset_repo
+            .create(user.id, &hash_token(&token), expires_at)
+            .await?;
+
+        let link = MailConfig::from_env().link("reset-password", &token);
+        mailer.send(MailMessage {
+            to: user.email.clone(),
+            subject: "Password reset".to_string(),
+            body: format!(
+                "Hello {},\n\nUse the link below to set a new password. It is valid for {} minutes and can be used only once.\n\n{}\n\nIf you did not request a password reset, you can ignore this email.",
+                user.full_name, expiration / 60, link
+            ),
+        }).await?;
+
+        tracing::info!("Password reset link sent to user {}", user.id);
+        Ok(())
+    }
+
+    // Set a new password using a single-use reset token; returns the ids of the revoked sessions
+    pub async fn reset_password(&self, token: &str, new_password: &str, client: &ClientInfo) -> Result<Vec<Uuid>, AppError> {
+        let invalid_token = || AppError::ValidationError("Invalid or expired password reset token".to_string());
+
+        // The token is consumed only once the new password is accepted, so a rejected password
+        // does not force the user to request another link
+        let pending = self.password_reset_repo
+            .find_valid(&hash_token(token))
+            .await?
+            .ok_or_else(invalid_token)?;
+        let user = self.repo.find_by_id(pending.user_id).await?;
+        validate_password(new_password, &PasswordContext::from(&user))?;
+
+        let reset = self.password_reset_repo
+            .consume(&hash_token(token))
+            .await?
+            .ok_or_else(invalid_token)?;
+
+        let password_hash = hash_password(new_password)?;
+        self.repo.update_password(reset.user_id, &password_hash).await?;
+
+        // Sessions started with the old password must not survive the reset
+        self.refresh_repo.revoke_all_for_user(reset.user_id).await?;
+        let revoked_sessions = self.sessions.revoke_all(reset.user_id, None).await?;
+
+        tracing::info!("Password reset completed for user {}", reset.user_id);
+
+        let audit = AuditContext::anonymous(client.clone()).as_actor(reset.user_id);
+        self.audit.record(&audit, AUTH_PASSWORD_RESET, Some((TARGET_USER, reset.user_id)), None).await;
+        Ok(revoked_sessions)
+    }
+
+    // Change the password after verifying the current one. All existing sessions are revoked and
+    // a new one is started; returns the user, its tokens and the ids of the revoked sessions.
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        current_password: &str,
+        new_password: &str,
+        client: &ClientInfo,
+    ) -> Result<(UserResponse, AuthTokens, Vec<Uuid>), AppError> {
+        let user = self.repo.find_by_id(user_id).await?;
+        if !user.active {
+            return Err(AppError::ValidationError("Account is inactive".to_string()));
+        }
+
+        // Wrong current passwords count towards the account lockout, like failed logins
+        UserRepository::ensure_not_locked(&user)?;
+        if !verify_password(current_password, &user.password_hash)? {
+            tracing::warn!("Invalid current password on password change for user {}", user.id);
+            self.repo.register_failed_login(user.id, &LockoutConfig::from_env()).await?;
+            return Err(AppError::ValidationError("Current password is incorrect".to_string()));
+        }
+
+        validate_password(new_password, &PasswordContext::from(&user))?;
+        if new_password == current_password {
+            return Err(AppError::ValidationError("New password must be different from the current password".to_string()));
+        }
+
+        let password_hash = hash_password(new_password)?;
+        self.repo.update_password(user.id, &password_hash).await?;
+
+        if user.failed_login_count > 0 || user.locked_until.is_some() {
+            self.repo.reset_failed_logins(user.id).await?;
+        }
+
+        // Sessions started with the old password must not survive the change
+        self.refresh_repo.revoke_all_for_user(user.id).await?;
+        let revoked_sessions = self.sessions.revoke_all(user.id, None).await?;
+
+        let user = self.repo.find_by_id(user.id).await?;
+        let tokens = self.issue_tokens(&user, client).await?;
+
+        tracing::info!("Password changed for user {}", user.id);
+
+        let audit = AuditContext::anonymous(client.clone()).as_actor(user.id);
+        self.audit.record(&audit, AUTH_PASSWORD_CHANGED, Some((TARGET_USER, user.id)), None).await;
+        Ok((UserResponse::from(user), tokens, revoked_sessions))
+    }
+
+    // Email a verification link to a user who has not confirmed their address yet
+    pub async fn send_verification_email(&self, user: &User, mailer: &dyn MailSender) -> Result<(), AppError> {
+        if user.email_verified_at.is_some() {
+            return Ok(());
+        }
+
+        let token = generate_opaque_token();
+        let expiration = AccountConfig::from_env().email_verification_expiration;
+        let expires_at = Utc::now() + Duration::seconds(expiration);
+        self.email_verification_repo
+            .create(user.id, &hash_token(&token), expires_at)
+            .await?;
+
+        let link = MailConfig::from_env().link("verify-email", &token);
+        mailer.send(MailMessage {
+            to: user.email.clone(),
+            subject: "Confirm your email address".to_string(),
+            body: format!(
+                "Hello {},\n\nPlease confirm your email address by opening the link below. It is valid for {} hours.\n\n{}",
+                user.full_name, expiration / 3600, link
+            ),
+        }).await?;
+
+        tracing::info!("Verification email sent to user {}", user.id);
+        Ok(())
+    }
+
+    // Send a fresh verification link; unknown or already verified emails are ignored silently
+    pub async fn resend_verification_email(&self, email: &str, mailer: &dyn MailSender) -> Result<(), AppError> {
+        validate_email(email)?;
+
+        match self.repo.find_by_email(email).await {
+            Ok(user) => self.send_verification_email(&user, mailer).await,
+            Err(AppError::NotFoundError(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Confirm an email address using a single-use verification token
+    pub async fn verify_email(&self, token: &str) -> Result<User, AppError> {
+        let verification = self.email_verification_repo
+            .consume(&hash_token(token))
+            .await?
+            .ok_or_else(|| AppError::ValidationError("Invalid or expired verification token".to_string()))?;
+
+        let user = self.repo.mark_email_verified(verification.user_id).await?;
+
+        tracing::info!("Email verified for user {}", user.id);
+        Ok(user)
+    }
+
+    async fn revoke_reused_family(&self, family_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        tracing::warn!(
+            "Refresh token reuse detected for user {} - revoking token family {}",
+            user_id, family_id
+        );
+        self.end_session(family_id, user_id).await
+    }
+
+    // Revokes the session and its refresh token family (tokens issued before sessions existed have no session row)
+    async fn end_session(&self, family_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+        self.refresh_repo.revoke_family(family_id).await?;
+        self.sessions.end(user_id, Some(family_id), None).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{ImpersonationLogEntry, ImpersonationLogQuery, PageRequest, PaginatedResponse, User};
+use crate::database::user::UserRepository;
+use crate::database::ImpersonationLogRepository;
+use crate::auth_utils::impersonation::ImpersonationConfig;
+use crate::auth_utils::jwt::generate_impersonation_token;
+use crate::auth_utils::permissions::{is_granted, USERS_IMPERSONATE};
+use crate::middleware::ClientInfo;
+use crate::services::PermissionService;
+
+pub struct ImpersonationService {
+    user_repo: UserRepository,
+    log_repo: ImpersonationLogRepository,
+    permission_service: PermissionService,
+}
+
+impl ImpersonationService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            user_repo: UserRepository::new(pool.clone()),
+            log_repo: ImpersonationLogRepository::new(pool.clone()),
+            permission_service: PermissionService::new(pool),
+        }
+    }
+
+    // Issue a time-limited token of `user_id` for the admin; the start is recorded in the log
+    pub async fn start(
+        &self,
+        actor_id: Uuid,
+        actor_session_id: Uuid,
+        user_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(String, DateTime<Utc>, User), AppError> {
+        if actor_id == user_id {
+            return Err(AppError::BadRequest("You cannot impersonate yourself".to_string()));
+        }
+
+        let user = self.user_repo.find_by_id(user_id).await?;
+        if !user.active {
+            return Err(AppError::BadRequest("Inactive users cannot be impersonated".to_string()));
+        }
+
+        // Impersonation must not give access to another admin's privileges
+        let permissions = self.permission_service.permissions_for_role(&user.role).await?;
+        if is_granted(&permissions, USERS_IMPERSONATE) {
+            return Err(AppError::Forbidden("Users who can impersonate cannot be impersonated".to_string()));
+        }
+
+        let expires_at = Utc::now() + Duration::seconds(ImpersonationConfig::from_env().expiration);
+        let token = generate_impersonation_token(&user, actor_id, actor_session_id, expires_at)?;
+
+        self.log_repo
+            .record_start(actor_id, user.id, client.ip.as_deref(), client.user_agent.as_deref())
+            .await?;
+
+        tracing::warn!(
+            target: "security",
+            event = "impersonation_started",
+            actor_id = %actor_id,
+            user_id = %user.id,
+            "Admin started impersonating a user"
+        );
+
+        Ok((token, expires_at, user))
+    }
+
+    pub async fn record_request(
+        &self,
+        actor_id: Uuid,
+        user_id: Uuid,
+        method: &str,
+        path: &str,
+        status_code: u16,
+        client: &ClientInfo,
+    ) -> Result<(), AppError> {
+        self.log_repo
+            .record_request(actor_id, user_id, method, path, i32::from(status_code), client.ip.as_deref())
+            .await
+    }
+
+    pub async fn list(&self, query: &ImpersonationLogQuery) -> Result<PaginatedResponse<ImpersonationLogEntry>, AppError> {
+        let page = PageRequest::new(query.page, query.per_page);
+        let (entries, total) = self.log_repo.find_page(query, page).await?;
+
+        Ok(PaginatedResponse::new(entries, page, total))
+    }
+}
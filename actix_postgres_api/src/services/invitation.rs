@@ -0,0 +1,123 @@
+use chrono::{Duration, Utc};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{AcceptInvitationRequest, CreateUserRequest, Invitation, User};
+use crate::models::role::UserRole;
+use crate::database::user::UserRepository;
+use crate::database::{InvitationRepository, RoleRepository};
+use crate::auth_utils::account::{AccountConfig, SignupPolicy};
+use crate::auth_utils::tokens::{generate_opaque_token, hash_token};
+use crate::auth_utils::{validate_email, validate_role};
+use crate::mail::{MailConfig, MailMessage, MailSender};
+use crate::services::{AuditContext, UserService};
+
+pub struct InvitationService {
+    repo: InvitationRepository,
+    user_repo: UserRepository,
+    role_repo: RoleRepository,
+    user_service: UserService,
+}
+
+impl InvitationService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repo: InvitationRepository::new(pool.clone()),
+            user_repo: UserRepository::new(pool.clone()),
+            role_repo: RoleRepository::new(pool.clone()),
+            user_service: UserService::new(pool),
+        }
+    }
+
+    // Role of a new invitation; the default is client
+    pub fn requested_role(role: Option<&str>) -> Result<String, AppError> {
+        match role {
+            Some(role) => validate_role(role),
+            None => Ok(UserRole::Client.to_string()),
+        }
+    }
+
+    // Create an invitation and email the link; earlier pending invitations for the email are revoked
+    pub async fn create(
+        &self,
+        invited_by: Uuid,
+        email: &str,
+        role: &str,
+        mailer: &dyn MailSender,
+    ) -> Result<Invitation, AppError> {
+        validate_email(email)?;
+
+        if !self.role_repo.exists(role).await? {
+            return Err(AppError::ValidationError(format!("Role {} does not exist", role)));
+        }
+
+        match self.user_repo.find_by_email(email).await {
+            Ok(_) => return Err(AppError::BadRequest("An account with this email already exists".to_string())),
+            Err(AppError::NotFoundError(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let token = generate_opaque_token();
+        let expiration = AccountConfig::from_env().invitation_expiration;
+        let expires_at = Utc::now() + Duration::seconds(expiration);
+        let invitation = self.repo
+            .create(email, role, invited_by, &hash_token(&token), expires_at)
+            .await?;
+
+        let link = MailConfig::from_env().link("accept-invitation", &token);
+        mailer.send(MailMessage {
+            to: invitation.email.clone(),
+            subject: "You have been invited".to_string(),
+            body: format!(
+                "Hello,\n\nYou have been invited to create an account with the {} role. Open the link below to accept the invitation. It is valid for {} days.\n\n{}",
+                invitation.role, expiration / 86400, link
+            ),
+        }).await?;
+
+        tracing::info!("Invitation {} for role {} sent by user {}", invitation.id, invitation.role, invited_by);
+        Ok(invitation)
+    }
+
+    pub async fn list(&self, invited_by: Option<Uuid>) -> Result<Vec<Invitation>, AppError> {
+        self.repo.find_all(invited_by).await
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Invitation, AppError> {
+        self.repo.find_by_id(id).await
+    }
+
+    pub async fn revoke(&self, id: Uuid) -> Result<Invitation, AppError> {
+        if !self.repo.revoke(id).await? {
+            return Err(AppError::BadRequest("Invitation has already been accepted or revoked".to_string()));
+        }
+
+        tracing::info!("Invitation {} revoked", id);
+        self.repo.find_by_id(id).await
+    }
+
+    // Create the account with the email and role from the invitation. The invitation link proves
+    // ownership of the address, so the email is marked as verified.
+    pub async fn accept(&self, request: AcceptInvitationRequest, pool: &PgPool, audit: &AuditContext) -> Result<User, AppError> {
+        if AccountConfig::from_env().signup_policy == SignupPolicy::Disabled {
+            return Err(AppError::Forbidden("Registration is disabled".to_string()));
+        }
+
+        let invitation = self.repo
+            .find_pending_by_token_hash(&hash_token(&request.token))
+            .await?
+            .ok_or_else(|| AppError::ValidationError("Invalid or expired invitation".to_string()))?;
+
+        let user = self.user_service.create_invited_user(CreateUserRequest {
+            username: request.username,
+            email: invitation.email.clone(),
+            password: request.password,
+            full_name: request.full_name,
+            phone_number: request.phone_number,
+            role: Some(invitation.role.clone()),
+        }, invitation.id, pool, audit).await?;
+
+        tracing::info!("Invitation {} accepted by user {}", invitation.id, user.id);
+        Ok(user)
+    }
+}
@@ -2,8 +2,24 @@
 pub mod user;
 pub mod auth;
 pub mod appointment;
+pub mod two_factor;
+pub mod permission;
+pub mod invitation;
+pub mod api_key;
+pub mod session;
+pub mod impersonation;
+pub mod audit;
+pub mod privacy;
 
 // Re-export all services for easier imports
 pub use user::UserService;
 pub use auth::AuthService;
-pub use appointment::AppointmentService;
\ No newline at end of file
+pub use appointment::AppointmentService;
+pub use two_factor::TwoFactorService;
+pub use permission::PermissionService;
+pub use invitation::InvitationService;
+pub use api_key::ApiKeyService;
+pub use session::SessionService;
+pub use impersonation::ImpersonationService;
+pub use audit::{AuditContext, AuditService};
+pub use privacy::PrivacyService;
\ No newline at end of file
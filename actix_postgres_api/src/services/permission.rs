@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use sqlx::postgres::PgPool;
+
+use crate::error::AppError;
+use crate::auth_utils::permissions::ROLES_MANAGE;
+use crate::auth_utils::validate_role;
+use crate::database::RoleRepository;
+use crate::models::role::{CreateRoleRequest, Permission, Role, RoleResponse, UpdateRoleRequest, UserRole};
+
+// Efektywne uprawnienia ról (z dziedziczeniem), odświeżane po PERMISSION_CACHE_TTL_SECONDS
+// albo natychmiast po zmianie wprowadzonej przez administratora
+lazy_static! {
+    static ref PERMISSION_CACHE: RwLock<Option<CachedPermissions>> = RwLock::new(None);
+}
+
+struct CachedPermissions {
+    roles: HashMap<String, Arc<HashSet<String>>>,
+    loaded_at: Instant,
+}
+
+fn cache_ttl() -> Duration {
+    Duration::from_secs(
+        env::var("PERMISSION_CACHE_TTL_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()
+            .unwrap_or(60),
+    )
+}
+
+pub struct PermissionService {
+    repo: RoleRepository,
+}
+
+impl PermissionService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repo: RoleRepository::new(pool),
+        }
+    }
+
+    // Effective permissions of a role; an unknown role has none
+    pub async fn permissions_for_role(&self, role: &str) -> Result<Arc<HashSet<String>>, AppError> {
+        Ok(self.effective_permissions().await?
+            .get(role)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn effective_permissions(&self) -> Result<HashMap<String, Arc<HashSet<String>>>, AppError> {
+        {
+            let cache = PERMISSION_CACHE.read()
+                .map_err(|_| AppError::InternalServerError("Permission cache poisoned".to_string()))?;
+            if let Some(cached) = cache.as_ref() {
+                if cached.loaded_at.elapsed() < cache_ttl() {
+                    return Ok(cached.roles.clone());
+                }
+            }
+        }
+
+        let roles = self.repo.find_all().await?;
+        let grants = self.repo.find_all_grants().await?;
+        let resolved = resolve_permissions(&roles, &grants);
+
+        *PERMISSION_CACHE.write()
+            .map_err(|_| AppError::InternalServerError("Permission cache poisoned".to_string()))? = Some(CachedPermissions {
+            roles: resolved.clone(),
+            loaded_at: Instant::now(),
+        });
+
+        Ok(resolved)
+    }
+
+    fn invalidate_cache() {
+        if let Ok(mut cache) = PERMISSION_CACHE.write() {
+            *cache = None;
+        }
+    }
+
+    pub async fn list_roles(&self) -> Result<Vec<RoleResponse>, AppError> {
+        let roles = self.repo.find_all().await?;
+        let grants = self.repo.find_all_grants().await?;
+        let effective = resolve_permissions(&roles, &grants);
+
+        Ok(roles
+            .into_iter()
+            .map(|role| role_response(role, &grants, &effective))
+            .collect())
+    }
+
+    pub async fn get_role(&self, name: &str) -> Result<RoleResponse, AppError> {
+        self.list_roles().await?
+            .into_iter()
+            .find(|role| role.name == name)
+            .ok_or_else(|| AppError::NotFound(format!("Role {} not found", name)))
+    }
+
+    pub async fn create_role(&self, request: CreateRoleRequest) -> Result<RoleResponse, AppError> {
+        let name = validate_role(&request.name)?;
+        let parent_role = self.validate_parent(&name, request.parent_role.as_deref()).await?;
+
+        let role = self.repo
+            .create(&name, request.description.as_deref(), parent_role.as_deref())
+            .await?
+            .ok_or_else(|| AppError::BadRequest(format!("Role {} already exists", name)))?;
+
+        Self::invalidate_cache();
+        tracing::info!("Role {} created (parent: {:?})", role.name, role.parent_role);
+
+        self.get_role(&role.name).await
+    }
+
+    pub async fn update_role(&self, name: &str, request: UpdateRoleRequest) -> Result<RoleResponse, AppError> {
+        self.repo.find_by_name(name).await?;
+        let parent_role = self.validate_parent(name, request.parent_role.as_deref()).await?;
+
+        self.repo.update(name, request.description.as_deref(), parent_role.as_deref()).await?;
+
+        Self::invalidate_cache();
+        tracing::info!("Role {} updated (parent: {:?})", name, parent_role);
+
+        self.get_role(name).await
+    }
+
+    pub async fn delete_role(&self, name: &str) -> Result<(), AppError> {
+        let role = self.repo.find_by_name(name).await?;
+        if role.is_system {
+            return Err(AppError::BadRequest(format!("System role {} cannot be deleted", name)));
+        }
+
+        self.repo.delete(name).await?;
+
+        Self::invalidate_cache();
+        tracing::info!("Role {} deleted", name);
+        Ok(())
+    }
+
+    pub async fn list_permissions(&self) -> Result<Vec<Permission>, AppError> {
+        self.repo.find_all_permissions().await
+    }
+
+    pub async fn grant_permission(&self, role: &str, permission: &str) -> Result<RoleResponse, AppError> {
+        self.repo.find_by_name(role).await?;
+        self.ensure_permission_exists(permission).await?;
+
+        if self.repo.grant(role, permission).await? {
+            Self::invalidate_cache();
+            tracing::info!("Permission {} granted to role {}", permission, role);
+        }
+
+        self.get_role(role).await
+    }
+
+    pub async fn revoke_permission(&self, role: &str, permission: &str) -> Result<RoleResponse, AppError> {
+        // Bez tego uprawnienia nikt nie mógłby już zarządzać rolami
+        if role == UserRole::Admin.to_string() && permission == ROLES_MANAGE {
+            return Err(AppError::BadRequest(format!("The admin role must keep the {} permission", ROLES_MANAGE)));
+        }
+
+        self.repo.find_by_name(role).await?;
+
+        if !self.repo.revoke(role, permission).await? {
+            return Err(AppError::NotFound(format!("Role {} does not have the permission {}", role, permission)));
+        }
+
+        Self::invalidate_cache();
+        tracing::info!("Permission {} revoked from role {}", permission, role);
+
+        self.get_role(role).await
+    }
+
+    async fn ensure_permission_exists(&self, permission: &str) -> Result<(), AppError> {
+        let exists = self.repo
+            .find_all_permissions()
+            .await?
+            .iter()
+            .any(|p| p.name == permission);
+
+        if !exists {
+            return Err(AppError::NotFound(format!("Permission {} not found", permission)));
+        }
+
+        Ok(())
+    }
+
+    // The parent must exist and must not be the role itself or one of its descendants
+    async fn validate_parent(&self, name: &str, parent_role: Option<&str>) -> Result<Option<String>, AppError> {
+        let Some(parent_role) = parent_role else {
+            return Ok(None);
+        };
+
+        let parent_role = validate_role(parent_role)?;
+        let roles = self.repo.find_all().await?;
+        let parents: HashMap<&str, Option<&str>> = roles
+            .iter()
+            .map(|role| (role.name.as_str(), role.parent_role.as_deref()))
+            .collect();
+
+        if !parents.contains_key(parent_role.as_str()) {
+            return Err(AppError::BadRequest(format!("Parent role {} does not exist", parent_role)));
+        }
+
+        let mut current = Some(parent_role.as_str());
+        let mut visited = HashSet::new();
+        while let Some(role) = current {
+            if role == name {
+                return Err(AppError::BadRequest("Role hierarchy cannot contain cycles".to_string()));
+            }
+            if !visited.insert(role) {
+                break;
+            }
+            current = parents.get(role).copied().flatten();
+        }
+
+        Ok(Some(parent_role))
+    }
+}
+
+// Uprawnienia roli to jej własne uprawnienia oraz uprawnienia wszystkich ról nadrzędnych
+fn resolve_permissions(roles: &[Role], grants: &[(String, String)]) -> HashMap<String, Arc<HashSet<String>>> {
+    let parents: HashMap<&str, Option<&str>> = roles
+        .iter()
+        .map(|role| (role.name.as_str(), role.parent_role.as_deref()))
+        .collect();
+
+    let mut direct: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (role, permission) in grants {
+        direct.entry(role.as_str()).or_default().push(permission.as_str());
+    }
+
+    roles
+        .iter()
+        .map(|role| {
+            let mut permissions = HashSet::new();
+            let mut visited = HashSet::new();
+            let mut current = Some(role.name.as_str());
+
+            // `visited` guards against cycles left behind by manual edits in the database
+            while let Some(name) = current {
+                if !visited.insert(name) {
+                    break;
+                }
+                if let Some(granted) = direct.get(name) {
+                    permissions.extend(granted.iter().map(|p| p.to_string()));
+                }
+                current = parents.get(name).copied().flatten();
+            }
+
+            (role.name.clone(), Arc::new(permissions))
+        })
+        .collect()
+}
+
+fn role_response(
+    role: Role,
+    grants: &[(String, String)],
+    effective: &HashMap<String, Arc<HashSet<String>>>,
+) -> RoleResponse {
+    let permissions: Vec<String> = grants
+        .iter()
+        .filter(|(name, _)| *name == role.name)
+        .map(|(_, permission)| permission.clone())
+        .collect();
+
+    let mut effective_permissions: Vec<String> = effective
+        .get(&role.name)
+        .map(|set| set.iter().cloned().collect())
+        .unwrap_or_default();
+    effective_permissions.sort();
+
+    RoleResponse {
+        name: role.name,
+        description: role.description,
+        parent_role: role.parent_role,
+        is_system: role.is_system,
+        permissions,
+        effective_permissions,
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::{Cursor, Write};
+
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::error::AppError;
+use crate::models::{User, UserDataExport, UserResponse};
+use crate::models::audit::{TARGET_USER, USER_DATA_EXPORTED, USER_ERASED, USER_ERASURE_CANCELLED, USER_ERASURE_REQUESTED};
+use crate::auth_utils::account::AccountConfig;
+use crate::database::{AppointmentRepository, ChatRepository, UserRepository};
+use crate::services::audit::{AuditContext, AuditService};
+use crate::services::SessionService;
+
+// Eksport danych osobowych i prawo do usunięcia danych (RODO)
+pub struct PrivacyService {
+    pool: PgPool,
+    users: UserRepository,
+    appointments: AppointmentRepository,
+    sessions: SessionService,
+    audit: AuditService,
+}
+
+impl PrivacyService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            users: UserRepository::new(pool.clone()),
+            appointments: AppointmentRepository::new(pool.clone()),
+            sessions: SessionService::new(pool.clone()),
+            audit: AuditService::new(pool.clone()),
+            pool,
+        }
+    }
+
+    pub async fn export(&self, user_id: Uuid, audit: &AuditContext) -> Result<UserDataExport, AppError> {
+        let user = self.users.find_by_id(user_id).await?;
+        let appointments = self.appointments.find_by_participant(user_id).await?;
+        let chat_messages = ChatRepository::get_messages_by_sender(&self.pool, user_id)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        self.audit.record(audit, USER_DATA_EXPORTED, Some((TARGET_USER, user_id)), None).await;
+
+        Ok(UserDataExport {
+            exported_at: Utc::now(),
+            profile: UserResponse::from(user),
+            appointments,
+            chat_messages,
+        })
+    }
+
+    // Schedules the anonymization after ERASURE_GRACE_PERIOD; a pending request keeps its original date
+    pub async fn request_erasure(&self, user_id: Uuid, audit: &AuditContext) -> Result<User, AppError> {
+        let user = self.users.find_by_id(user_id).await?;
+        if user.erased_at.is_some() {
+            return Err(AppError::NotFoundError(format!("User with id {} not found", user_id)));
+        }
+
+        if user.erasure_scheduled_at.is_some() {
+            return Ok(user);
+        }
+
+        let scheduled_at = Utc::now() + Duration::seconds(AccountConfig::from_env().erasure_grace_period);
+        let user = self.users.schedule_erasure(user_id, scheduled_at).await?;
+
+        self.audit
+            .record(audit, USER_ERASURE_REQUESTED, Some((TARGET_USER, user_id)), Some(serde_json::json!({ "scheduled_at": scheduled_at })))
+            .await;
+        Ok(user)
+    }
+
+    pub async fn cancel_erasure(&self, user_id: Uuid, audit: &AuditContext) -> Result<User, AppError> {
+        let user = self.users.find_by_id(user_id).await?;
+        if user.erasure_scheduled_at.is_none() {
+            return Err(AppError::NotFoundError(format!("No pending erasure request for user {}", user_id)));
+        }
+
+        let user = self.users.cancel_erasure(user_id).await?;
+
+        self.audit.record(audit, USER_ERASURE_CANCELLED, Some((TARGET_USER, user_id)), None).await;
+        Ok(user)
+    }
+
+    // Anonymizes the account and signs out all its devices; returns the revoked session ids
+    pub async fn erase(&self, user_id: Uuid, audit: &AuditContext) -> Result<Vec<Uuid>, AppError> {
+        let revoked_sessions = self.sessions.revoke_all(user_id, None).await?;
+        self.users.anonymize(user_id).await?;
+
+        // Bez zmian w zdarzeniu - dziennik audytu nie może przechowywać usuniętych danych
+        self.audit.record(audit, USER_ERASED, Some((TARGET_USER, user_id)), None).await;
+        Ok(revoked_sessions)
+    }
+
+    // Erases every account whose grace period has ended; run periodically in the background
+    pub async fn erase_due(&self) -> Result<Vec<Uuid>, AppError> {
+        let mut revoked_sessions = Vec::new();
+
+        for user_id in self.users.find_due_erasures().await? {
+            match self.erase(user_id, &AuditContext::default()).await {
+                Ok(revoked) => revoked_sessions.extend(revoked),
+                Err(e) => tracing::error!("Failed to erase user {}: {}", user_id, e),
+            }
+        }
+
+        Ok(revoked_sessions)
+    }
+}
+
+// Archiwum ZIP z osobnym plikiem JSON dla każdej części eksportu
+pub fn export_to_zip(export: &UserDataExport) -> Result<Vec<u8>, AppError> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    write_zip_entry(&mut zip, "profile.json", &export.profile, options)?;
+    write_zip_entry(&mut zip, "appointments.json", &export.appointments, options)?;
+    write_zip_entry(&mut zip, "chat_messages.json", &export.chat_messages, options)?;
+    write_zip_entry(&mut zip, "export.json", &serde_json::json!({ "exported_at": export.exported_at }), options)?;
+
+    let cursor = zip
+        .finish()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build export archive: {}", e)))?;
+
+    Ok(cursor.into_inner())
+}
+
+fn write_zip_entry<T: Serialize>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    name: &str,
+    value: &T,
+    options: SimpleFileOptions,
+) -> Result<(), AppError> {
+    let json = serde_json::to_vec_pretty(value)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to serialize {}: {}", name, e)))?;
+
+    zip.start_file(name, options)
+        .and_then(|_| zip.write_all(&json).map_err(Into::into))
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build export archive: {}", e)))
+}
@@ -0,0 +1,88 @@
+use chrono::{Duration, Utc};
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::Session;
+use crate::database::SessionRepository;
+use crate::auth_utils::jwt::JwtConfig;
+use crate::auth_utils::sessions;
+use crate::middleware::ClientInfo;
+
+// Activity is recorded at most once a minute per session, not on every request
+const ACTIVITY_UPDATE_INTERVAL: i64 = 60;
+
+pub struct SessionService {
+    repo: SessionRepository,
+}
+
+impl SessionService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repo: SessionRepository::new(pool),
+        }
+    }
+
+    // Start a session for a successful login; it lasts as long as its refresh tokens
+    pub async fn start(&self, user_id: Uuid, client: &ClientInfo) -> Result<Session, AppError> {
+        let expires_at = Utc::now() + Duration::seconds(JwtConfig::from_env().refresh_expiration());
+
+        self.repo
+            .create(user_id, client.user_agent.as_deref(), client.ip.as_deref(), expires_at)
+            .await
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Session>, AppError> {
+        self.repo.find_active_by_user(user_id).await
+    }
+
+    // Checks that the session of an access token is still active and records the activity
+    pub async fn touch(&self, session_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        let stale_before = Utc::now() - Duration::seconds(ACTIVITY_UPDATE_INTERVAL);
+        let active = self.repo.touch(session_id, user_id, stale_before).await?;
+
+        // Revoked by another instance - remember it so chat connections are refused here as well
+        if !active {
+            sessions::mark_revoked(&[session_id]);
+        }
+
+        Ok(active)
+    }
+
+    // Keep an active session alive after its refresh token was rotated
+    pub async fn extend(&self, session_id: Uuid) -> Result<bool, AppError> {
+        let expires_at = Utc::now() + Duration::seconds(JwtConfig::from_env().refresh_expiration());
+        self.repo.extend(session_id, expires_at).await
+    }
+
+    // Sign out one device of the user
+    pub async fn revoke(&self, user_id: Uuid, session_id: Uuid) -> Result<(), AppError> {
+        if self.end(user_id, Some(session_id), None).await?.is_empty() {
+            return Err(AppError::NotFound(format!("Session with ID {} not found", session_id)));
+        }
+
+        Ok(())
+    }
+
+    // Sign out all devices of the user, optionally except one; returns the revoked session ids
+    pub async fn revoke_all(&self, user_id: Uuid, except: Option<Uuid>) -> Result<Vec<Uuid>, AppError> {
+        self.end(user_id, None, except).await
+    }
+
+    // Revokes sessions and their refresh tokens; access tokens of the sessions are rejected from now on
+    pub async fn end(
+        &self,
+        user_id: Uuid,
+        session_id: Option<Uuid>,
+        except: Option<Uuid>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        let revoked = self.repo.revoke(user_id, session_id, except).await?;
+
+        if !revoked.is_empty() {
+            sessions::mark_revoked(&revoked);
+            tracing::info!("Revoked {} session(s) of user {}", revoked.len(), user_id);
+        }
+
+        Ok(revoked)
+    }
+}
@@ -0,0 +1,113 @@
+use sqlx::postgres::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{TwoFactorEnrollResponse, UserTotp};
+use crate::database::user::UserRepository;
+use crate::database::TwoFactorRepository;
+use crate::auth_utils::totp::{
+    generate_recovery_codes, generate_totp_secret, normalize_recovery_code, provisioning_uri, verify_totp_code,
+};
+use crate::auth_utils::tokens::hash_token;
+use crate::auth_utils::lockout::LockoutConfig;
+
+pub struct TwoFactorService {
+    repo: TwoFactorRepository,
+    user_repo: UserRepository,
+}
+
+impl TwoFactorService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            repo: TwoFactorRepository::new(pool.clone()),
+            user_repo: UserRepository::new(pool),
+        }
+    }
+
+    // Start enrollment: generate a new secret that stays inactive until confirmed with a code
+    pub async fn enroll(&self, user_id: Uuid) -> Result<TwoFactorEnrollResponse, AppError> {
+        // Dostęp do konfiguracji 2FA (two_factor:enroll) sprawdza handler
+        let user = self.user_repo.find_by_id(user_id).await?;
+
+        let secret = generate_totp_secret();
+        self.repo
+            .upsert_pending(user.id, &secret)
+            .await?
+            .ok_or_else(|| AppError::BadRequest("Two-factor authentication is already enabled".to_string()))?;
+
+        let otpauth_uri = provisioning_uri(&secret, &user.email)?;
+
+        Ok(TwoFactorEnrollResponse {
+            secret,
+            otpauth_uri,
+            message: "Scan the QR code with an authenticator app and confirm with a generated code".to_string(),
+        })
+    }
+
+    // Finish enrollment with the first valid code; returns recovery codes in plain text (shown once)
+    pub async fn confirm(&self, user_id: Uuid, code: &str) -> Result<Vec<String>, AppError> {
+        let totp = match self.repo.find_by_user(user_id).await? {
+            Some(totp) if totp.enabled_at.is_none() => totp,
+            Some(_) => return Err(AppError::BadRequest("Two-factor authentication is already enabled".to_string())),
+            None => return Err(AppError::BadRequest("Two-factor enrollment has not been started".to_string())),
+        };
+
+        let step = verify_totp_code(&totp.secret, code, totp.last_used_step)?
+            .ok_or_else(|| AppError::ValidationError("Invalid two-factor code".to_string()))?;
+
+        let recovery_codes = generate_recovery_codes();
+        let hashes: Vec<String> = recovery_codes
+            .iter()
+            .map(|code| hash_token(&normalize_recovery_code(code)))
+            .collect();
+
+        self.repo.enable(user_id, step, &hashes).await?;
+
+        tracing::info!("Two-factor authentication enabled for user {}", user_id);
+        Ok(recovery_codes)
+    }
+
+    // Turn 2FA off; requires a current TOTP code or a recovery code
+    pub async fn disable(&self, user_id: Uuid, code: &str) -> Result<(), AppError> {
+        let totp = self.find_enabled(user_id).await?
+            .ok_or_else(|| AppError::BadRequest("Two-factor authentication is not enabled".to_string()))?;
+
+        // Wrong codes count towards the account lockout, like at login
+        let user = self.user_repo.find_by_id(user_id).await?;
+        UserRepository::ensure_not_locked(&user)?;
+
+        if !self.verify_code(&totp, code).await? {
+            tracing::warn!("Invalid two-factor code when disabling 2FA for user {}", user_id);
+            self.user_repo.register_failed_login(user_id, &LockoutConfig::from_env()).await?;
+            return Err(AppError::ValidationError("Invalid two-factor code".to_string()));
+        }
+
+        self.repo.disable(user_id).await?;
+
+        tracing::info!("Two-factor authentication disabled for user {}", user_id);
+        Ok(())
+    }
+
+    // Returns the confirmed TOTP settings of the user, if 2FA is on
+    pub async fn find_enabled(&self, user_id: Uuid) -> Result<Option<UserTotp>, AppError> {
+        Ok(self.repo
+            .find_by_user(user_id)
+            .await?
+            .filter(|totp| totp.enabled_at.is_some()))
+    }
+
+    // Accepts either a TOTP code (each time step only once) or an unused recovery code
+    pub async fn verify_code(&self, totp: &UserTotp, code: &str) -> Result<bool, AppError> {
+        if let Some(step) = verify_totp_code(&totp.secret, code, totp.last_used_step)? {
+            return self.repo.mark_step_used(totp.user_id, step).await;
+        }
+
+        let code_hash = hash_token(&normalize_recovery_code(code));
+        let used = self.repo.consume_recovery_code(totp.user_id, &code_hash).await?;
+        if used {
+            tracing::warn!("Two-factor recovery code used by user {}", totp.user_id);
+        }
+
+        Ok(used)
+    }
+}
@@ -1,24 +1,79 @@
+use chrono::{Duration, Utc};
 use sqlx::postgres::PgPool;
 use uuid::Uuid as UuidTrait;
 
 use crate::error::AppError;
-use crate::models::{CreateUserRequest, UpdateUserRequest, User};
+use crate::models::{CreateUserRequest, PageRequest, PaginatedResponse, RegisterRequest, UpdateUserRequest, User, UserCursor, UserListQuery};
+use crate::models::pagination::{decode_cursor, encode_cursor};
+use crate::models::audit::{TARGET_USER, USER_CREATED, USER_DELETED, USER_PURGED, USER_REGISTERED, USER_RESTORED, USER_ROLE_CHANGED, USER_UPDATED};
+use crate::auth_utils::account::AccountConfig;
 use crate::database::user::UserRepository;
-use crate::auth_utils::{validate_password, validate_email, validate_phone_number, validate_username, validate_full_name, validate_role};
+use crate::database::{RefreshTokenRepository, RoleRepository};
+use crate::services::audit::{diff, AuditContext, AuditService};
+use crate::services::SessionService;
+use crate::middleware::IfMatch;
+use crate::auth_utils::{validate_password, PasswordContext, validate_email, validate_phone_number, validate_username, validate_full_name, validate_role};
 
 pub struct UserService {
     repo: UserRepository,
+    role_repo: RoleRepository,
+    refresh_repo: RefreshTokenRepository,
+    sessions: SessionService,
+    audit: AuditService,
 }
 
 impl UserService {
     pub fn new(pool: PgPool) -> Self {
         Self {
-            repo: UserRepository::new(pool),
+            repo: UserRepository::new(pool.clone()),
+            role_repo: RoleRepository::new(pool.clone()),
+            refresh_repo: RefreshTokenRepository::new(pool.clone()),
+            sessions: SessionService::new(pool.clone()),
+            audit: AuditService::new(pool),
         }
     }
 
-    pub async fn get_all_users(&self) -> Result<Vec<User>, AppError> {
-        self.repo.find_all().await
+    // Stronicowana lista użytkowników - z kursorem (`cursor`) albo numerem strony (`page`)
+    pub async fn list_users(&self, mut query: UserListQuery) -> Result<PaginatedResponse<User>, AppError> {
+        let page = PageRequest::new(query.page, query.per_page);
+
+        if let Some(ref role) = query.role {
+            query.role = Some(validate_role(role)?);
+        }
+
+        let cursor = match query.cursor.as_deref() {
+            Some(cursor) => {
+                let cursor: UserCursor = decode_cursor(cursor)?;
+                if cursor.sort != query.sort || cursor.order != query.order {
+                    return Err(AppError::BadRequest("Cursor does not match the requested sort order".to_string()));
+                }
+                if cursor.filters != query.filter_hash() {
+                    return Err(AppError::BadRequest("Cursor does not match the requested filters".to_string()));
+                }
+                Some(cursor)
+            }
+            None => None,
+        };
+
+        let (mut users, total) = self.repo.find_page(&query, page, cursor.as_ref()).await?;
+
+        // The repository fetches one row more than requested to tell whether there is a next page
+        let has_more = users.len() as i64 > page.per_page;
+        users.truncate(page.per_page as usize);
+
+        let next_cursor = users.last().filter(|_| has_more).map(|last| {
+            encode_cursor(&UserCursor {
+                sort: query.sort,
+                order: query.order,
+                filters: query.filter_hash(),
+                value: query.sort.value_of(last),
+                id: last.id,
+            })
+        });
+
+        // A page number is reported only for pages requested by number
+        let page_number = cursor.is_none().then_some(page);
+        Ok(PaginatedResponse::with_cursor(users, page_number, page.per_page, total, next_cursor))
     }
 
     pub async fn get_user_by_id(&self, id_str: &str) -> Result<User, AppError> {
@@ -28,7 +83,36 @@ impl UserService {
         self.repo.find_by_id(user_id).await
     }
 
-    pub async fn create_user(&self, user: CreateUserRequest, pool: &PgPool) -> Result<User, AppError> {
+    // Account created by an admin or from an invitation
+    pub async fn create_user(&self, user: CreateUserRequest, pool: &PgPool, audit: &AuditContext) -> Result<User, AppError> {
+        let created = self.insert_user(user, pool).await?;
+
+        self.audit.record(audit, USER_CREATED, Some((TARGET_USER, created.id)), diff(None, Some(&created))).await;
+        Ok(created)
+    }
+
+    // Account created from an invitation; the invitation is marked as accepted together with it
+    pub async fn create_invited_user(
+        &self,
+        user: CreateUserRequest,
+        invitation_id: UuidTrait,
+        pool: &PgPool,
+        audit: &AuditContext,
+    ) -> Result<User, AppError> {
+        let user = self.validate_new_user(user, pool).await?;
+        let created = self.repo.create_from_invitation(user, invitation_id).await?;
+
+        self.audit.record(audit, USER_CREATED, Some((TARGET_USER, created.id)), diff(None, Some(&created))).await;
+        Ok(created)
+    }
+
+    async fn insert_user(&self, user: CreateUserRequest, pool: &PgPool) -> Result<User, AppError> {
+        let user = self.validate_new_user(user, pool).await?;
+        self.repo.create(user).await
+    }
+
+    // Checks a new account and returns it with the normalized role
+    async fn validate_new_user(&self, user: CreateUserRequest, pool: &PgPool) -> Result<CreateUserRequest, AppError> {
         // Walidacja nazwy użytkownika
         validate_username(&user.username)?;
         
@@ -38,8 +122,8 @@ impl UserService {
         // Walidacja pełnego imienia i nazwiska
         validate_full_name(&user.full_name)?;
         
-        // Walidacja hasła
-        validate_password(&user.password)?;
+        // Walidacja hasła (polityka haseł, w tym dane konta)
+        validate_password(&user.password, &PasswordContext::new(&user.username, &user.email, &user.full_name))?;
         
         // Validate phone number if provided
         if let Some(ref phone) = user.phone_number {
@@ -48,7 +132,7 @@ impl UserService {
         
         // Walidacja roli, jeśli podano
         let role = match &user.role {
-            Some(role) => Some(validate_role(role)?),
+            Some(role) => Some(self.validate_existing_role(role).await?),
             None => None
         };
         
@@ -82,14 +166,52 @@ impl UserService {
             user_data.role = Some(validated_role);
         }
         
-        self.repo.create(user_data).await
+        Ok(user_data)
     }
 
-    pub async fn update_user(&self, id_str: &str, user: UpdateUserRequest, pool: &PgPool) -> Result<User, AppError> {
+    // Public signup, subject to SIGNUP_POLICY; the role is always the default one
+    pub async fn register(&self, request: RegisterRequest, pool: &PgPool, audit: &AuditContext) -> Result<User, AppError> {
+        AccountConfig::from_env().ensure_signup_open()?;
+
+        let created = self.insert_user(request.into(), pool).await?;
+
+        self.audit
+            .record(&audit.as_actor(created.id), USER_REGISTERED, Some((TARGET_USER, created.id)), diff(None, Some(&created)))
+            .await;
+        Ok(created)
+    }
+
+    // Returns the updated user and the sessions revoked by a password change
+    pub async fn update_user(
+        &self,
+        id_str: &str,
+        user: UpdateUserRequest,
+        pool: &PgPool,
+        audit: &AuditContext,
+        if_match: &IfMatch,
+    ) -> Result<(User, Vec<UuidTrait>), AppError> {
         let user_id = UuidTrait::parse_str(id_str)
             .map_err(|_| AppError::ValidationError("Invalid UUID format".to_string()))?;
+        let current = self.repo.find_by_id(user_id).await?;
+
+        // Sprawdzane od razu, także gdy żądanie niczego nie zmienia
+        if !if_match.matches(&current.updated_at) {
+            return Err(AppError::PreconditionFailed(format!("User with id {} has been modified by another request", user_id)));
+        }
+
+        // Walidacja roli najpierw, bo normalizuje jej nazwę przed porównaniem z bieżącą
+        let mut user = user;
+        if let Some(ref role) = user.role {
+            user.role = Some(self.validate_existing_role(role).await?);
+        }
+
+        // Pola bez zmian są pomijane - nic do zapisania oznacza brak aktualizacji
+        let user = user.retain_changes(&current);
+        if user.is_empty() {
+            return Ok((current, Vec::new()));
+        }
         
-        // Walidacja danych wejściowych
+        // Walidacja zmienianych pól
         if let Some(ref email) = user.email {
             validate_email(email)?;
             
@@ -132,36 +254,89 @@ impl UserService {
             validate_full_name(full_name)?;
         }
         
-        // Walidacja hasła, jeśli jest aktualizowane
+        // Walidacja hasła, jeśli jest aktualizowane - wobec danych konta po tej zmianie
         if let Some(ref password) = user.password {
-            validate_password(password)?;
+            let context = PasswordContext::new(
+                user.username.as_deref().unwrap_or(&current.username),
+                user.email.as_deref().unwrap_or(&current.email),
+                user.full_name.as_deref().unwrap_or(&current.full_name),
+            );
+            validate_password(password, &context)?;
         }
         
-        // Validate phone number if provided
-        if let Some(ref phone) = user.phone_number {
+        // Clearing the phone number (null) needs no validation
+        if let Some(Some(ref phone)) = user.phone_number {
             validate_phone_number(phone)?;
         }
         
-        // Walidacja roli, jeśli jest aktualizowana
-        let mut user_data = user;
-        if let Some(ref role) = user_data.role {
-            user_data.role = Some(validate_role(role)?); 
+        let password_changed = user.password.is_some();
+        let updated = self.repo.update(user_id, user, if_match.versions()).await?;
+
+        // Sessions started with the old password must not survive the change
+        let revoked_sessions = if password_changed {
+            self.refresh_repo.revoke_all_for_user(user_id).await?;
+            self.sessions.revoke_all(user_id, None).await?
+        } else {
+            Vec::new()
+        };
+
+        // Role changes get their own action, so they are easy to find in the log
+        let action = if updated.role != current.role { USER_ROLE_CHANGED } else { USER_UPDATED };
+        if let Some(changes) = diff(Some(&current), Some(&updated)) {
+            self.audit.record(audit, action, Some((TARGET_USER, user_id)), Some(changes)).await;
         }
-        
-        self.repo.update(user_id, user_data).await
+
+        Ok((updated, revoked_sessions))
     }
 
-    pub async fn delete_user(&self, id_str: &str) -> Result<(), AppError> {
+    // Role must be well-formed and defined in the roles table
+    async fn validate_existing_role(&self, role: &str) -> Result<String, AppError> {
+        let role = validate_role(role)?;
+
+        if !self.role_repo.exists(&role).await? {
+            return Err(AppError::ValidationError(format!("Role {} does not exist", role)));
+        }
+
+        Ok(role)
+    }
+
+    // Soft delete that also signs the user out everywhere; returns the revoked session ids
+    pub async fn delete_user(&self, id_str: &str, audit: &AuditContext, if_match: &IfMatch) -> Result<Vec<UuidTrait>, AppError> {
         let user_id = UuidTrait::parse_str(id_str)
             .map_err(|_| AppError::ValidationError("Invalid UUID format".to_string()))?;
+        let existing = self.repo.find_by_id(user_id).await?;
         
-        self.repo.delete(user_id).await
+        self.repo.delete(user_id, if_match.versions()).await?;
+        let revoked_sessions = self.sessions.revoke_all(user_id, None).await?;
+
+        self.audit.record(audit, USER_DELETED, Some((TARGET_USER, user_id)), diff(Some(&existing), None)).await;
+        Ok(revoked_sessions)
     }
 
-    pub async fn get_users_by_role(&self, role: &str) -> Result<Vec<User>, AppError> {
-        self.repo.find_by_role(role).await
+    // Przywraca konto usunięte miękko, o ile nie minął jeszcze okres przechowywania
+    pub async fn restore_user(&self, user_id: UuidTrait, audit: &AuditContext) -> Result<User, AppError> {
+        let restored = self.repo.restore(user_id).await?;
+
+        self.audit.record(audit, USER_RESTORED, Some((TARGET_USER, user_id)), None).await;
+        Ok(restored)
     }
-    
+
+    // Anonymizes accounts soft-deleted longer than DELETED_USER_RETENTION; run periodically in the background
+    pub async fn purge_deleted_users(&self) -> Result<usize, AppError> {
+        let deleted_before = Utc::now() - Duration::seconds(AccountConfig::from_env().deleted_user_retention);
+        let purged = self.repo.purge_deleted(deleted_before).await?;
+
+        for user_id in &purged {
+            self.audit.record(&AuditContext::default(), USER_PURGED, Some((TARGET_USER, *user_id)), None).await;
+        }
+
+        if !purged.is_empty() {
+            tracing::info!("Purged {} deleted user(s)", purged.len());
+        }
+
+        Ok(purged.len())
+    }
+
     // Dodatkowe metody pomocnicze
     pub async fn count_users_by_role(&self) -> Result<Vec<(String, i64)>, AppError> {
         self.repo.count_users_by_role().await